@@ -1,8 +1,10 @@
 use bitvec::bitvec;
 use cgmath::Point2;
+use instant::{Duration, Instant};
 use itertools::Itertools;
 use key_names::KeyMappingCode;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::f32::consts::{PI, TAU};
 use std::fmt;
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::Path;
@@ -10,11 +12,21 @@ use std::path::PathBuf;
 use winit::event::{ElementState, ModifiersState, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 
-use crate::commands::{Command, PuzzleCommand, PuzzleMouseCommand};
+use crate::commands::{Command, PuzzleCommand, PuzzleDragCommand, PuzzleMouseCommand};
 use crate::logfile::LogFileFormat;
-use crate::preferences::{Key, Keybind, PieceFilter, Preferences, Preset};
+use crate::preferences::{
+    ConfirmMode, DragAxis, Key, Keybind, PieceFilter, Preferences, Preset, RotationMode,
+    ViewPreferences,
+};
 use crate::puzzle::*;
 use crate::render::{GraphicsState, PuzzleRenderCache};
+use crate::timer::SolveTimer;
+
+/// Minimum number of times per second that a held twist key repeats, to avoid
+/// dividing by zero.
+const MIN_KEY_REPEAT_RATE: f32 = 0.1;
+/// Maximum number of twists in a hint.
+const HINT_MAX_DEPTH: usize = 2;
 
 #[cfg(target_arch = "wasm32")]
 macro_rules! unsupported_on_web {
@@ -39,9 +51,16 @@ pub struct App {
     pub(crate) puzzle_texture_size: (u32, u32),
     force_redraw: bool,
 
+    /// Name of the graphics adapter in use.
+    pub(crate) adapter_name: String,
+    /// Names of all graphics adapters that could be used.
+    pub(crate) available_adapters: Vec<String>,
+
     /// Mouse cursor position relative to the puzzle texture. Each axis ranges
     /// from -1.0 to +1.0.
     pub(crate) cursor_pos: Option<Point2<f32>>,
+    /// Box selection to apply the next time the puzzle is drawn.
+    pub(crate) pending_box_select: Option<BoxSelect>,
 
     /// Set of pressed keys.
     pressed_keys: HashSet<Key>,
@@ -51,11 +70,19 @@ pub struct App {
     pressed_modifiers: ModifiersState,
     /// Set of modifiers toggled on using buttons in the UI.
     toggled_modifiers: ModifiersState,
+    /// Held key whose twist command is being repeated.
+    key_repeat: Option<KeyRepeat>,
+
+    /// Axis that the current mouse drag is locked to while holding `Shift`.
+    drag_axis_lock: Option<DragAxis>,
 
-    /// Grips that are tied to a held key.
-    transient_grips: HashMap<Key, Grip>,
-    /// Grip that is more permanent.
-    pub(crate) toggle_grip: Grip,
+    /// Speedsolving timer.
+    pub(crate) timer: SolveTimer,
+
+    /// Time of the most recent user input, used to start idle spinning.
+    last_input_time: Instant,
+    /// Time of the most recent call to `frame()`.
+    last_frame_time: Instant,
 
     status_msg: String,
 }
@@ -71,15 +98,24 @@ impl App {
             puzzle_texture_size: (0, 0),
             force_redraw: true,
 
+            adapter_name: String::new(),
+            available_adapters: vec![],
+
             cursor_pos: None,
+            pending_box_select: None,
 
             pressed_keys: HashSet::default(),
             toggled_keys: HashSet::default(),
             pressed_modifiers: ModifiersState::default(),
             toggled_modifiers: ModifiersState::default(),
+            key_repeat: None,
+
+            drag_axis_lock: None,
 
-            transient_grips: HashMap::default(),
-            toggle_grip: Grip::default(),
+            timer: SolveTimer::default(),
+
+            last_input_time: Instant::now(),
+            last_frame_time: Instant::now(),
 
             status_msg: String::default(),
         };
@@ -151,7 +187,7 @@ impl App {
                 Command::Open => {
                     unsupported_on_web! {
                         self;
-                        if self.confirm_discard_changes("open another file") {
+                        if self.confirm_discard_before_load("open another file") {
                             if let Some(path) = file_dialog().pick_file() {
                                 self.try_load_puzzle(path);
                             }
@@ -168,6 +204,8 @@ impl App {
                     }
                 }
                 Command::SaveAs => unsupported_on_web! { self; self.try_save_puzzle_as() },
+                Command::ExportObj => unsupported_on_web! { self; self.try_export_obj(false) },
+                Command::ExportObjAll => unsupported_on_web! { self; self.try_export_obj(true) },
 
                 Command::Exit => {
                     unsupported_on_web! {
@@ -189,14 +227,17 @@ impl App {
                     self.puzzle.redo()?;
                 }
                 Command::Reset => {
-                    if self.confirm_discard_changes("reset puzzle") {
-                        self.puzzle.reset();
+                    let mode = self.prefs.interaction.confirm_reset;
+                    if self.confirm_discard_changes_with(mode, "reset puzzle") {
+                        self.reset_puzzle();
+                        self.timer.cancel();
                     }
                 }
 
                 Command::ScrambleN(n) => {
                     if self.confirm_discard_changes("scramble") {
                         self.puzzle.scramble_n(n)?;
+                        self.timer.cancel();
                         self.set_status_ok(format!(
                             "Scrambled with {} random {}",
                             n,
@@ -207,13 +248,15 @@ impl App {
                 Command::ScrambleFull => {
                     if self.confirm_discard_changes("scramble") {
                         self.puzzle.scramble_full()?;
+                        self.timer.start_inspection();
                         self.set_status_ok("Scrambled fully");
                     }
                 }
 
                 Command::NewPuzzle(puzzle_type) => {
-                    if self.confirm_discard_changes("reset puzzle") {
-                        self.puzzle = PuzzleController::new(puzzle_type);
+                    if self.confirm_discard_before_load("reset puzzle") {
+                        self.set_puzzle(PuzzleController::new(puzzle_type));
+                        self.timer.cancel();
                         self.set_status_ok(format!("Loaded {}", puzzle_type));
                     }
                 }
@@ -226,6 +269,11 @@ impl App {
                     self.prefs.needs_save = true;
                     self.request_redraw_puzzle();
                 }
+                Command::SnapCamera => {
+                    let ty = self.puzzle.ty();
+                    self.puzzle.snap_camera_to_symmetry(self.prefs.view_mut(ty));
+                    self.prefs.needs_save = true;
+                }
 
                 Command::None => (),
             },
@@ -242,9 +290,11 @@ impl App {
                 });
                 if let Some(bind) = matching_mousebind {
                     match bind.command {
-                        PuzzleMouseCommand::TwistCw => self.click_twist(|tw| tw.cw)?,
-                        PuzzleMouseCommand::TwistCcw => self.click_twist(|tw| tw.ccw)?,
-                        PuzzleMouseCommand::Recenter => self.click_twist(|tw| tw.recenter)?,
+                        PuzzleMouseCommand::TwistCw => self.click_twist(|tw| tw.cw, true)?,
+                        PuzzleMouseCommand::TwistCcw => self.click_twist(|tw| tw.ccw, true)?,
+                        PuzzleMouseCommand::Recenter => {
+                            self.click_twist(|tw| tw.recenter, false)?
+                        }
                         PuzzleMouseCommand::SelectPiece => {
                             if let Some(sticker) = self.puzzle.hovered_sticker() {
                                 self.puzzle.toggle_select(sticker);
@@ -256,18 +306,95 @@ impl App {
                     }
                 }
             }
-            AppEvent::Drag(delta) => {
-                let delta = delta * self.prefs.interaction.drag_sensitivity * 360.0;
+            AppEvent::Drag { delta, cursor } => {
+                let raw_delta = delta;
+                let mut delta = delta;
+                // Holding shift locks the drag to whichever axis it starts
+                // moving along.
+                let axis = match self.prefs.interaction.constrain_drag_to_axis {
+                    Some(axis) => Some(axis),
+                    None if self.pressed_modifiers().shift() => {
+                        if self.drag_axis_lock.is_none() {
+                            self.drag_axis_lock = DragAxis::dominant(delta);
+                        }
+                        self.drag_axis_lock
+                    }
+                    None => None,
+                };
+                if let Some(axis) = axis {
+                    delta = axis.constrain(delta);
+                }
                 self.puzzle.freeze_view_angle_offset();
-                self.puzzle
-                    .add_view_angle_offset([delta.x, delta.y], self.prefs.view(self.puzzle.ty()));
+                self.puzzle.set_drag_axis(axis);
+                let view_prefs = self.prefs.view(self.puzzle.ty());
+                match self.prefs.interaction.rotation_mode {
+                    _ if axis == Some(DragAxis::Roll) => {
+                        // Roll by the angle that the cursor swept around the
+                        // center of the view. Y points down on screen, so
+                        // negate it.
+                        let from = cursor - raw_delta;
+                        let swept = (cursor.angle() - from.angle() + PI).rem_euclid(TAU) - PI;
+                        self.puzzle
+                            .add_view_angle_roll(-swept.to_degrees(), view_prefs);
+                    }
+                    RotationMode::Turntable => {
+                        let delta = delta * self.prefs.interaction.drag_sensitivity * 360.0;
+                        self.puzzle
+                            .add_view_angle_offset([delta.x, delta.y], view_prefs);
+                    }
+                    RotationMode::Trackball => {
+                        let from = cursor - raw_delta;
+                        let to = from + delta;
+                        self.puzzle.add_trackball_rotation(
+                            [from.x, from.y],
+                            [to.x, to.y],
+                            view_prefs,
+                        );
+                    }
+                }
+            }
+            AppEvent::Pan(delta) if delta != egui::Vec2::ZERO => {
+                let view_prefs = self.prefs.view_mut(self.puzzle.ty());
+                // At a scale of 1, the smaller dimension of the viewport spans
+                // two units of puzzle geometry.
+                view_prefs.pan[0] += delta.x * 2.0 / view_prefs.scale;
+                view_prefs.pan[1] -= delta.y * 2.0 / view_prefs.scale;
+                self.prefs.needs_save = true;
+                self.request_redraw_puzzle();
+            }
+            AppEvent::Pan(_) => (),
+            AppEvent::RecolorSelection(color) => {
+                self.puzzle.recolor_selection(color);
+                self.request_redraw_puzzle();
+            }
+            AppEvent::BoxSelect(box_select) => {
+                self.pending_box_select = Some(box_select);
+                self.request_redraw_puzzle();
             }
             AppEvent::DragReleased => {
+                self.drag_axis_lock = None;
                 if self.prefs.interaction.realign_on_release {
                     self.puzzle.unfreeze_view_angle_offset();
                 }
             }
 
+            AppEvent::LoadStickerPattern(pattern) => self.try_load_sticker_pattern(&pattern),
+            AppEvent::Hint => self.show_hint(),
+            AppEvent::ScrambleWeighted {
+                moves,
+                axis_weights,
+            } => {
+                if self.confirm_discard_changes("scramble") {
+                    self.puzzle.scramble_n_weighted(moves, &axis_weights)?;
+                    self.timer.cancel();
+                    self.set_status_ok(format!(
+                        "Scrambled with {} weighted random {}",
+                        moves,
+                        if moves == 1 { "move" } else { "moves" }
+                    ));
+                }
+            }
+
             AppEvent::StatusError(msg) => return Err(msg),
         }
 
@@ -284,7 +411,7 @@ impl App {
                 let _ = path;
                 unsupported_on_web! {
                     self;
-                    if self.confirm_discard_changes("open another file") {
+                    if self.confirm_discard_before_load("open another file") {
                         self.try_load_puzzle(path.to_owned());
                     }
                 }
@@ -306,14 +433,17 @@ impl App {
                 // right modifiers are both pressed at once (at least in my
                 // testing on Windows 11) so clean that up here just in case.
                 let mods = self.pressed_modifiers();
-                self.remove_held_grips(|k| {
-                    // If the grip requires a modifier and that modifier is not
-                    // pressed, then remove the grip.
-                    k.is_shift() && !mods.shift()
-                        || k.is_ctrl() && !mods.ctrl()
-                        || k.is_alt() && !mods.alt()
-                        || k.is_logo() && !mods.logo()
-                });
+                self.puzzle.release_held_grips(
+                    |k| {
+                        // If the grip requires a modifier and that modifier is
+                        // not pressed, then remove the grip.
+                        k.is_shift() && !mods.shift()
+                            || k.is_ctrl() && !mods.ctrl()
+                            || k.is_alt() && !mods.alt()
+                            || k.is_logo() && !mods.logo()
+                    },
+                    &self.prefs.interaction,
+                );
             }
 
             WindowEvent::KeyboardInput { input, .. } => {
@@ -353,14 +483,29 @@ impl App {
         }
     }
 
+    /// Twists the puzzle based on the hovered sticker. If `directional` is
+    /// `true` and opposite twist directions are not mirrored, then twists on
+    /// the second axis of each pair of opposite axes are reversed, so that the
+    /// same click on opposite faces turns the puzzle the same way.
     fn click_twist(
         &mut self,
         get_twist: fn(ClickTwists) -> Option<Twist>,
+        directional: bool,
     ) -> Result<(), &'static str> {
         if self.puzzle.current_twist().is_none() {
             if let Some(twists) = self.puzzle.hovered_twists() {
                 if let Some(mut t) = get_twist(twists) {
                     t.layers = self.gripped_layers(t.layers);
+                    let is_second_of_pair = self
+                        .puzzle
+                        .opposite_twist_axis(t.axis)
+                        .map_or(false, |opposite| opposite.0 < t.axis.0);
+                    if directional
+                        && is_second_of_pair
+                        && !self.prefs.interaction.mirror_opposite_twist_direction
+                    {
+                        t = self.puzzle.reverse_twist(t);
+                    }
                     self.puzzle.twist(t)?;
                 }
             }
@@ -409,7 +554,8 @@ impl App {
                     new_grip.layers = Some(layers.to_layer_mask(self.puzzle.layer_count()))
                         .filter(|&l| l != LayerMask(0));
 
-                    self.transient_grips.insert(key, new_grip);
+                    self.puzzle
+                        .set_held_grip(key, new_grip, &self.prefs.interaction);
 
                     success = true;
                 }
@@ -429,6 +575,15 @@ impl App {
                             Ok(()) => {
                                 done_twist_command = true;
                                 success = true;
+
+                                if self.prefs.interaction.twist_key_repeat {
+                                    let delay = self.prefs.interaction.key_repeat_delay;
+                                    self.key_repeat = Some(KeyRepeat {
+                                        sc,
+                                        vk,
+                                        next: Instant::now() + Duration::from_secs_f32(delay),
+                                    });
+                                }
                             }
                             Err(e) => grip_error = Some(e),
                         }
@@ -551,7 +706,8 @@ impl App {
                     return; // Do not try to match other keybinds.
                 }
                 PuzzleCommand::ViewPreset { view_preset_name } => {
-                    let presets = match self.puzzle.ty().projection_type() {
+                    let ty = self.puzzle.ty();
+                    let presets = match ty.projection_type() {
                         ProjectionType::_3D => &mut self.prefs.view_3d,
                         ProjectionType::_4D => &mut self.prefs.view_4d,
                     };
@@ -560,7 +716,10 @@ impl App {
                         .iter()
                         .find(|p| &p.preset_name == view_preset_name)
                     {
-                        let old = std::mem::replace(&mut presets.current, preset.value.clone());
+                        let defaults =
+                            ViewPreferences::default_for_ndim(ty.projection_type().ndim());
+                        let new = presets.resolve_preset(&preset.value, &defaults);
+                        let old = std::mem::replace(&mut presets.current, new);
                         self.puzzle.animate_from_view_settings(old);
                         presets.active_preset = Some(preset.clone());
                         self.prefs.needs_save = true;
@@ -593,7 +752,65 @@ impl App {
     }
     fn handle_key_release(&mut self, sc: Option<KeyMappingCode>, vk: Option<VirtualKeyCode>) {
         // Remove grips for this held key.
-        self.remove_held_grips(|k| Some(k) == sc.map(Key::Sc) || Some(k) == vk.map(Key::Vk));
+        self.puzzle.release_held_grips(
+            |k| Some(k) == sc.map(Key::Sc) || Some(k) == vk.map(Key::Vk),
+            &self.prefs.interaction,
+        );
+
+        // Stop repeating this key.
+        if let Some(repeat) = &self.key_repeat {
+            if sc.is_some() && repeat.sc == sc || vk.is_some() && repeat.vk == vk {
+                self.key_repeat = None;
+            }
+        }
+    }
+    /// Repeats the twist command of a held key if it is time to do so. The
+    /// keypress is resolved again using the current grip, so releasing or
+    /// pressing a grip key while holding a twist key changes which twist is
+    /// repeated.
+    fn handle_key_repeat(&mut self) {
+        let Some(repeat) = self.key_repeat else {
+            return;
+        };
+        let now = Instant::now();
+        if now < repeat.next {
+            return;
+        }
+        // Wait for the previous twist to finish so that repeated twists don't
+        // pile up in the queue.
+        if self.puzzle.current_twist().is_some() {
+            return;
+        }
+
+        let active_puzzle_keybinds =
+            self.prefs.puzzle_keybinds[self.puzzle.ty()].get_active_keybinds();
+        let twist_done = self
+            .resolve_keypress(active_puzzle_keybinds, repeat.sc, repeat.vk)
+            .into_iter()
+            .any(|bind| match &bind.command {
+                PuzzleCommand::Twist {
+                    axis,
+                    direction,
+                    layers,
+                } => {
+                    let layers = layers.to_layer_mask(self.puzzle.layer_count());
+                    self.do_twist(axis.as_deref(), direction, layers).is_ok()
+                }
+                _ => false,
+            });
+
+        self.key_repeat = twist_done.then(|| {
+            let interval = 1.0
+                / self
+                    .prefs
+                    .interaction
+                    .key_repeat_rate
+                    .max(MIN_KEY_REPEAT_RATE);
+            KeyRepeat {
+                next: now + Duration::from_secs_f32(interval),
+                ..repeat
+            }
+        });
     }
 
     pub(crate) fn resolve_keypress<'a, C>(
@@ -631,7 +848,7 @@ impl App {
         // A modifier is also ignored when matching its own key, hence
         // `.chain(&sc).chain(&vk)`. For example, the shift modifier is ignored
         // when matching the shift key.
-        let ignored_keys = self.transient_grips.keys().chain(&sc).chain(&vk);
+        let ignored_keys = self.puzzle.held_grip_keys().chain(&sc).chain(&vk);
         ignored_keys.fold(
             // Consider all modifiers, but don't distinguish left vs. right.
             ModifiersState::SHIFT
@@ -666,7 +883,8 @@ impl App {
                 .twist_axis_from_name(name)
                 .ok_or_else(|| format!("Unknown twist axis {name:?}"));
         }
-        self.grip().axes.iter().copied().exactly_one().map_err(|e| {
+        let grip = self.puzzle.keyboard_grip();
+        grip.axes.iter().copied().exactly_one().map_err(|e| {
             if e.len() == 0 {
                 "No twist axis gripped".to_string()
             } else {
@@ -680,7 +898,7 @@ impl App {
         if fallback != LayerMask::default() {
             fallback
         } else {
-            self.grip().layers.unwrap_or_default()
+            self.puzzle.keyboard_grip().layers.unwrap_or_default()
         }
     }
 
@@ -709,6 +927,19 @@ impl App {
     pub(crate) fn pressed_modifiers(&self) -> ModifiersState {
         self.pressed_modifiers | self.toggled_modifiers
     }
+    /// Returns what a drag on the puzzle does if it starts with `mouse_button`
+    /// and the current modifiers.
+    pub(crate) fn dragbind_command(&self, mouse_button: egui::PointerButton) -> PuzzleDragCommand {
+        let pressed_modifiers = self.pressed_modifiers();
+        self.prefs
+            .dragbinds
+            .iter()
+            .find(|bind| {
+                egui::PointerButton::from(bind.button) == mouse_button
+                    && pressed_modifiers.contains(bind.mods())
+            })
+            .map_or(PuzzleDragCommand::None, |bind| bind.command)
+    }
     pub(crate) fn toggle_key(&mut self, sc: Option<KeyMappingCode>, vk: Option<VirtualKeyCode>) {
         let maybe_vk = vk.map(Key::Vk);
         let maybe_sc = sc.map(Key::Sc);
@@ -745,10 +976,64 @@ impl App {
     }
 
     pub(crate) fn frame(&mut self) {
-        self.puzzle.set_grip(self.grip(), &self.prefs.interaction);
+        let now = Instant::now();
+        let delta = now - self.last_frame_time;
+        self.last_frame_time = now;
+
+        self.handle_key_repeat();
+        self.update_idle_spin(delta);
+
+        self.puzzle.apply_transient_rotation();
+        self.puzzle
+            .set_max_undo_depth(self.prefs.interaction.max_undo_depth);
+
+        self.timer.update(&self.puzzle);
 
         if self.puzzle.check_just_solved() {
-            self.set_status_ok("Solved!");
+            let twist_count = self.puzzle.twist_count(TwistMetric::Stm);
+            let log =
+                crate::logfile::serialize(&self.puzzle, LogFileFormat::Hsc).unwrap_or_default();
+            match self.timer.stop(twist_count, log) {
+                Some(time) => self.set_status_ok(format!(
+                    "Solved in {}!",
+                    crate::timer::format_duration(time),
+                )),
+                None => self.set_status_ok("Solved!"),
+            }
+
+            if self.prefs.interaction.reset_on_solve {
+                // Leave the timer stopped so that the solve time stays visible.
+                self.reset_puzzle();
+            }
+        }
+    }
+
+    /// Records that the user has just given some input, which stops idle
+    /// spinning.
+    pub(crate) fn notice_input(&mut self) {
+        self.last_input_time = Instant::now();
+    }
+    /// Rotates the puzzle around its vertical axis if idle spinning is enabled
+    /// and there has been no input for long enough.
+    fn update_idle_spin(&mut self, delta: Duration) {
+        let prefs = &self.prefs.interaction;
+        if !prefs.idle_spin_enabled
+            || self.last_input_time.elapsed().as_secs_f32() < prefs.idle_spin_delay
+        {
+            return;
+        }
+        let angle = prefs.idle_spin_speed * delta.as_secs_f32();
+        let view_prefs = self.prefs.view_mut(self.puzzle.ty());
+        view_prefs.yaw = (view_prefs.yaw + angle + 180.0).rem_euclid(360.0) - 180.0;
+    }
+
+    /// Replaces the puzzle, loading the default view preset for the new
+    /// puzzle type if it differs from the old one.
+    fn set_puzzle(&mut self, puzzle: PuzzleController) {
+        let old_ty = self.puzzle.ty();
+        self.puzzle = puzzle;
+        if let Some(old) = self.prefs.switch_puzzle_view(old_ty, self.puzzle.ty()) {
+            self.puzzle.animate_from_view_settings(old);
         }
     }
 
@@ -766,15 +1051,27 @@ impl App {
     }
 
     fn confirm_discard_changes(&mut self, action: &str) -> bool {
-        let mut needs_save = self.puzzle.is_unsaved();
-
-        if self.prefs.interaction.confirm_discard_only_when_scrambled
-            && !self.puzzle.has_been_fully_scrambled()
-        {
-            needs_save = false;
-        }
+        self.confirm_discard_changes_with(ConfirmMode::WhenUnsaved, action)
+    }
+    fn confirm_discard_before_load(&mut self, action: &str) -> bool {
+        let mode = match self.prefs.interaction.confirm_load_when_unsaved {
+            true => ConfirmMode::WhenUnsaved,
+            false => ConfirmMode::Never,
+        };
+        self.confirm_discard_changes_with(mode, action)
+    }
+    fn confirm_discard_changes_with(&mut self, mode: ConfirmMode, action: &str) -> bool {
+        let needs_confirmation = match mode {
+            ConfirmMode::Always => true,
+            ConfirmMode::WhenUnsaved => {
+                self.puzzle.is_unsaved()
+                    && (!self.prefs.interaction.confirm_discard_only_when_scrambled
+                        || self.puzzle.has_been_fully_scrambled())
+            }
+            ConfirmMode::Never => false,
+        };
 
-        let confirm = !needs_save
+        let confirm = !needs_confirmation
             || rfd::MessageDialog::new()
                 .set_title("Unsaved changes")
                 .set_description(&format!("Discard puzzle state and {}?", action))
@@ -787,11 +1084,20 @@ impl App {
         confirm
     }
 
+    /// Resets the puzzle and forgets its log file, so that saving afterward
+    /// doesn't overwrite the log of the previous solve.
+    fn reset_puzzle(&mut self) {
+        self.puzzle.reset();
+        self.prefs.log_file = None;
+        self.prefs.needs_save = true;
+    }
+
     fn try_paste_puzzle(&mut self, log_file_contents: &str) {
         match crate::logfile::deserialize(log_file_contents) {
             Ok((puzzle, warnings)) => {
                 if self.confirm_load_puzzle(&warnings) {
-                    self.puzzle = puzzle;
+                    self.set_puzzle(puzzle);
+                    self.timer.cancel();
 
                     self.set_status_ok("Loaded puzzle log file from clipboard");
 
@@ -804,6 +1110,37 @@ impl App {
             }
         }
     }
+    fn try_load_sticker_pattern(&mut self, pattern: &str) {
+        match PuzzleController::from_sticker_pattern(self.puzzle.ty(), pattern) {
+            Ok(puzzle) => {
+                if self.confirm_discard_before_load("load a sticker pattern") {
+                    let legality = puzzle.latest().check_legal();
+                    self.set_puzzle(puzzle);
+                    self.timer.cancel();
+
+                    match legality {
+                        Ok(()) => self.set_status_ok("Loaded sticker pattern"),
+                        Err(reason) => self.set_status_err(format!(
+                            "Loaded sticker pattern, but it cannot be solved: {reason}"
+                        )),
+                    }
+                }
+            }
+            Err(e) => {
+                self.set_status_err(format!("Unable to load sticker pattern: {e}"));
+            }
+        }
+    }
+    fn show_hint(&mut self) {
+        match self.puzzle.latest().simple_hint(HINT_MAX_DEPTH) {
+            Some(twists) => {
+                let notation = self.puzzle.notation_scheme();
+                let twists = twists.iter().map(|&t| notation.twist_to_string(t));
+                self.set_status_ok(format!("Hint: {}", twists.format(" ")));
+            }
+            None => self.set_status_err(format!("No hint found within {HINT_MAX_DEPTH} twists")),
+        }
+    }
     fn try_copy_puzzle(&mut self, format: LogFileFormat, response: &mut AppEventResponse) {
         let ext = format.extension();
         match crate::logfile::serialize(&self.puzzle, format) {
@@ -823,7 +1160,8 @@ impl App {
         match crate::logfile::load_file(&path) {
             Ok((puzzle, warnings)) => {
                 if self.confirm_load_puzzle(&warnings) {
-                    self.puzzle = puzzle;
+                    self.set_puzzle(puzzle);
+                    self.timer.cancel();
 
                     self.set_status_ok(format!("Loaded log file from {}", path.display()));
 
@@ -857,6 +1195,28 @@ impl App {
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn try_export_obj(&mut self, include_hidden: bool) {
+        let dialog = rfd::FileDialog::new().add_filter("Wavefront OBJ", &["obj"]);
+        if let Some(obj_path) = dialog.save_file() {
+            let mtl_path = obj_path.with_extension("mtl");
+            let mtl_file_name = mtl_path.file_name().unwrap_or_default().to_string_lossy();
+
+            let export = export_obj(
+                &mut self.puzzle,
+                &self.prefs,
+                &mtl_file_name,
+                include_hidden,
+            );
+            match std::fs::write(&obj_path, export.obj)
+                .and_then(|()| std::fs::write(&mtl_path, export.mtl))
+            {
+                Ok(()) => self.set_status_ok(format!("Exported OBJ to {}", obj_path.display())),
+                Err(e) => show_error_dialog("Unable to export OBJ", e),
+            }
+        }
+    }
+
     #[cfg(target_arch = "wasm32")]
     const LOCAL_STORAGE_KEY: &str = "hyperspeedcube_puzzle_log";
     #[cfg(target_arch = "wasm32")]
@@ -900,21 +1260,6 @@ impl App {
         self.status_msg = format!("Error: {}", msg)
     }
 
-    pub(crate) fn grip(&self) -> Grip {
-        let mut ret = self
-            .transient_grips
-            .values()
-            .fold(Grip::default(), |a, b| a | b);
-        ret.axes.extend(&self.toggle_grip.axes);
-        if ret.layers.is_none() {
-            ret.layers = self.toggle_grip.layers;
-        }
-        ret
-    }
-    fn remove_held_grips(&mut self, mut remove_if: impl FnMut(Key) -> bool) {
-        self.transient_grips.retain(|&k, _v| !remove_if(k));
-    }
-
     #[cfg(target_arch = "wasm32")]
     pub(crate) fn modifiers(&self) -> ModifiersState {
         self.pressed_modifiers
@@ -929,9 +1274,33 @@ pub(crate) enum AppEvent {
 
     Click(egui::PointerButton),
     /// Drag event with a per-frame delta, sent every frame until the drag ends
-    /// (even if the delta is zero).
-    Drag(egui::Vec2),
+    /// (even if the delta is zero). Both the delta and the cursor position
+    /// (relative to the center of the puzzle view) are in units of the smaller
+    /// dimension of the puzzle view.
+    Drag {
+        delta: egui::Vec2,
+        cursor: egui::Vec2,
+    },
     DragReleased,
+    /// Move the puzzle by a delta in units of the smaller dimension of the
+    /// puzzle view.
+    Pan(egui::Vec2),
+    /// Select or deselect all pieces in a rectangle.
+    BoxSelect(BoxSelect),
+    /// Draw the selected stickers with a color, or with their own colors if
+    /// `None`.
+    RecolorSelection(Option<Face>),
+
+    /// Replace the puzzle with one loaded from a sticker pattern.
+    LoadStickerPattern(String),
+    /// Show a short sequence of twists that places another piece.
+    Hint,
+    /// Scramble some number of moves, choosing each twist axis with
+    /// probability proportional to its weight.
+    ScrambleWeighted {
+        moves: usize,
+        axis_weights: Vec<f32>,
+    },
 
     StatusError(String),
 
@@ -949,6 +1318,27 @@ impl From<Twist> for AppEvent {
     }
 }
 
+/// Held key whose twist command repeats.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct KeyRepeat {
+    sc: Option<KeyMappingCode>,
+    vk: Option<VirtualKeyCode>,
+    /// Time at which to repeat the twist command next.
+    next: Instant,
+}
+
+/// Rectangle in which to select or deselect pieces.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct BoxSelect {
+    /// One corner of the rectangle, in the same coordinates as
+    /// `App::cursor_pos`.
+    pub start: Point2<f32>,
+    /// The opposite corner of the rectangle.
+    pub end: Point2<f32>,
+    /// Whether to select the pieces (`true`) or deselect them (`false`).
+    pub select: bool,
+}
+
 #[derive(Debug, Default, Clone)]
 #[must_use]
 pub(crate) struct AppEventResponse {
@@ -19,6 +19,8 @@ pub enum Command {
     Open,
     Save,
     SaveAs,
+    ExportObj,
+    ExportObjAll,
     Exit,
 
     // File menu (web)
@@ -39,6 +41,7 @@ pub enum Command {
     NewPuzzle(PuzzleTypeEnum),
 
     ToggleBlindfold,
+    SnapCamera,
 
     #[default]
     #[serde(other)]
@@ -50,6 +53,8 @@ impl Command {
             Command::Open => "🗁".to_owned(),
             Command::Save => "💾".to_owned(),
             Command::SaveAs => "Save As".to_owned(),
+            Command::ExportObj => "Export OBJ".to_owned(),
+            Command::ExportObjAll => "Export OBJ (all pieces)".to_owned(),
             Command::Exit => "Exit".to_owned(),
 
             Command::CopyHscLog => "🗐".to_owned(),
@@ -66,6 +71,7 @@ impl Command {
             Command::NewPuzzle(ty) => format!("New {}", ty.name()),
 
             Command::ToggleBlindfold => "BLD".to_owned(),
+            Command::SnapCamera => "Snap camera".to_owned(),
 
             Command::None => String::new(),
         }
@@ -85,6 +91,19 @@ pub enum PuzzleMouseCommand {
     None,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PuzzleDragCommand {
+    RotateCamera,
+    PanCamera,
+    BoxSelect,
+    BoxDeselect,
+
+    #[default]
+    #[serde(other)]
+    None,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum PuzzleCommand {
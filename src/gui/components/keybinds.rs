@@ -47,7 +47,8 @@ impl egui::Widget for KeybindSetsList<'_> {
                 puzzle_keybinds.active = new_preset.preset_name.clone();
             });
             ui.separator();
-            presets_ui.show_list(ui, |ui, _idx, set| {
+            let active_set_name = puzzle_keybinds.active.clone();
+            presets_ui.show_list_with_active_preset(ui, Some(&active_set_name), |ui, _idx, set| {
                 let mut changed = false;
 
                 let mut r = ui.with_layout(
@@ -243,6 +244,8 @@ impl egui::Widget for CommandSelectWidget<'_, GlobalKeybindsAccessor> {
                     "Open..." => Cmd::Open,
                     "Save" => Cmd::Save,
                     "Save as..." => Cmd::SaveAs,
+                    "Export OBJ..." => Cmd::ExportObj,
+                    "Export OBJ (all pieces)..." => Cmd::ExportObjAll,
                     "Exit" => Cmd::Exit,
 
                     "Copy .hsc" => Cmd::CopyHscLog,
@@ -256,6 +259,7 @@ impl egui::Widget for CommandSelectWidget<'_, GlobalKeybindsAccessor> {
                     "Scramble partially" => Cmd::ScrambleN(PARTIAL_SCRAMBLE_MOVE_COUNT_MIN),
                     "Scramble fully" => Cmd::ScrambleFull,
                     "Toggle blindfold" => Cmd::ToggleBlindfold,
+                    "Snap camera to face" => Cmd::SnapCamera,
                     "New puzzle" => Cmd::NewPuzzle(PuzzleTypeEnum::default()),
                 }
             );
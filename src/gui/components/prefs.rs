@@ -1,10 +1,17 @@
 use egui::NumExt;
+use itertools::Itertools;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
 
 use crate::app::App;
 use crate::gui::components::{with_reset_button, PresetsUi, WidgetWithReset};
 use crate::gui::ext::*;
 use crate::gui::util::Access;
-use crate::preferences::{OpacityPreferences, DEFAULT_PREFS};
+use crate::preferences::{
+    ColorMode, ConfirmMode, DragAxis, GfxBackend, GfxPowerPreference, OpacityPreferences,
+    OutlineColorMode, PartialViewPreferences, RotationMode, ViewPreferences, DEFAULT_PREFS,
+};
 use crate::puzzle::{traits::*, Face, ProjectionType};
 use crate::serde_impl::hex_color;
 
@@ -54,10 +61,11 @@ impl<T> PrefsUi<'_, T> {
         })
     }
 
-    pub fn num<N: egui::emath::Numeric + ToString>(
+    pub fn num<N: egui::emath::Numeric + ToString + FromStr>(
         &mut self,
         label: &str,
         access: Access<T, N>,
+        clamp_range: RangeInclusive<N>,
         modify_widget: impl FnOnce(egui::DragValue) -> egui::DragValue,
     ) -> egui::Response {
         let reset_value = *(access.get_ref)(self.defaults);
@@ -67,7 +75,13 @@ impl<T> PrefsUi<'_, T> {
             value: (access.get_mut)(current),
             reset_value,
             reset_value_str,
-            make_widget: |value| modify_widget(egui::DragValue::new(value)),
+            make_widget: |value| {
+                |ui: &mut egui::Ui| {
+                    precise_drag_value(ui, value, clamp_range, |value| {
+                        modify_widget(egui::DragValue::new(value))
+                    })
+                }
+            },
         })
     }
 
@@ -80,16 +94,19 @@ impl<T> PrefsUi<'_, T> {
             reset_value,
             reset_value_str,
             make_widget: |value| {
-                egui::DragValue::from_get_set(|new_value| {
-                    if let Some(x) = new_value {
-                        *value = x as f32 / 100.0;
+                |ui: &mut egui::Ui| {
+                    let mut percent = *value * 100.0;
+                    let r = precise_drag_value(ui, &mut percent, 0.0..=100.0, |percent| {
+                        egui::DragValue::new(percent)
+                            .suffix("%")
+                            .fixed_decimals(0)
+                            .speed(0.5)
+                    });
+                    if r.changed() {
+                        *value = percent / 100.0;
                     }
-                    *value as f64 * 100.0
-                })
-                .suffix("%")
-                .fixed_decimals(0)
-                .clamp_range(0.0..=100.0_f32)
-                .speed(0.5)
+                    r
+                }
             },
         })
     }
@@ -98,6 +115,7 @@ impl<T> PrefsUi<'_, T> {
         &mut self,
         label: &str,
         access: Access<T, f32>,
+        clamp_range: RangeInclusive<f32>,
         modify_widget: impl FnOnce(egui::DragValue) -> egui::DragValue,
     ) -> egui::Response {
         let reset_value = *(access.get_ref)(self.defaults);
@@ -108,7 +126,11 @@ impl<T> PrefsUi<'_, T> {
             reset_value,
             reset_value_str,
             make_widget: |value| {
-                modify_widget(egui::DragValue::new(value).suffix("°").fixed_decimals(0))
+                |ui: &mut egui::Ui| {
+                    precise_drag_value(ui, value, clamp_range, |value| {
+                        modify_widget(egui::DragValue::new(value).suffix("°").fixed_decimals(0))
+                    })
+                }
             },
         })
     }
@@ -126,11 +148,75 @@ impl<T> PrefsUi<'_, T> {
     }
 }
 
+/// Adds a `DragValue` that can also be right-clicked to type in a value at full
+/// precision, since the drag value only shows as many decimals as are useful
+/// while dragging. Typed values are clamped to the same range as the drag value.
+fn precise_drag_value<N: egui::emath::Numeric + ToString + FromStr>(
+    ui: &mut egui::Ui,
+    value: &mut N,
+    clamp_range: RangeInclusive<N>,
+    make_drag_value: impl FnOnce(&mut N) -> egui::DragValue<'_>,
+) -> egui::Response {
+    let (min, max) = (clamp_range.start().to_f64(), clamp_range.end().to_f64());
+    let mut r = ui.add(make_drag_value(value).clamp_range(min..=max));
+    let popup_id = r.id.with("precise_input");
+    let just_opened = r.secondary_clicked();
+    if just_opened {
+        ui.data().insert_temp(popup_id, value.to_string());
+        ui.memory().open_popup(popup_id);
+    }
+
+    let mut changed = false;
+    egui::popup_below_widget(ui, popup_id, &r, |ui| {
+        let mut text: String = ui.data().get_temp(popup_id).unwrap_or_default();
+        let text_resp = ui.add(egui::TextEdit::singleline(&mut text).desired_width(120.0));
+        if just_opened {
+            text_resp.request_focus();
+        }
+        if text_resp.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+            if let Ok(new_value) = text.trim().parse::<N>() {
+                *value = N::from_f64(new_value.to_f64().clamp(min, max));
+                changed = true;
+            }
+            ui.memory().close_popup();
+        }
+        ui.data().insert_temp(popup_id, text);
+    });
+    if changed {
+        r.mark_changed();
+    }
+    r.on_hover_text("Right-click to enter a precise value")
+}
+
 pub fn build_colors_section(ui: &mut egui::Ui, app: &mut App) {
     let puzzle_type = app.puzzle.ty();
     let prefs = &mut app.prefs;
 
     let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.strong("Faces");
+        let modified_faces = prefs
+            .colors
+            .modified_faces(&DEFAULT_PREFS.colors, puzzle_type);
+        if !modified_faces.is_empty() {
+            let r = ui.small_button("Reset modified").on_hover_text(format!(
+                "Modified: {}",
+                modified_faces
+                    .iter()
+                    .map(|&face| puzzle_type.info(face).name)
+                    .join(", "),
+            ));
+            if r.clicked() {
+                for face in modified_faces {
+                    prefs
+                        .colors
+                        .reset_face(&DEFAULT_PREFS.colors, puzzle_type, face);
+                }
+                changed = true;
+            }
+        }
+    });
     let mut prefs_ui = PrefsUi {
         ui,
         current: &mut prefs.colors,
@@ -138,15 +224,31 @@ pub fn build_colors_section(ui: &mut egui::Ui, app: &mut App) {
         changed: &mut changed,
     };
 
-    prefs_ui.ui.strong("Faces");
+    let color_usage = puzzle_type.color_usage();
     for (i, &face) in puzzle_type.faces().iter().enumerate() {
-        prefs_ui.color(face.name, access!([(puzzle_type, Face(i as _))]));
+        prefs_ui
+            .color(face.name, access!([(puzzle_type, Face(i as _))]))
+            .on_hover_text(format!("Used by {} stickers", color_usage[i]));
+    }
+    let unused_colors = puzzle_type.unused_colors();
+    if !unused_colors.is_empty() {
+        prefs_ui.ui.colored_label(
+            egui::Color32::RED,
+            format!(
+                "Not used by any sticker: {}",
+                unused_colors
+                    .iter()
+                    .map(|&face| puzzle_type.info(face).name)
+                    .join(", "),
+            ),
+        );
     }
 
     prefs_ui.ui.separator();
 
     prefs_ui.ui.strong("Special");
     prefs_ui.color("Background", access!(.background));
+    prefs_ui.color("Sticker gap", access!(.sticker_gap));
     prefs_ui.color("Blindfolded stickers", access!(.blind_face));
     prefs_ui.checkbox("Blindfold mode", access!(.blindfold));
 
@@ -155,6 +257,31 @@ pub fn build_colors_section(ui: &mut egui::Ui, app: &mut App) {
         app.request_redraw_puzzle();
     }
 }
+pub fn build_accessibility_section(ui: &mut egui::Ui, app: &mut App) {
+    let prefs = &mut app.prefs;
+
+    let mut changed = false;
+    let mut prefs_ui = PrefsUi {
+        ui,
+        current: &mut prefs.accessibility,
+        defaults: &DEFAULT_PREFS.accessibility,
+        changed: &mut changed,
+    };
+
+    prefs_ui
+        .checkbox("High contrast", access!(.high_contrast))
+        .on_hover_explanation(
+            "",
+            "Thickens outlines, uses high-contrast sticker \
+             colors, and switches to a high-contrast theme. \
+             Other settings are restored when disabled.",
+        );
+
+    prefs.needs_save |= changed;
+    if changed {
+        app.request_redraw_puzzle();
+    }
+}
 pub fn build_graphics_section(ui: &mut egui::Ui, app: &mut App) {
     let prefs = &mut app.prefs;
 
@@ -168,8 +295,8 @@ pub fn build_graphics_section(ui: &mut egui::Ui, app: &mut App) {
 
     let speed = prefs_ui.current.fps_limit as f64 / 1000.0; // logarithmic speed
     prefs_ui
-        .num("FPS limit", access!(.fps_limit), |dv| {
-            dv.fixed_decimals(0).clamp_range(30..=1000).speed(speed)
+        .num("FPS limit", access!(.fps_limit), 30..=1000, |dv| {
+            dv.fixed_decimals(0).speed(speed)
         })
         .on_hover_explanation("Frames Per Second", "Limits framerate to save power");
 
@@ -188,6 +315,59 @@ pub fn build_graphics_section(ui: &mut egui::Ui, app: &mut App) {
             );
     });
 
+    prefs_ui.ui.separator();
+
+    prefs_ui.ui.strong("Adapter");
+    prefs_ui.ui.label(format!("Current: {}", app.adapter_name));
+    let gfx_prefs = &mut *prefs_ui.current;
+    let mut r = egui::ComboBox::from_label("Backend")
+        .selected_text(gfx_prefs.backend.to_string())
+        .show_ui(prefs_ui.ui, |ui| {
+            for backend in GfxBackend::iter() {
+                *prefs_ui.changed |= ui
+                    .selectable_value(&mut gfx_prefs.backend, backend, backend.to_string())
+                    .changed();
+            }
+        })
+        .response;
+    r |= egui::ComboBox::from_label("Prefer")
+        .selected_text(gfx_prefs.power_preference.to_string())
+        .show_ui(prefs_ui.ui, |ui| {
+            for power_preference in GfxPowerPreference::iter() {
+                *prefs_ui.changed |= ui
+                    .selectable_value(
+                        &mut gfx_prefs.power_preference,
+                        power_preference,
+                        power_preference.to_string(),
+                    )
+                    .changed();
+            }
+        })
+        .response;
+    r |= egui::ComboBox::from_label("Adapter")
+        .selected_text(gfx_prefs.adapter.as_deref().unwrap_or("Automatic"))
+        .show_ui(prefs_ui.ui, |ui| {
+            *prefs_ui.changed |= ui
+                .selectable_value(&mut gfx_prefs.adapter, None, "Automatic")
+                .changed();
+            for adapter_name in &app.available_adapters {
+                *prefs_ui.changed |= ui
+                    .selectable_value(
+                        &mut gfx_prefs.adapter,
+                        Some(adapter_name.clone()),
+                        adapter_name,
+                    )
+                    .changed();
+            }
+        })
+        .response;
+    r.on_hover_explanation(
+        "",
+        "Which GPU to render with. If the chosen backend \
+         or adapter isn't available, another one is used \
+         instead. Changes take effect after restarting.",
+    );
+
     prefs.needs_save |= changed;
     if changed {
         app.request_redraw_puzzle();
@@ -216,12 +396,199 @@ pub fn build_interaction_section(ui: &mut egui::Ui, app: &mut App) {
              is only shown when the puzzle has been fully \
              scrambled.",
         );
+    prefs_ui
+        .ui
+        .horizontal(|ui| {
+            let mode = &mut prefs_ui.current.confirm_reset;
+            let mut r = ui.selectable_value(mode, ConfirmMode::Always, "Always");
+            r |= ui.selectable_value(mode, ConfirmMode::WhenUnsaved, "When unsaved");
+            r |= ui.selectable_value(mode, ConfirmMode::Never, "Never");
+            *prefs_ui.changed |= r.changed();
+            ui.label("Confirm reset");
+        })
+        .response
+        .on_hover_explanation(
+            "",
+            "When to show a confirmation dialog before \
+             resetting the puzzle.",
+        );
+    prefs_ui
+        .checkbox(
+            "Confirm loading when unsaved",
+            access!(.confirm_load_when_unsaved),
+        )
+        .on_hover_explanation(
+            "",
+            "When enabled, a confirmation dialog is shown \
+             before opening a file or loading a new puzzle \
+             if the current puzzle has unsaved changes.",
+        );
+    prefs_ui
+        .checkbox("Reset on solve", access!(.reset_on_solve))
+        .on_hover_explanation(
+            "",
+            "When enabled, the puzzle is reset as soon as it \
+             is solved. This is useful for drilling.",
+        );
 
     prefs_ui.ui.separator();
 
-    prefs_ui.num("Drag sensitivity", access!(.drag_sensitivity), |dv| {
-        dv.fixed_decimals(2).clamp_range(0.0..=3.0_f32).speed(0.01)
-    });
+    prefs_ui
+        .checkbox("Show recent moves", access!(.show_recent_moves))
+        .on_hover_explanation(
+            "",
+            "When enabled, the notation for the most recent \
+             moves is shown in the corner of the puzzle view.",
+        );
+    prefs_ui.num(
+        "Recent moves count",
+        access!(.recent_moves_count),
+        1..=50_usize,
+        |dv| dv,
+    );
+    prefs_ui
+        .checkbox("Show sticker labels", access!(.show_sticker_labels))
+        .on_hover_explanation(
+            "",
+            "When enabled, each visible sticker is labeled \
+             with its ID.",
+        );
+    prefs_ui
+        .checkbox("Show hovered sticker info", access!(.show_hover_info))
+        .on_hover_explanation(
+            "",
+            "When enabled, hovering over a sticker shows its \
+             ID, the ID and type of its piece, and its color. \
+             This is useful for debugging puzzle definitions.",
+        );
+
+    prefs_ui.ui.separator();
+
+    prefs_ui
+        .num(
+            "Max undo depth",
+            access!(.max_undo_depth),
+            0..=100_000_usize,
+            |dv| dv,
+        )
+        .on_hover_explanation(
+            "",
+            "Maximum number of twists that can be undone or \
+             redone. Older twists are dropped, and the log \
+             file starts from the puzzle state after them \
+             instead of the scramble. Set to 0 for no limit.",
+        );
+    prefs_ui.ui.label(format!(
+        "History uses about {:.1} KiB",
+        app.puzzle.history_memory_estimate() as f32 / 1024.0,
+    ));
+
+    prefs_ui.ui.separator();
+
+    prefs_ui.num(
+        "Drag sensitivity",
+        access!(.drag_sensitivity),
+        0.0..=3.0_f32,
+        |dv| dv.fixed_decimals(2).speed(0.01),
+    );
+    prefs_ui
+        .ui
+        .horizontal(|ui| {
+            let mode = &mut prefs_ui.current.rotation_mode;
+            let mut r = ui.selectable_value(mode, RotationMode::Turntable, "Turntable");
+            r |= ui.selectable_value(mode, RotationMode::Trackball, "Trackball");
+            *prefs_ui.changed |= r.changed();
+            ui.label("Rotation mode");
+        })
+        .response
+        .on_hover_explanation(
+            "",
+            "Turntable rotates around the puzzle's vertical \
+             axis and the screen's horizontal axis, and never \
+             rolls the puzzle. Trackball rotates the puzzle as \
+             if dragging a point on a sphere around it, which \
+             can roll the puzzle. Drag sensitivity only \
+             affects turntable mode.",
+        );
+    prefs_ui
+        .ui
+        .horizontal(|ui| {
+            let constraint = &mut prefs_ui.current.constrain_drag_to_axis;
+            let mut r = ui.selectable_value(constraint, None, "Free");
+            r |= ui.selectable_value(constraint, Some(DragAxis::Yaw), "Yaw only");
+            r |= ui.selectable_value(constraint, Some(DragAxis::Pitch), "Pitch only");
+            r |= ui.selectable_value(constraint, Some(DragAxis::Roll), "Roll only");
+            *prefs_ui.changed |= r.changed();
+            ui.label("Drag axis");
+        })
+        .response
+        .on_hover_explanation(
+            "",
+            "Constrains mouse dragging to rotate around only \
+             one axis. Roll rotates the puzzle as the cursor \
+             circles the center of the view. When set to \
+             \"Free,\" hold shift while dragging to lock to the \
+             axis of initial movement. Smart realign respects \
+             the constraint.",
+        );
+    prefs_ui
+        .num("Pick radius", access!(.pick_radius), 0.0..=20.0_f32, |dv| {
+            dv.fixed_decimals(0).speed(0.1)
+        })
+        .on_hover_explanation(
+            "",
+            "Number of pixels to search around the cursor \
+             for a sticker when there is no sticker directly \
+             under it. This makes thin stickers easier to \
+             click.",
+        );
+    prefs_ui
+        .checkbox(
+            "Highlight whole piece on hover",
+            access!(.highlight_piece_on_hover),
+        )
+        .on_hover_explanation(
+            "",
+            "When enabled, hovering over a sticker highlights \
+             every sticker on its piece. When disabled, only \
+             the hovered sticker is highlighted.",
+        );
+    prefs_ui
+        .checkbox("Grip on hover", access!(.grip_on_hover))
+        .on_hover_explanation(
+            "",
+            "When enabled, hovering over a sticker grips the \
+             layers that clicking it would twist, so that \
+             ungripped pieces are dimmed. Grip keys take \
+             priority while held.",
+        );
+    prefs_ui
+        .checkbox(
+            "Mirror opposite twist directions",
+            access!(.mirror_opposite_twist_direction),
+        )
+        .on_hover_explanation(
+            "",
+            "When enabled, clicking twists each face relative \
+             to itself, like a physical cube, so the same click \
+             on opposite faces (such as R and L) turns them in \
+             opposite directions. When disabled, twists on the \
+             second face of each pair are reversed so that both \
+             faces turn the same way (such as R and L').",
+        );
+    prefs_ui
+        .num(
+            "Drag threshold",
+            access!(.drag_threshold),
+            0.0..=50.0_f32,
+            |dv| dv.fixed_decimals(0).speed(0.1),
+        )
+        .on_hover_explanation(
+            "",
+            "Number of pixels the mouse must move before \
+             dragging rotates the puzzle. Shorter drags \
+             count as clicks.",
+        );
     prefs_ui
         .checkbox("Realign puzzle on release", access!(.realign_on_release))
         .on_hover_explanation(
@@ -245,8 +612,52 @@ pub fn build_interaction_section(ui: &mut egui::Ui, app: &mut App) {
              adds a full-puzzle rotation to the undo history.",
         );
 
+    prefs_ui
+        .checkbox("Repeat held twist keys", access!(.twist_key_repeat))
+        .on_hover_explanation(
+            "",
+            "When enabled, holding a key bound to a twist \
+             repeats the twist. Grip keys can be pressed or \
+             released while holding it to change which twist \
+             is repeated.",
+        );
+    prefs_ui.num(
+        "Key repeat delay",
+        access!(.key_repeat_delay),
+        0.0..=2.0_f32,
+        |dv| dv.fixed_decimals(2).speed(0.01).suffix(" s"),
+    );
+    prefs_ui.num(
+        "Key repeat rate",
+        access!(.key_repeat_rate),
+        1.0..=30.0_f32,
+        |dv| dv.fixed_decimals(1).speed(0.1).suffix("/s"),
+    );
+
     prefs_ui.ui.separator();
 
+    prefs_ui.collapsing("Idle spin", |mut prefs_ui| {
+        prefs_ui
+            .checkbox("Spin when idle", access!(.idle_spin_enabled))
+            .on_hover_explanation(
+                "",
+                "When enabled, the puzzle slowly rotates after \
+                 a period with no input. Any input stops it.",
+            );
+        prefs_ui.num(
+            "Spin speed",
+            access!(.idle_spin_speed),
+            -360.0..=360.0_f32,
+            |dv| dv.fixed_decimals(1).speed(0.1).suffix("°/s"),
+        );
+        prefs_ui.num(
+            "Spin delay",
+            access!(.idle_spin_delay),
+            0.0..=600.0_f32,
+            |dv| dv.fixed_decimals(0).speed(0.5).suffix(" s"),
+        );
+    });
+
     prefs_ui.collapsing("Animations", |mut prefs_ui| {
         prefs_ui
             .checkbox("Dynamic twist speed", access!(.dynamic_twist_speed))
@@ -256,22 +667,78 @@ pub fn build_interaction_section(ui: &mut egui::Ui, app: &mut App) {
                  many moves are queued up. When all queued \
                  moves are complete, the twist speed resets.",
             );
+        prefs_ui
+            .num(
+                "Speed ramp",
+                access!(.dynamic_twist_ramp),
+                0.0..=2.0_f32,
+                |dv| dv.fixed_decimals(2).speed(0.01),
+            )
+            .on_hover_explanation(
+                "",
+                "How quickly dynamic twist speed increases. Each \
+                 queued move multiplies the twist speed by \
+                 e^ramp, up to the maximum speed multiplier.",
+            );
+        prefs_ui
+            .num(
+                "Max speed multiplier",
+                access!(.dynamic_twist_max_multiplier),
+                1.0..=100.0_f32,
+                |dv| dv.fixed_decimals(1).speed(0.1),
+            )
+            .on_hover_explanation(
+                "",
+                "Maximum factor by which dynamic twist speed \
+                 can exceed the normal twist speed.",
+            );
 
         let speed = prefs_ui.current.twist_duration.at_least(0.1) / 100.0; // logarithmic speed
-        prefs_ui.num("Twist duration", access!(.twist_duration), |dv| {
-            dv.fixed_decimals(2).clamp_range(0.0..=5.0_f32).speed(speed)
-        });
+        prefs_ui.num(
+            "Twist duration",
+            access!(.twist_duration),
+            0.0..=5.0_f32,
+            |dv| dv.fixed_decimals(2).speed(speed),
+        );
 
         let speed = prefs_ui.current.other_anim_duration.at_least(0.1) / 100.0; // logarithmic speed
         prefs_ui
-            .num("Other animations", access!(.other_anim_duration), |dv| {
-                dv.fixed_decimals(2).clamp_range(0.0..=1.0_f32).speed(speed)
-            })
+            .num(
+                "Other animations",
+                access!(.other_anim_duration),
+                0.0..=1.0_f32,
+                |dv| dv.fixed_decimals(2).speed(speed),
+            )
             .on_hover_explanation(
                 "",
                 "Number of seconds for other animations, \
                  such as hiding a piece.",
             );
+
+        prefs_ui
+            .checkbox(
+                "Animate geometry changes",
+                access!(.animate_geometry_changes),
+            )
+            .on_hover_explanation(
+                "",
+                "When enabled, changing the face or sticker \
+                 spacing animates smoothly, the same way as \
+                 loading a view preset.",
+            );
+
+        prefs_ui
+            .checkbox(
+                "Show twist rotation guide",
+                access!(.show_twist_rotation_guide),
+            )
+            .on_hover_explanation(
+                "",
+                "When enabled, a faint line (in 3D) or plane \
+                 (in 4D) is drawn through the center of the \
+                 puzzle while a twist animates, showing what \
+                 the twist rotates around.",
+            );
     });
 
     prefs.needs_save |= changed;
@@ -288,6 +755,22 @@ pub fn build_outlines_section(ui: &mut egui::Ui, app: &mut App) {
     };
 
     prefs_ui.ui.strong("Colors");
+    prefs_ui
+        .ui
+        .horizontal(|ui| {
+            let mode = &mut prefs_ui.current.color_mode;
+            let mut r = ui.selectable_value(mode, OutlineColorMode::Fixed, "Fixed");
+            r |= ui.selectable_value(mode, OutlineColorMode::Sticker, "Sticker");
+            *prefs_ui.changed |= r.changed();
+            ui.label("Default source");
+        })
+        .response
+        .on_hover_explanation(
+            "",
+            "Whether outlines of pieces that are not hovered \
+             or selected use the default color below or a \
+             darker version of each sticker's color.",
+        );
     prefs_ui.color("Default", access!(.default_color));
     prefs_ui.color("Hidden", access!(.hidden_color));
     prefs_ui.color("Hovered", access!(.hovered_color));
@@ -298,16 +781,34 @@ pub fn build_outlines_section(ui: &mut egui::Ui, app: &mut App) {
 
     prefs_ui.ui.strong("Sizes");
 
+    const OUTLINE_SIZE_RANGE: RangeInclusive<f32> = 0.0..=5.0;
     fn outline_size_dv(drag_value: egui::DragValue<'_>) -> egui::DragValue<'_> {
-        drag_value
-            .fixed_decimals(1)
-            .clamp_range(0.0..=5.0_f32)
-            .speed(0.01)
+        drag_value.fixed_decimals(1).speed(0.01)
     }
-    prefs_ui.num("Default", access!(.default_size), outline_size_dv);
-    prefs_ui.num("Hidden", access!(.hidden_size), outline_size_dv);
-    prefs_ui.num("Hovered", access!(.hovered_size), outline_size_dv);
-    prefs_ui.num("Selected", access!(.selected_size), outline_size_dv);
+    prefs_ui.num(
+        "Default",
+        access!(.default_size),
+        OUTLINE_SIZE_RANGE,
+        outline_size_dv,
+    );
+    prefs_ui.num(
+        "Hidden",
+        access!(.hidden_size),
+        OUTLINE_SIZE_RANGE,
+        outline_size_dv,
+    );
+    prefs_ui.num(
+        "Hovered",
+        access!(.hovered_size),
+        OUTLINE_SIZE_RANGE,
+        outline_size_dv,
+    );
+    prefs_ui.num(
+        "Selected",
+        access!(.selected_size),
+        OUTLINE_SIZE_RANGE,
+        outline_size_dv,
+    );
 
     prefs.needs_save |= changed;
     if changed {
@@ -340,10 +841,25 @@ pub fn build_view_section(ui: &mut egui::Ui, app: &mut App) {
     let puzzle_type = app.puzzle.ty();
     let proj_ty = puzzle_type.projection_type();
     let prefs = &mut app.prefs;
-    let presets = prefs.view_presets(&app.puzzle);
+    let animate_geometry_changes = prefs.interaction.animate_geometry_changes;
+    let presets = match proj_ty {
+        ProjectionType::_3D => &mut prefs.view_3d,
+        ProjectionType::_4D => &mut prefs.view_4d,
+    };
 
     let mut changed = false;
 
+    let defaults = &ViewPreferences::default_for_ndim(proj_ty.ndim());
+    let resolved_presets: Vec<ViewPreferences> = presets
+        .presets
+        .iter()
+        .map(|p| presets.resolve_preset(&p.value, defaults))
+        .collect();
+    let active_preset_defaults = presets
+        .active_preset
+        .as_ref()
+        .map(|p| presets.resolve_preset(&p.value, defaults));
+
     ui.collapsing("Presets", |ui| {
         let mut presets_ui = PresetsUi {
             id: unique_id!(),
@@ -355,68 +871,174 @@ pub fn build_view_section(ui: &mut egui::Ui, app: &mut App) {
 
         presets_ui.show_header_with_active_preset(
             ui,
-            || presets.current.clone(),
+            || presets.current.clone().into(),
             |new_preset| presets.active_preset = Some(new_preset.clone()),
         );
         ui.separator();
-        presets_ui.show_list(ui, |ui, _idx, preset| {
-            let mut changed = false;
-
-            let mut r = ui.scope(|ui| {
-                if ui.button("Load").clicked() {
-                    let old = std::mem::replace(&mut presets.current, preset.value.clone());
-                    app.puzzle.animate_from_view_settings(old);
-                    presets.active_preset = Some(preset.clone());
-                    changed = true;
-                }
-                if presets.active_preset.as_ref() == Some(preset) {
-                    ui.strong(&preset.preset_name);
-                } else {
-                    ui.label(&preset.preset_name);
+        let active_preset_name = presets
+            .active_preset
+            .as_ref()
+            .map(|p| p.preset_name.clone());
+        presets_ui.show_list_with_active_preset(
+            ui,
+            active_preset_name.as_deref(),
+            |ui, idx, preset| {
+                let mut changed = false;
+
+                let mut r = ui.scope(|ui| {
+                    if ui.button("Load").clicked() {
+                        let new = match resolved_presets.get(idx) {
+                            Some(resolved) => resolved.clone(),
+                            None => preset.value.resolve(defaults),
+                        };
+                        let old = std::mem::replace(&mut presets.current, new);
+                        app.puzzle.animate_from_view_settings(old);
+                        presets.active_preset = Some(preset.clone());
+                        changed = true;
+                    }
+                    if presets.active_preset.as_ref() == Some(preset) {
+                        ui.strong(&preset.preset_name);
+                    } else {
+                        ui.label(&preset.preset_name);
+                    }
+                });
+                if changed {
+                    r.response.mark_changed();
                 }
-            });
-            if changed {
-                r.response.mark_changed();
+                r.response
+            },
+        );
+    });
+
+    ui.collapsing("Inheritance", |ui| {
+        for i in 0..presets.presets.len() {
+            let preset = &presets.presets[i];
+            let mut parent = preset.value.parent.clone();
+            let candidates: Vec<String> = presets
+                .presets
+                .iter()
+                .filter(|p| {
+                    p.preset_name != preset.preset_name
+                        && !presets.inherits_from(&p.value, &preset.preset_name)
+                })
+                .map(|p| p.preset_name.clone())
+                .collect();
+
+            let mut parent_changed = false;
+            egui::ComboBox::from_label(&preset.preset_name)
+                .selected_text(parent.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    parent_changed |= ui.selectable_value(&mut parent, None, "None").changed();
+                    for name in candidates {
+                        parent_changed |= ui
+                            .selectable_value(&mut parent, Some(name.clone()), name)
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_explanation(
+                    "",
+                    "Preset to inherit settings from. Only settings \
+                     that differ from the parent are saved.",
+                );
+
+            if parent_changed {
+                // Keep the effective settings the same, but store them
+                // relative to the new parent.
+                let parent_resolved = parent
+                    .as_ref()
+                    .and_then(|name| presets.presets.iter().position(|p| &p.preset_name == name))
+                    .map_or(defaults, |j| &resolved_presets[j]);
+                presets.presets[i].value =
+                    PartialViewPreferences::diff(&resolved_presets[i], parent, parent_resolved);
+                changed = true;
             }
-            r.response
-        });
+        }
+    });
+
+    ui.collapsing("Defaults", |ui| {
+        let preset_names: Vec<&str> = presets.presets.iter().map(|p| &*p.preset_name).collect();
+        let defaults = &mut prefs.default_view_presets;
+        let mut combo_box = |label: &str, value: &mut Option<String>| {
+            egui::ComboBox::from_label(label)
+                .selected_text(value.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(value, None, "None").changed();
+                    for &name in &preset_names {
+                        changed |= ui
+                            .selectable_value(value, Some(name.to_owned()), name)
+                            .changed();
+                    }
+                })
+                .response
+        };
+        combo_box(puzzle_type.name(), &mut defaults.per_puzzle[puzzle_type])
+            .on_hover_explanation("", "View preset to load when switching to this puzzle.");
+        combo_box(
+            puzzle_type.family_display_name(),
+            &mut defaults.per_family[puzzle_type],
+        )
+        .on_hover_explanation(
+            "",
+            "View preset to load when switching to a puzzle \
+                 in this family, if this puzzle has no default.",
+        );
+        combo_box("Everything", &mut defaults.global).on_hover_explanation(
+            "",
+            "View preset to load when switching to any puzzle \
+             with no more specific default.",
+        );
     });
 
     let mut prefs_ui = PrefsUi {
         ui,
         current: &mut presets.current,
-        defaults: match &presets.active_preset {
-            Some(p) => &p.value,
-            None => DEFAULT_PREFS.view(puzzle_type),
-        },
+        defaults: active_preset_defaults.as_ref().unwrap_or(defaults),
         changed: &mut changed,
     };
 
     prefs_ui.collapsing("Position", |mut prefs_ui| {
-        prefs_ui.num("Horizontal align", access!(.align_h), |dv| {
-            dv.clamp_range(-1.0..=1.0).fixed_decimals(2).speed(0.01)
+        prefs_ui.num("Horizontal align", access!(.align_h), -1.0..=1.0, |dv| {
+            dv.fixed_decimals(2).speed(0.01)
         });
-        prefs_ui.num("Vertical align", access!(.align_v), |dv| {
-            dv.clamp_range(-1.0..=1.0).fixed_decimals(2).speed(0.01)
+        prefs_ui.num("Vertical align", access!(.align_v), -1.0..=1.0, |dv| {
+            dv.fixed_decimals(2).speed(0.01)
         });
+        prefs_ui
+            .num(
+                "Horizontal pan",
+                access!(.pan[0]),
+                f32::MIN..=f32::MAX,
+                |dv| dv.fixed_decimals(2).speed(0.01),
+            )
+            .on_hover_explanation(
+                "",
+                "Offset of the puzzle, which moves with \
+                 the puzzle when zooming. Drag with the \
+                 middle mouse button to pan.",
+            );
+        prefs_ui.num(
+            "Vertical pan",
+            access!(.pan[1]),
+            f32::MIN..=f32::MAX,
+            |dv| dv.fixed_decimals(2).speed(0.01),
+        );
     });
 
     prefs_ui.collapsing("View angle", |mut prefs_ui| {
-        prefs_ui.angle("Pitch", access!(.pitch), |dv| dv.clamp_range(-90.0..=90.0));
-        prefs_ui.angle("Yaw", access!(.yaw), |dv| dv.clamp_range(-180.0..=180.0));
-        prefs_ui.angle("Roll", access!(.roll), |dv| dv.clamp_range(-180.0..=180.0));
+        prefs_ui.angle("Pitch", access!(.pitch), -90.0..=90.0, |dv| dv);
+        prefs_ui.angle("Yaw", access!(.yaw), -180.0..=180.0, |dv| dv);
+        prefs_ui.angle("Roll", access!(.roll), -180.0..=180.0, |dv| dv);
     });
 
     prefs_ui.collapsing("Projection", |mut prefs_ui| {
         let speed = prefs_ui.current.scale / 100.0; // logarithmic speed
-        prefs_ui.num("Scale", access!(.scale), |dv| {
-            dv.fixed_decimals(2).clamp_range(0.1..=5.0_f32).speed(speed)
+        prefs_ui.num("Scale", access!(.scale), 0.1..=5.0_f32, |dv| {
+            dv.fixed_decimals(2).speed(speed)
         });
 
         if proj_ty == ProjectionType::_4D {
-            prefs_ui.angle("4D FOV", access!(.fov_4d), |dv| {
-                dv.clamp_range(1.0..=120.0).speed(0.5)
-            });
+            prefs_ui.angle("4D FOV", access!(.fov_4d), 1.0..=120.0, |dv| dv.speed(0.5));
         }
 
         let label = if prefs_ui.current.fov_3d == 120.0 {
@@ -426,9 +1048,7 @@ pub fn build_view_section(ui: &mut egui::Ui, app: &mut App) {
         } else {
             "3D FOV"
         };
-        prefs_ui.angle(label, access!(.fov_3d), |dv| {
-            dv.clamp_range(-120.0..=120.0).speed(0.5)
-        });
+        prefs_ui.angle(label, access!(.fov_3d), -120.0..=120.0, |dv| dv.speed(0.5));
     });
 
     prefs_ui.collapsing("Geometry", |mut prefs_ui| {
@@ -440,24 +1060,109 @@ pub fn build_view_section(ui: &mut egui::Ui, app: &mut App) {
             prefs_ui.checkbox("Clip 4D", access!(.clip_4d));
         }
 
-        prefs_ui.num("Face spacing", access!(.face_spacing), |dv| {
-            dv.fixed_decimals(2).clamp_range(0.0..=0.9_f32).speed(0.005)
-        });
+        let old_view_prefs = prefs_ui.current.clone();
+        let mut spacing_changed = false;
 
-        prefs_ui.num("Sticker spacing", access!(.sticker_spacing), |dv| {
-            dv.fixed_decimals(2).clamp_range(0.0..=0.9_f32).speed(0.005)
-        });
+        spacing_changed |= prefs_ui
+            .num(
+                "Face spacing",
+                access!(.face_spacing),
+                0.0..=0.9_f32,
+                |dv| dv.fixed_decimals(2).speed(0.005),
+            )
+            .changed();
+
+        spacing_changed |= prefs_ui
+            .num(
+                "Sticker spacing",
+                access!(.sticker_spacing),
+                0.0..=0.9_f32,
+                |dv| dv.fixed_decimals(2).speed(0.005),
+            )
+            .changed();
+
+        if spacing_changed && animate_geometry_changes {
+            app.puzzle.animate_from_view_settings(old_view_prefs);
+        }
+
+        prefs_ui
+            .num("Sticker gap", access!(.sticker_gap), 0.0..=0.5_f32, |dv| {
+                dv.fixed_decimals(2).speed(0.005)
+            })
+            .on_hover_explanation(
+                "",
+                "Width of the gap around each sticker, which \
+                 is filled with the sticker gap color. Unlike \
+                 sticker spacing, this does not move stickers \
+                 apart.",
+            );
+
+        if proj_ty == ProjectionType::_3D {
+            let old_view_prefs = prefs_ui.current.clone();
+            let r = prefs_ui
+                .percent("Unfold", access!(.unfold_amount))
+                .on_hover_explanation(
+                    "",
+                    "How far to unfold the faces of the puzzle \
+                     into a flat net. Switching to a preset with \
+                     a different amount animates the unfolding.",
+                );
+            if r.changed() && animate_geometry_changes {
+                app.puzzle.animate_from_view_settings(old_view_prefs);
+            }
+        }
     });
 
     prefs_ui.collapsing("Lighting", |mut prefs_ui| {
-        prefs_ui.angle("Pitch", access!(.light_pitch), |dv| {
-            dv.clamp_range(-90.0..=90.0)
-        });
-        prefs_ui.angle("Yaw", access!(.light_yaw), |dv| {
-            dv.clamp_range(-180.0..=180.0)
-        });
+        prefs_ui.angle("Pitch", access!(.light_pitch), -90.0..=90.0, |dv| dv);
+        prefs_ui.angle("Yaw", access!(.light_yaw), -180.0..=180.0, |dv| dv);
         prefs_ui.percent("Directional", access!(.light_directional));
         prefs_ui.percent("Ambient", access!(.light_ambient));
+        prefs_ui
+            .percent("Edge darkening", access!(.ambient_occlusion))
+            .on_hover_explanation(
+                "",
+                "Darkens stickers toward their edges, \
+                 which makes flat-shaded puzzles look \
+                 more three-dimensional.",
+            );
+    });
+
+    prefs_ui.collapsing("Colors", |mut prefs_ui| {
+        prefs_ui
+            .ui
+            .horizontal(|ui| {
+                let mode = &mut prefs_ui.current.color_mode;
+                let mut r = ui.selectable_value(mode, ColorMode::Stickers, "Stickers");
+                r |= ui.selectable_value(mode, ColorMode::PieceType, "Piece type");
+                *prefs_ui.changed |= r.changed();
+                ui.label("Color by");
+            })
+            .response
+            .on_hover_explanation(
+                "",
+                "Whether each sticker is drawn with its own \
+                 color or with a color for the type of its \
+                 piece, which makes it easy to see which \
+                 pieces are corners, edges, etc.",
+            );
+    });
+
+    prefs_ui.collapsing("Selection glow", |mut prefs_ui| {
+        prefs_ui
+            .checkbox("Enabled", access!(.selection_glow.enabled))
+            .on_hover_explanation(
+                "",
+                "Draws a glow around selected pieces, \
+                 behind the rest of the puzzle.",
+            );
+        prefs_ui.color("Color", access!(.selection_glow.color));
+        prefs_ui.num(
+            "Radius",
+            access!(.selection_glow.radius),
+            0.0..=64.0_f32,
+            |dv| dv.fixed_decimals(1).speed(0.1),
+        );
     });
 
     prefs.needs_save |= changed;
@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use crate::gui::components::{big_icon_button, PlaintextYamlEditor, ReorderableList};
 use crate::preferences::Preset;
 
+/// Minimum number of presets for which to show a filter box above the list.
+const MIN_PRESETS_FOR_FILTER: usize = 6;
+
 pub struct PresetsUi<'a, T> {
     pub id: egui::Id,
     pub presets: &'a mut Vec<Preset<T>>,
@@ -102,9 +105,30 @@ where
         (edit_presets && !self.plaintext_yaml_editor().is_active(ui)).then(|| postheader_ui(ui))
     }
 
+    /// Shows the list of presets. When there are enough presets, a filter box
+    /// above the list hides presets whose names do not match, except the one
+    /// named `active_preset_name`, which is pinned to the top instead.
+    pub fn show_list_with_active_preset(
+        &mut self,
+        ui: &mut egui::Ui,
+        active_preset_name: Option<&str>,
+        preset_ui: impl FnMut(&mut egui::Ui, usize, &mut Preset<T>) -> egui::Response,
+    ) {
+        self.show_list_impl(ui, active_preset_name, preset_ui)
+    }
+
     pub fn show_list(
         &mut self,
         ui: &mut egui::Ui,
+        preset_ui: impl FnMut(&mut egui::Ui, usize, &mut Preset<T>) -> egui::Response,
+    ) {
+        self.show_list_impl(ui, None, preset_ui)
+    }
+
+    fn show_list_impl(
+        &mut self,
+        ui: &mut egui::Ui,
+        active_preset_name: Option<&str>,
         mut preset_ui: impl FnMut(&mut egui::Ui, usize, &mut Preset<T>) -> egui::Response,
     ) {
         let edit_presets = ui.data().get_temp::<bool>(self.id).unwrap_or(false);
@@ -116,8 +140,40 @@ where
                     .changed();
             }
         } else {
-            for (idx, preset) in self.presets.iter_mut().enumerate() {
+            let filter_id = self.id.with("filter");
+            let mut filter: String = ui.data().get_temp(filter_id).unwrap_or_default();
+            if self.presets.len() >= MIN_PRESETS_FOR_FILTER {
+                ui.add(
+                    egui::TextEdit::singleline(&mut filter)
+                        .hint_text("🔍 Filter")
+                        .desired_width(f32::INFINITY),
+                );
+            } else {
+                filter.clear();
+            }
+            let lowercase_filter = filter.trim().to_lowercase();
+            ui.data().insert_temp(filter_id, filter);
+            let is_match = |preset: &Preset<T>| {
+                preset
+                    .preset_name
+                    .to_lowercase()
+                    .contains(&lowercase_filter)
+            };
+
+            // Keep the active preset visible even if it is filtered out.
+            let pinned_idx = active_preset_name
+                .and_then(|name| self.presets.iter().position(|p| p.preset_name == name))
+                .filter(|&idx| !is_match(&self.presets[idx]));
+            if let Some(idx) = pinned_idx {
+                let preset = &mut self.presets[idx];
                 ui.horizontal(|ui| *self.changed |= preset_ui(ui, idx, preset).changed());
+                ui.separator();
+            }
+
+            for (idx, preset) in self.presets.iter_mut().enumerate() {
+                if is_match(preset) {
+                    ui.horizontal(|ui| *self.changed |= preset_ui(ui, idx, preset).changed());
+                }
             }
         }
     }
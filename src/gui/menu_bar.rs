@@ -16,6 +16,9 @@ pub fn build(ui: &mut egui::Ui, app: &mut App) {
                 command_button(ui, app, "Save", Command::Save);
                 command_button(ui, app, "Save as...", Command::SaveAs);
                 ui.separator();
+                command_button(ui, app, "Export OBJ...", Command::ExportObj);
+                command_button(ui, app, "Export OBJ (all pieces)...", Command::ExportObjAll);
+                ui.separator();
             }
             command_button_with_explanation(
                 ui,
@@ -64,6 +67,8 @@ pub fn build(ui: &mut egui::Ui, app: &mut App) {
             if let Some(ty) = puzzle_type_menu(ui) {
                 app.event(Command::NewPuzzle(ty));
             }
+            ui.separator();
+            command_button(ui, app, "Snap camera to face", Command::SnapCamera);
         });
 
         ui.menu_button("Settings", |ui| {
@@ -74,6 +79,7 @@ pub fn build(ui: &mut egui::Ui, app: &mut App) {
             windows::GLOBAL_KEYBINDS.menu_button_toggle(ui);
             windows::PUZZLE_KEYBINDS.menu_button_toggle(ui);
             windows::MOUSEBINDS.menu_button_toggle(ui);
+            windows::DRAGBINDS.menu_button_toggle(ui);
 
             #[cfg(target_arch = "wasm32")]
             {
@@ -90,6 +96,7 @@ pub fn build(ui: &mut egui::Ui, app: &mut App) {
         ui.menu_button("Tools", |ui| {
             windows::PIECE_FILTERS.menu_button_toggle(ui);
             windows::PUZZLE_CONTROLS.menu_button_toggle(ui);
+            windows::BOOKMARKS.menu_button_toggle(ui);
             windows::KEYBIND_SETS.menu_button_toggle(ui);
             windows::MODIFIER_KEYS.menu_button_toggle(ui);
         });
@@ -1,10 +1,19 @@
+use itertools::Itertools;
 use winit::event::ModifiersState;
 
-use crate::app::{App, AppEvent};
+use crate::app::{App, AppEvent, BoxSelect};
+use crate::commands::PuzzleDragCommand;
 
 // experimental
 const ENABLE_CONTEXT_MENU: bool = false;
 
+/// Distance from the corner of the puzzle view to the recent moves text.
+const RECENT_MOVES_MARGIN: f32 = 8.0;
+/// Font size of the recent moves text.
+const RECENT_MOVES_FONT_SIZE: f32 = 16.0;
+/// Font size of the sticker labels.
+const STICKER_LABEL_FONT_SIZE: f32 = 12.0;
+
 pub fn build(ui: &mut egui::Ui, app: &mut App, puzzle_texture_id: egui::TextureId) {
     let dpi = ui.ctx().pixels_per_point();
 
@@ -32,12 +41,14 @@ pub fn build(ui: &mut egui::Ui, app: &mut App, puzzle_texture_id: egui::TextureI
         egui::Image::new(puzzle_texture_id, egui_rect.size()).sense(egui::Sense::click_and_drag()),
     );
 
-    // Update app cursor position.
-    app.cursor_pos = r.hover_pos().map(|pos| {
+    // Transform from egui to wgpu coordinates.
+    let to_puzzle_pos = |pos: egui::Pos2| {
         let p = (pos - egui_rect.min) / egui_rect.size();
-        // Transform from egui to wgpu coordinates.
         cgmath::point2(p.x * 2.0 - 1.0, 1.0 - p.y * 2.0)
-    });
+    };
+
+    // Update app cursor position.
+    app.cursor_pos = r.hover_pos().map(to_puzzle_pos);
 
     let popup_state_id = egui::Id::new("puzzle_context_menu_state");
     let mut popup_was_open = ui.data().get_temp(popup_state_id).unwrap_or(false);
@@ -70,20 +81,149 @@ pub fn build(ui: &mut egui::Ui, app: &mut App, puzzle_texture_id: egui::TextureI
         }
     }
 
-    // Submit drag events.
-    if r.dragged() {
-        app.event(AppEvent::Drag(r.drag_delta() / egui_rect.size().min_elem()))
+    // Look up what the drag does when it starts.
+    let drag_state_id = egui::Id::new("puzzle_drag_state");
+    let mut drag_state: DragState = ui.data().get_temp(drag_state_id).unwrap_or_default();
+    if r.drag_started() {
+        let button = [
+            egui::PointerButton::Primary,
+            egui::PointerButton::Secondary,
+            egui::PointerButton::Middle,
+        ]
+        .into_iter()
+        .find(|&button| r.dragged_by(button));
+        drag_state = DragState {
+            button,
+            command: button.map_or(PuzzleDragCommand::None, |b| app.dragbind_command(b)),
+            ..Default::default()
+        };
     }
-    if r.drag_released() {
-        app.event(AppEvent::DragReleased);
+
+    // Select or deselect pieces in a box.
+    let box_select_id = egui::Id::new("puzzle_box_select");
+    let is_box_select = matches!(
+        drag_state.command,
+        PuzzleDragCommand::BoxSelect | PuzzleDragCommand::BoxDeselect,
+    );
+    if r.drag_started() && is_box_select {
+        if let Some(pos) = r.interact_pointer_pos() {
+            ui.data().insert_temp(box_select_id, (pos, pos));
+        }
     }
+    let box_select_corners: Option<(egui::Pos2, egui::Pos2)> = ui.data().get_temp(box_select_id);
+    if let Some((start, mut end)) = box_select_corners {
+        if let Some(pos) = r.interact_pointer_pos() {
+            end = pos;
+            ui.data().insert_temp(box_select_id, (start, end));
+        }
+
+        let visuals = &ui.visuals().selection;
+        ui.painter().rect(
+            egui::Rect::from_two_pos(start, end),
+            0.0,
+            visuals.bg_fill.linear_multiply(0.25),
+            visuals.stroke,
+        );
 
-    // Show debug info for each sticker.
-    #[cfg(debug_assertions)]
+        if r.drag_released() {
+            ui.data().remove::<(egui::Pos2, egui::Pos2)>(box_select_id);
+            app.event(AppEvent::BoxSelect(BoxSelect {
+                start: to_puzzle_pos(start),
+                end: to_puzzle_pos(end),
+                select: drag_state.command == PuzzleDragCommand::BoxSelect,
+            }));
+        }
+    } else {
+        // Submit drag events. Drags shorter than the threshold don't rotate the
+        // puzzle, and count as clicks when released.
+        if r.dragged() {
+            drag_state.total_delta += r.drag_delta();
+            let delta = if drag_state.past_threshold {
+                r.drag_delta()
+            } else if drag_state.total_delta.length() * dpi > app.prefs.interaction.drag_threshold {
+                drag_state.past_threshold = true;
+                drag_state.total_delta
+            } else {
+                egui::Vec2::ZERO
+            };
+            let size = egui_rect.size().min_elem();
+            let cursor = r
+                .interact_pointer_pos()
+                .map_or(egui::Vec2::ZERO, |pos| (pos - egui_rect.center()) / size);
+            match drag_state.command {
+                PuzzleDragCommand::RotateCamera => app.event(AppEvent::Drag {
+                    delta: delta / size,
+                    cursor,
+                }),
+                PuzzleDragCommand::PanCamera => app.event(AppEvent::Pan(delta / size)),
+                _ => (),
+            }
+        }
+        if r.drag_released() {
+            if let Some(button) = drag_state.button {
+                if !drag_state.past_threshold && !r.clicked_by(button) {
+                    app.event(AppEvent::Click(button));
+                }
+            }
+            app.event(AppEvent::DragReleased);
+        }
+    }
+    ui.data().insert_temp(drag_state_id, drag_state);
+
+    // Show the most recent moves.
+    if app.prefs.interaction.show_recent_moves {
+        use crate::puzzle::traits::*;
+
+        let history = app.puzzle.undo_buffer();
+        let first = history
+            .len()
+            .saturating_sub(app.prefs.interaction.recent_moves_count);
+        let notation = app.puzzle.notation_scheme();
+        let text = history[first..]
+            .iter()
+            .map(|entry| entry.to_string(notation))
+            .join(" ");
+        ui.painter().text(
+            egui_rect.left_bottom() + egui::vec2(RECENT_MOVES_MARGIN, -RECENT_MOVES_MARGIN),
+            egui::Align2::LEFT_BOTTOM,
+            text,
+            egui::FontId::monospace(RECENT_MOVES_FONT_SIZE),
+            ui.visuals().strong_text_color(),
+        );
+    }
+
+    // Label each visible sticker with its ID.
+    if app.prefs.interaction.show_sticker_labels {
+        for (sticker, pos) in app.render_cache.visible_stickers() {
+            let pos = egui_rect.lerp(egui::vec2((pos.x + 1.0) / 2.0, (1.0 - pos.y) / 2.0));
+            ui.painter().text(
+                pos,
+                egui::Align2::CENTER_CENTER,
+                sticker.0,
+                egui::FontId::monospace(STICKER_LABEL_FONT_SIZE),
+                ui.visuals().strong_text_color(),
+            );
+        }
+    }
+
+    // Show debug info for the hovered sticker.
     if let Some(sticker) = app.puzzle.hovered_sticker() {
         use crate::puzzle::traits::*;
 
         let mut s = String::new();
+        if app.prefs.interaction.show_hover_info {
+            let ty = app.puzzle.ty();
+            let sticker_info = ty.info(sticker);
+            let piece_info = ty.info(sticker_info.piece);
+            s += &format!("Sticker: {}\n", sticker.0);
+            s += &format!(
+                "Piece: {} ({})\n",
+                sticker_info.piece.0,
+                ty.info(piece_info.piece_type).name,
+            );
+            s += &format!("Color: {}", ty.info(sticker_info.color).name);
+        }
+        #[cfg(debug_assertions)]
         app.puzzle.displayed().sticker_debug_info(&mut s, sticker);
         if !s.is_empty() {
             egui::popup::show_tooltip_at_pointer(
@@ -95,6 +235,19 @@ pub fn build(ui: &mut egui::Ui, app: &mut App, puzzle_texture_id: egui::TextureI
     }
 }
 
+/// State of a mouse drag on the puzzle.
+#[derive(Debug, Default, Copy, Clone)]
+struct DragState {
+    /// Mouse button that started the drag.
+    button: Option<egui::PointerButton>,
+    /// What the drag does, from the dragbinds.
+    command: PuzzleDragCommand,
+    /// Total movement since the drag started.
+    total_delta: egui::Vec2,
+    /// Whether the drag has moved far enough to rotate the puzzle.
+    past_threshold: bool,
+}
+
 fn build_puzzle_context_menu(_ui: &mut egui::Ui, _app: &mut App) {
     // let ty = app.puzzle.ty();
 
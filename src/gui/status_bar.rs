@@ -7,6 +7,7 @@ use crate::app::App;
 use crate::commands::Command;
 use crate::preferences::Key;
 use crate::puzzle::TwistMetric;
+use crate::timer::{format_duration, TimerPhase};
 
 pub fn build(ui: &mut egui::Ui, app: &mut App) {
     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -17,6 +18,10 @@ pub fn build(ui: &mut egui::Ui, app: &mut App) {
         twist_count(ui, app);
         ui.separator();
 
+        if solve_timer(ui, app) {
+            ui.separator();
+        }
+
         // Left-aligned segments
         ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
             if app.prefs.info.modifier_toggles {
@@ -171,3 +176,34 @@ fn twist_count(ui: &mut egui::Ui, app: &mut App) {
 
     app.prefs.needs_save |= changed;
 }
+
+/// Shows the solve timer. Returns `false` if there is nothing to show.
+fn solve_timer(ui: &mut egui::Ui, app: &mut App) -> bool {
+    let timer = &app.timer;
+    let text = match timer.phase() {
+        TimerPhase::Idle if timer.solves().is_empty() => return false,
+        TimerPhase::Idle => "--".to_owned(),
+        TimerPhase::Inspection { .. } => {
+            let remaining = timer.inspection_remaining().unwrap_or_default();
+            format!("Inspection: {}", remaining.as_secs() + 1)
+        }
+        TimerPhase::Running { .. } | TimerPhase::Stopped { .. } => {
+            format_duration(timer.elapsed().unwrap_or_default())
+        }
+    };
+    if timer.is_ticking() {
+        ui.ctx().request_repaint();
+    }
+
+    let fmt_avg = |avg: Option<_>| avg.map(format_duration).unwrap_or_else(|| "--".to_owned());
+    ui.label(text).on_hover_explanation(
+        "Solve timer",
+        &format!(
+            "Solves: {}\nao5: {}\nao12: {}",
+            timer.solves().len(),
+            fmt_avg(timer.ao5()),
+            fmt_avg(timer.ao12()),
+        ),
+    );
+    true
+}
@@ -0,0 +1,53 @@
+use super::Window;
+use crate::app::{App, AppEvent};
+
+pub(crate) const BOOKMARKS: Window = Window {
+    name: "Bookmarks",
+    vscroll: true,
+    build,
+    ..Window::DEFAULT
+};
+
+fn build(ui: &mut egui::Ui, app: &mut App) {
+    if ui.button("➕ Bookmark current state").clicked() {
+        let name = format!("Bookmark {}", app.puzzle.bookmarks().len() + 1);
+        app.puzzle.bookmark(name);
+    }
+
+    if app.puzzle.bookmarks().is_empty() {
+        ui.label("No bookmarks");
+        return;
+    }
+
+    ui.separator();
+
+    let mut to_restore = None;
+    let mut to_remove = None;
+    let mut to_rename = None;
+    for bookmark in app.puzzle.bookmarks() {
+        ui.horizontal(|ui| {
+            if ui.button("Restore").clicked() {
+                to_restore = Some(bookmark.id);
+            }
+            if ui.button("🗑").on_hover_text("Delete").clicked() {
+                to_remove = Some(bookmark.id);
+            }
+            let mut name = bookmark.name.clone();
+            if ui.text_edit_singleline(&mut name).changed() {
+                to_rename = Some((bookmark.id, name));
+            }
+        });
+    }
+
+    if let Some(id) = to_restore {
+        if let Err(e) = app.puzzle.restore_bookmark(id) {
+            app.event(AppEvent::StatusError(e.to_string()));
+        }
+    }
+    if let Some(id) = to_remove {
+        app.puzzle.remove_bookmark(id);
+    }
+    if let Some((id, name)) = to_rename {
+        app.puzzle.rename_bookmark(id, name);
+    }
+}
@@ -91,8 +91,8 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
         prefs_ui.checkbox("Function keys", access!(.function));
         prefs_ui.checkbox("Navigation keys", access!(.navigation));
         prefs_ui.checkbox("Numpad", access!(.numpad));
-        prefs_ui.num("Max font size", access!(.max_font_size), |dv| {
-            dv.fixed_decimals(1).clamp_range(1.0..=3.0).speed(0.01)
+        prefs_ui.num("Max font size", access!(.max_font_size), 1.0..=3.0, |dv| {
+            dv.fixed_decimals(1).speed(0.01)
         });
 
         app.prefs.needs_save |= changed;
@@ -242,6 +242,8 @@ fn draw_key(ui: &mut egui::Ui, app: &mut App, key: KeyMappingCode, rect: egui::R
                 Command::Open => ui.label("Open"),
                 Command::Save => ui.label("Save"),
                 Command::SaveAs => ui.label("Save As"),
+                Command::ExportObj => ui.label("Export OBJ"),
+                Command::ExportObjAll => ui.label("Export OBJ (all pieces)"),
                 Command::Exit => ui.label("Exit"),
 
                 Command::CopyHscLog => ui.label("Copy puzzle log (.hsc)"),
@@ -265,6 +267,7 @@ fn draw_key(ui: &mut egui::Ui, app: &mut App, key: KeyMappingCode, rect: egui::R
                 }
 
                 Command::ToggleBlindfold => ui.label("Toggle blindfold"),
+                Command::SnapCamera => ui.label("Snap camera to face"),
 
                 Command::None => unreachable!(),
             });
@@ -1,4 +1,5 @@
 mod about;
+mod bookmarks;
 mod keybind_sets;
 mod keybinds_reference;
 mod keybinds_table;
@@ -11,6 +12,7 @@ mod welcome;
 
 use crate::app::App;
 pub(crate) use about::*;
+pub(crate) use bookmarks::*;
 pub(crate) use keybind_sets::*;
 pub(crate) use keybinds_reference::*;
 pub(crate) use keybinds_table::*;
@@ -35,6 +37,7 @@ pub const ALL: &[Window] = &[
     // Tools
     KEYBINDS_REFERENCE,
     PUZZLE_CONTROLS,
+    BOOKMARKS,
     PIECE_FILTERS,
     MODIFIER_KEYS,
     // Settings
@@ -46,6 +49,7 @@ pub const ALL: &[Window] = &[
     GLOBAL_KEYBINDS,
     PUZZLE_KEYBINDS,
     MOUSEBINDS,
+    DRAGBINDS,
 ];
 
 #[cfg(debug_assertions)]
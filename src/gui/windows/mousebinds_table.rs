@@ -1,29 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
 use super::Window;
-use crate::app::App;
-use crate::commands::PuzzleMouseCommand;
+use crate::commands::{PuzzleDragCommand, PuzzleMouseCommand};
 use crate::gui::components::{
     big_icon_button, FancyComboBox, PlaintextYamlEditor, ReorderableList,
 };
-use crate::preferences::{MouseButton, Mousebind};
+use crate::preferences::{shadowed_mousebinds, MouseButton, Mousebind};
 
 pub(crate) const MOUSEBINDS: Window = Window {
     name: "Mousebinds",
     build: |ui, app| {
-        let r = ui.add(MousebindsTable { app });
+        let r = ui.add(MousebindsTable {
+            mousebinds: &mut app.prefs.mousebinds,
+            command_options: vec![
+                (PuzzleMouseCommand::None, "None".into()),
+                (PuzzleMouseCommand::TwistCw, "Twist clockwise".into()),
+                (
+                    PuzzleMouseCommand::TwistCcw,
+                    "Twist counterclockwise".into(),
+                ),
+                (PuzzleMouseCommand::Recenter, "Recenter".into()),
+                (PuzzleMouseCommand::SelectPiece, "Select piece".into()),
+            ],
+            allow_extra_mods: false,
+        });
         app.prefs.needs_save |= r.changed();
     },
     ..Window::DEFAULT
 };
 
-struct MousebindsTable<'a> {
-    app: &'a mut App,
+pub(crate) const DRAGBINDS: Window = Window {
+    name: "Dragbinds",
+    build: |ui, app| {
+        let r = ui.add(MousebindsTable {
+            mousebinds: &mut app.prefs.dragbinds,
+            command_options: vec![
+                (PuzzleDragCommand::None, "None".into()),
+                (PuzzleDragCommand::RotateCamera, "Rotate camera".into()),
+                (PuzzleDragCommand::PanCamera, "Pan camera".into()),
+                (PuzzleDragCommand::BoxSelect, "Select pieces in box".into()),
+                (
+                    PuzzleDragCommand::BoxDeselect,
+                    "Deselect pieces in box".into(),
+                ),
+            ],
+            allow_extra_mods: true,
+        });
+        app.prefs.needs_save |= r.changed();
+    },
+    ..Window::DEFAULT
+};
+
+struct MousebindsTable<'a, C> {
+    mousebinds: &'a mut Vec<Mousebind<C>>,
+    command_options: Vec<(C, Cow<'a, str>)>,
+    /// Whether a binding matches even when extra modifiers are held.
+    allow_extra_mods: bool,
 }
 
-impl egui::Widget for MousebindsTable<'_> {
+impl<C> egui::Widget for MousebindsTable<'_, C>
+where
+    C: Default + Clone + PartialEq + Serialize + for<'de> Deserialize<'de>,
+{
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         let mut changed = false;
 
-        let mousebinds = &mut self.app.prefs.mousebinds;
+        let mousebinds = self.mousebinds;
+        let command_options = self.command_options;
+        let shadowed = shadowed_mousebinds(mousebinds, self.allow_extra_mods);
 
         let yaml_editor = PlaintextYamlEditor { id: unique_id!() };
 
@@ -84,18 +129,17 @@ impl egui::Widget for MousebindsTable<'_> {
                         r |= ui.add(FancyComboBox {
                             combo_box: egui::ComboBox::from_id_source(unique_id!(idx)),
                             selected: &mut mousebind.command,
-                            options: vec![
-                                (PuzzleMouseCommand::None, "None".into()),
-                                (PuzzleMouseCommand::TwistCw, "Twist clockwise".into()),
-                                (
-                                    PuzzleMouseCommand::TwistCcw,
-                                    "Twist counterclockwise".into(),
-                                ),
-                                (PuzzleMouseCommand::Recenter, "Recenter".into()),
-                                (PuzzleMouseCommand::SelectPiece, "Select piece".into()),
-                            ],
+                            options: command_options.clone(),
                         });
 
+                        if shadowed.contains(&idx) {
+                            ui.colored_label(egui::Color32::RED, "⚠").on_hover_text(
+                                "This is never used because an earlier \
+                                 binding on the same button always \
+                                 matches first",
+                            );
+                        }
+
                         r
                     });
                     changed |= r.changed();
@@ -72,6 +72,31 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
         .show(ui, app);
 
     ui.collapsing("Types", |ui| {
+        ui.horizontal(|ui| {
+            let mut isolated = app.puzzle.isolated_piece_type();
+            let piece_type_name = |piece_type: Option<PieceType>| match piece_type {
+                Some(pt) => puzzle_type.info(pt).name.clone(),
+                None => "-".to_owned(),
+            };
+            let r = egui::ComboBox::from_id_source(unique_id!())
+                .selected_text(piece_type_name(isolated))
+                .show_ui(ui, |ui| {
+                    let mut changed = ui.selectable_value(&mut isolated, None, "-").changed();
+                    for i in 0..puzzle_type.piece_types().len() {
+                        let pt = Some(PieceType(i as _));
+                        changed |= ui
+                            .selectable_value(&mut isolated, pt, piece_type_name(pt))
+                            .changed();
+                    }
+                    changed
+                });
+            if r.inner == Some(true) {
+                app.puzzle.isolate_piece_type(isolated);
+                app.request_redraw_puzzle();
+            }
+            ui.label("Show only");
+        });
+
         for (i, piece_type) in puzzle_type.piece_types().iter().enumerate() {
             PieceFilterWidget::new_uppercased(
                 &format!("{}s", piece_type.name),
@@ -86,7 +111,7 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
     ui.collapsing("Colors", |ui| {
         ui.set_enabled(!app.prefs.colors.blindfold);
 
-        let face_colors = app.prefs.colors.face_colors_list(app.puzzle.ty());
+        let face_colors = app.prefs.face_colors_list(app.puzzle.ty());
 
         let colors_selection_id = unique_id!();
         let mut selected_colors: Vec<bool> =
@@ -141,6 +166,28 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
         ui.data().insert_temp(colors_selection_id, selected_colors);
     });
 
+    ui.collapsing("Sticker colors", |ui| {
+        ui.set_enabled(!app.prefs.colors.blindfold);
+
+        let face_colors = app.prefs.face_colors_list(puzzle_type);
+
+        let mut changed = false;
+        for (i, face) in puzzle_type.faces().iter().enumerate() {
+            let color = Face(i as _);
+            ui.horizontal(|ui| {
+                egui::color_picker::show_color(ui, face_colors[i], ui.spacing().interact_size);
+                let mut visible = app.puzzle.is_color_visible(color);
+                if ui.checkbox(&mut visible, face.name).changed() {
+                    app.puzzle.set_color_visible(color, visible);
+                    changed = true;
+                }
+            });
+        }
+        if changed {
+            app.request_redraw_puzzle();
+        }
+    });
+
     ui.collapsing("Presets", |ui| {
         ui.set_enabled(!app.prefs.colors.blindfold);
 
@@ -1,5 +1,5 @@
 use super::Window;
-use crate::app::App;
+use crate::app::{App, AppEvent};
 use crate::gui::components::reset_button;
 use crate::puzzle::*;
 
@@ -13,13 +13,14 @@ pub(crate) const PUZZLE_CONTROLS: Window = Window {
 fn cleanup(_ctx: &egui::Context, app: &mut App) {
     // It'd be really confusing if the puzzle controls window still had an
     // effect when closed.
-    app.toggle_grip = Default::default();
+    app.puzzle.clear_grip();
 }
 
 fn build(ui: &mut egui::Ui, app: &mut App) {
     let puzzle_type = app.puzzle.ty();
 
-    let grip = app.grip();
+    let grip = app.puzzle.keyboard_grip().clone();
+    let mut toggled_grip = app.puzzle.toggled_grip().clone();
 
     let h_layout = egui::Layout::left_to_right(egui::Align::TOP).with_main_wrap(true);
 
@@ -28,13 +29,12 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
 
     ui.strong("Twist axis");
     ui.with_layout(h_layout, |ui| {
-        reset_button(ui, &mut app.toggle_grip.axes, Grip::default().axes, "");
+        reset_button(ui, &mut toggled_grip.axes, Grip::default().axes, "");
         for (i, twist_axis) in puzzle_type.twist_axes().iter().enumerate() {
             let mut is_sel = grip.axes.contains(&TwistAxis(i as _));
             let r = ui.selectable_value(&mut is_sel, true, twist_axis.name);
             if r.changed() {
-                app.toggle_grip
-                    .toggle_axis(TwistAxis(i as _), !multi_select);
+                toggled_grip.toggle_axis(TwistAxis(i as _), !multi_select);
             }
         }
     });
@@ -43,16 +43,22 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
 
     ui.strong("Layers");
     ui.with_layout(h_layout, |ui| {
-        reset_button(ui, &mut app.toggle_grip.layers, Grip::default().layers, "");
+        reset_button(ui, &mut toggled_grip.layers, Grip::default().layers, "");
         for i in 0..puzzle_type.layer_count() {
             let mut is_sel = grip.layers.unwrap_or_default()[i];
             let r = ui.selectable_value(&mut is_sel, true, format!("{}", i + 1));
             if r.changed() {
-                app.toggle_grip.toggle_layer(i, false);
+                toggled_grip.toggle_layer(i, false);
             }
         }
     });
 
+    if toggled_grip != *app.puzzle.toggled_grip() {
+        app.puzzle
+            .set_toggled_grip(toggled_grip, &app.prefs.interaction);
+    }
+    let grip = app.puzzle.keyboard_grip().clone();
+
     ui.separator();
 
     let twist_axis = app.gripped_twist_axis(None);
@@ -62,17 +68,212 @@ fn build(ui: &mut egui::Ui, app: &mut App) {
     ui.add_enabled_ui(can_twist, |ui| {
         ui.with_layout(h_layout, |ui| {
             for (i, twist_direction) in puzzle_type.twist_directions().iter().enumerate() {
-                if ui.button(twist_direction.name).clicked() {
-                    if let Ok(axis) = twist_axis {
-                        // should always be `Ok`
-                        app.event(Twist {
-                            axis,
-                            direction: TwistDirection(i as _),
-                            layers: app.grip().layers.unwrap_or_default(),
-                        })
+                let twist = twist_axis.as_ref().ok().map(|&axis| Twist {
+                    axis,
+                    direction: TwistDirection(i as _),
+                    layers: grip.layers.unwrap_or_default(),
+                });
+                let is_legal = twist.map_or(false, |t| app.puzzle.latest().can_twist(t).is_ok());
+                let r = ui.add_enabled(is_legal, egui::Button::new(twist_direction.name));
+                if r.clicked() {
+                    if let Some(twist) = twist {
+                        app.event(twist);
+                    }
+                }
+            }
+        });
+    });
+
+    ui.separator();
+
+    ui.strong("History");
+    let history_len = app.puzzle.history_len();
+    let mut position = app.puzzle.history_position();
+    let r = ui.add_enabled(
+        history_len > 0,
+        egui::Slider::new(&mut position, 0..=history_len).text(format!("of {history_len}")),
+    );
+    if r.changed() {
+        // Animate single steps, but jump instantly when scrubbing.
+        let animate = position.abs_diff(app.puzzle.history_position()) == 1;
+        if let Err(e) = app.puzzle.goto_move(position, animate) {
+            app.event(AppEvent::StatusError(e.to_string()));
+        }
+    }
+    if ui
+        .button("Hint")
+        .on_hover_text("Find a short sequence of twists that places another piece")
+        .clicked()
+    {
+        app.event(AppEvent::Hint);
+    }
+
+    ui.separator();
+
+    ui.strong("Replay");
+    if let Some(replay) = app.puzzle.replay_mut() {
+        ui.horizontal(|ui| {
+            let pause_text = if replay.paused { "Resume" } else { "Pause" };
+            if ui.button(pause_text).clicked() {
+                replay.paused = !replay.paused;
+            }
+            ui.label(format!("{} moves left", replay.remaining()));
+        });
+        ui.add(
+            egui::Slider::new(&mut replay.speed_multiplier, 0.25..=4.0)
+                .logarithmic(true)
+                .text("Speed")
+                .suffix("x"),
+        );
+        if ui.button("Stop").clicked() {
+            app.puzzle.stop_replay();
+        }
+    } else {
+        let rate_id = unique_id!();
+        let mut moves_per_second: f32 = ui.data().get_temp(rate_id).unwrap_or(2.0);
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut moves_per_second)
+                    .clamp_range(0.1..=20.0)
+                    .speed(0.1)
+                    .suffix(" moves/s"),
+            );
+            let can_replay = history_len > 0;
+            let r = ui
+                .add_enabled(can_replay, egui::Button::new("Replay from start"))
+                .on_hover_text("Replay the solve at a constant rate");
+            if r.clicked() {
+                // Undo everything instantly, then play back the forward
+                // history. The redo buffer is stored in reverse order.
+                let twists: Vec<Twist> = itertools::chain(
+                    app.puzzle.undo_buffer().iter(),
+                    app.puzzle.redo_buffer().iter().rev(),
+                )
+                .filter_map(|&entry| entry.twist())
+                .collect();
+                match app.puzzle.goto_move(0, false) {
+                    Ok(()) => app.puzzle.start_replay(twists, moves_per_second),
+                    Err(e) => app.event(AppEvent::StatusError(e.to_string())),
+                }
+            }
+        });
+        ui.data().insert_temp(rate_id, moves_per_second);
+    }
+
+    ui.separator();
+
+    ui.strong("Weighted scramble");
+    let weights_id = unique_id!(puzzle_type);
+    let mut weights: Vec<f32> = ui
+        .data()
+        .get_temp(weights_id)
+        .unwrap_or_else(|| vec![1.0; puzzle_type.twist_axes().len()]);
+    ui.with_layout(h_layout, |ui| {
+        for (twist_axis, weight) in puzzle_type.twist_axes().iter().zip(&mut weights) {
+            ui.horizontal(|ui| {
+                ui.label(twist_axis.name);
+                ui.add(
+                    egui::DragValue::new(weight)
+                        .clamp_range(0.0..=10.0)
+                        .speed(0.05)
+                        .fixed_decimals(1),
+                );
+            });
+        }
+    });
+    let moves_id = unique_id!();
+    let mut moves: usize = ui.data().get_temp(moves_id).unwrap_or(20);
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut moves)
+                .clamp_range(1..=1000)
+                .suffix(" moves"),
+        );
+        let any_weight = weights.iter().any(|&w| w > 0.0);
+        if ui
+            .add_enabled(any_weight, egui::Button::new("Scramble"))
+            .clicked()
+        {
+            app.event(AppEvent::ScrambleWeighted {
+                moves,
+                axis_weights: weights.clone(),
+            });
+        }
+    });
+    ui.data().insert_temp(weights_id, weights);
+    ui.data().insert_temp(moves_id, moves);
+
+    ui.separator();
+
+    ui.strong("Algorithm");
+    let alg_id = unique_id!();
+    let mut alg: String = ui.data().get_temp(alg_id).unwrap_or_default();
+    ui.horizontal(|ui| {
+        let r = ui.add(egui::TextEdit::singleline(&mut alg).hint_text("[R, U]"));
+        let submitted = r.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+        if ui.button("Apply").clicked() || submitted {
+            let notation = app.puzzle.notation_scheme();
+            match notation.parse_sequence(&alg, |t| app.puzzle.reverse_twist(t)) {
+                Ok(twists) => {
+                    for twist in twists {
+                        app.event(twist);
                     }
                 }
+                Err(e) => app.event(AppEvent::StatusError(format!("Invalid algorithm: {e}"))),
+            }
+        }
+    });
+    ui.data().insert_temp(alg_id, alg);
+
+    ui.separator();
+
+    ui.strong("Sticker pattern");
+    let pattern_id = unique_id!();
+    let mut pattern: String = ui.data().get_temp(pattern_id).unwrap_or_default();
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut pattern).hint_text("Paste a pattern"));
+        if ui.button("Copy current").clicked() {
+            pattern = app.puzzle.latest().sticker_pattern();
+            ui.output().copied_text = pattern.clone();
+        }
+        if ui.button("Load").clicked() {
+            app.event(AppEvent::LoadStickerPattern(pattern.clone()));
+        }
+    });
+    ui.data().insert_temp(pattern_id, pattern);
+
+    ui.separator();
+
+    ui.strong("Recolor selection");
+    let selection = app.puzzle.selection();
+    let any_selected = !selection.stickers.is_empty() || !selection.pieces.is_empty();
+    let face_colors = app.prefs.face_colors_list(puzzle_type);
+    ui.add_enabled_ui(any_selected, |ui| {
+        ui.with_layout(h_layout, |ui| {
+            for (i, face) in puzzle_type.faces().iter().enumerate() {
+                let r = ui
+                    .add(egui::Button::new(face.symbol).fill(face_colors[i]))
+                    .on_hover_text(face.name);
+                if r.clicked() {
+                    app.event(AppEvent::RecolorSelection(Some(Face(i as _))));
+                }
+            }
+            if ui.button("Reset").clicked() {
+                app.event(AppEvent::RecolorSelection(None));
             }
         });
     });
+
+    ui.separator();
+
+    ui.strong("Measure");
+    let selected_stickers: Vec<Sticker> = app.puzzle.selection().stickers.iter().copied().collect();
+    if let [a, b] = selected_stickers[..] {
+        let params = app.puzzle.geometry_params(&app.prefs);
+        let m = app.puzzle.displayed().measure(a, b, params);
+        ui.label(format!("Angle: {:.1}°", m.angle_deg));
+        ui.label(format!("Distance: {:.3}", m.distance));
+    } else {
+        ui.label("Select two stickers to measure between them");
+    }
 }
@@ -18,6 +18,9 @@ pub(crate) const APPEARANCE_SETTINGS: Window = Window {
         ui.collapsing("Performance", |ui| {
             prefs::build_graphics_section(ui, app);
         });
+        ui.collapsing("Accessibility", |ui| {
+            prefs::build_accessibility_section(ui, app);
+        });
     },
     ..Window::DEFAULT
 };
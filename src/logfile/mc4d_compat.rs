@@ -152,6 +152,9 @@ impl fmt::Display for Mc4dLogFile {
 }
 impl Mc4dLogFile {
     pub fn from_puzzle(puzzle: &PuzzleController) -> Result<Self, LogFileError> {
+        if puzzle.initial_pattern().is_some() || puzzle.history_base_pattern().is_some() {
+            return Err(LogFileError::UnsupportedPattern);
+        }
         match puzzle.ty() {
             PuzzleTypeEnum::Rubiks4D { layer_count } => Ok(Self {
                 edge_length: layer_count,
@@ -202,6 +205,7 @@ pub enum LogFileError {
     UnsupportedPuzzle(String),
     BadViewMatrix,
     MissingSep,
+    UnsupportedPattern,
 }
 impl fmt::Display for LogFileError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -212,6 +216,7 @@ impl fmt::Display for LogFileError {
             Self::UnsupportedPuzzle(name) => write!(f, "unsupported puzzle: {name}"),
             Self::BadViewMatrix => write!(f, "invalid view matrix"),
             Self::MissingSep => write!(f, "missing sep"),
+            Self::UnsupportedPattern => write!(f, "puzzles loaded from a pattern are unsupported"),
         }
     }
 }
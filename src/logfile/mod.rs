@@ -90,6 +90,8 @@ struct LogFile {
         with = "crate::serde_impl::hex_bitvec::opt"
     )]
     visible_pieces: Option<BitVec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
     #[serde(
         default,
         skip_serializing_if = "cgmath::Zero::is_zero",
@@ -133,6 +135,14 @@ impl LogFile {
     fn new(puzzle: &PuzzleController) -> Self {
         let notation = puzzle.notation_scheme();
 
+        // If old undo history has been dropped, start from the state after it
+        // instead of from the scramble.
+        let base_pattern = puzzle.history_base_pattern();
+        let scramble: &[Twist] = match base_pattern {
+            Some(_) => &[],
+            None => puzzle.scramble(),
+        };
+
         Self {
             version: Self::VERSION,
             puzzle: Some(puzzle.ty()),
@@ -140,13 +150,12 @@ impl LogFile {
             visible_pieces: puzzle
                 .is_any_piece_hidden()
                 .then(|| puzzle.visible_pieces().to_bitvec()),
-            scramble_length: puzzle.scramble().len(),
+            pattern: base_pattern.or_else(|| puzzle.initial_pattern().map(str::to_owned)),
+            scramble_length: scramble.len(),
             twist_count: TwistMetric::iter()
                 .map(|metric| (metric, puzzle.twist_count(metric)))
                 .collect(),
-            scramble: crate::util::wrap_words(
-                puzzle.scramble().iter().map(|twist| twist.to_string()),
-            ),
+            scramble: crate::util::wrap_words(scramble.iter().map(|twist| twist.to_string())),
             twists: crate::util::wrap_words(
                 puzzle
                     .undo_buffer()
@@ -209,7 +218,11 @@ impl LogFile {
         }
 
         let puzzle_type = self.puzzle.context("unable to find puzzle type")?;
-        let mut ret = PuzzleController::new(puzzle_type);
+        let mut ret = match &self.pattern {
+            Some(pattern) => PuzzleController::from_sticker_pattern(puzzle_type, pattern)
+                .map_err(|e| anyhow!("invalid sticker pattern: {e}"))?,
+            None => PuzzleController::new(puzzle_type),
+        };
 
         let scramble_state = ScrambleState::from_primitive(self.state);
 
@@ -219,20 +232,14 @@ impl LogFile {
 
         let (twists, parse_errors) = self.scramble();
         warnings.extend(parse_errors.iter().map(|e| e.to_string()));
-        for twist in twists {
-            if let Err(e) = ret.twist_no_collapse(twist) {
-                warnings.push(e.to_string());
-            }
-        }
+        let twist_errors = ret.twist_sequence_instantly(twists);
+        warnings.extend(twist_errors.iter().map(|e| e.to_string()));
         ret.add_scramble_marker(scramble_state);
 
         let (twists, parse_errors) = self.twists(&puzzle_type);
         warnings.extend(parse_errors.iter().map(|e| e.to_string()));
-        for twist in twists {
-            if let Err(e) = ret.twist_no_collapse(twist) {
-                warnings.push(e.to_string());
-            }
-        }
+        let twist_errors = ret.twist_sequence_instantly(twists);
+        warnings.extend(twist_errors.iter().map(|e| e.to_string()));
         ret.skip_twist_animations();
         ret.mark_saved();
 
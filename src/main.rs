@@ -40,6 +40,7 @@ mod preferences;
 pub mod puzzle;
 mod render;
 mod serde_impl;
+mod timer;
 mod util;
 #[cfg(target_arch = "wasm32")]
 mod web_workarounds;
@@ -124,8 +125,15 @@ async fn run() {
     #[cfg(not(target_arch = "wasm32"))]
     let mut clipboard = clipboard(&event_loop);
 
+    let initial_file = std::env::args().nth(1).map(std::path::PathBuf::from);
+
+    // Initialize app state.
+    let mut app = App::new(&event_loop, initial_file);
+
     // Initialize graphics state.
-    let mut gfx = render::GraphicsState::new(&window).await;
+    let mut gfx = render::GraphicsState::new(&window, &app.prefs.gfx).await;
+    app.adapter_name = gfx.adapter_name.clone();
+    app.available_adapters = gfx.available_adapters.clone();
     let mut last_fps = 0;
     let mut frames_this_second = 0;
     let mut last_second = Instant::now();
@@ -138,6 +146,10 @@ async fn run() {
         dark_light::Mode::Dark => switch_to_dark_mode(&egui_ctx),
         dark_light::Mode::Default => switch_to_dark_mode(&egui_ctx),
     };
+    // Whether the high-contrast theme is currently applied.
+    let mut high_contrast_theme = false;
+    // Style to restore when the high-contrast theme is turned off.
+    let mut style_before_high_contrast = None;
     let mut egui_renderer = egui_wgpu::Renderer::new(&gfx.device, gfx.config.format, None, 1);
     let puzzle_texture_id = egui_renderer.register_native_texture(
         &gfx.device,
@@ -145,11 +157,6 @@ async fn run() {
         wgpu::FilterMode::Linear,
     );
 
-    let initial_file = std::env::args().nth(1).map(std::path::PathBuf::from);
-
-    // Initialize app state.
-    let mut app = App::new(&event_loop, initial_file);
-
     if app.prefs.show_welcome_at_startup {
         gui::windows::WELCOME.set_open(&egui_ctx, true);
     }
@@ -246,6 +253,17 @@ async fn run() {
                     }
                 }
 
+                if matches!(
+                    &event,
+                    WindowEvent::KeyboardInput { .. }
+                        | WindowEvent::MouseInput { .. }
+                        | WindowEvent::MouseWheel { .. }
+                        | WindowEvent::CursorMoved { .. }
+                        | WindowEvent::Touch(_)
+                ) {
+                    app.notice_input();
+                }
+
                 match &event {
                     WindowEvent::Resized(new_size) => gfx.resize(*new_size),
                     WindowEvent::ScaleFactorChanged {
@@ -255,10 +273,14 @@ async fn run() {
                         gfx.set_scale_factor(*scale_factor as f32);
                         gfx.resize(**new_inner_size);
                     }
-                    WindowEvent::ThemeChanged(theme) => match theme {
-                        winit::window::Theme::Light => switch_to_light_mode(&egui_ctx),
-                        winit::window::Theme::Dark => switch_to_dark_mode(&egui_ctx),
-                    },
+                    WindowEvent::ThemeChanged(theme) => {
+                        match theme {
+                            winit::window::Theme::Light => switch_to_light_mode(&egui_ctx),
+                            winit::window::Theme::Dark => switch_to_dark_mode(&egui_ctx),
+                        }
+                        // Reapply the high-contrast theme on the next frame.
+                        high_contrast_theme = false;
+                    }
                     _ => {
                         if !event_has_been_captured {
                             app.handle_window_event(&event);
@@ -331,6 +353,16 @@ async fn run() {
                         }
                     }
 
+                    if app.prefs.accessibility.high_contrast != high_contrast_theme {
+                        high_contrast_theme = app.prefs.accessibility.high_contrast;
+                        if high_contrast_theme {
+                            style_before_high_contrast = Some(egui_ctx.style());
+                            switch_to_high_contrast_mode(&egui_ctx);
+                        } else if let Some(style) = style_before_high_contrast.take() {
+                            egui_ctx.set_style(style);
+                        }
+                    }
+
                     let egui_output = egui_ctx.run(egui_input, |ctx| {
                         // Build all the UI.
                         gui::build(ctx, &mut app, puzzle_texture_id);
@@ -503,6 +535,29 @@ fn switch_to_light_mode(ctx: &egui::Context) {
     });
     set_style_overrides(ctx);
 }
+fn switch_to_high_contrast_mode(ctx: &egui::Context) {
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(egui::Color32::WHITE);
+    visuals.extreme_bg_color = egui::Color32::BLACK;
+    visuals.hyperlink_color = egui::Color32::from_rgb(0x00, 0xff, 0xff);
+    visuals.selection.stroke.color = egui::Color32::WHITE;
+    let widgets = &mut visuals.widgets;
+    for widget in [
+        &mut widgets.noninteractive,
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+        &mut widgets.open,
+    ] {
+        widget.bg_stroke.color = egui::Color32::WHITE;
+        widget.fg_stroke.color = egui::Color32::WHITE;
+    }
+    ctx.set_style(egui::Style {
+        visuals,
+        ..Default::default()
+    });
+    set_style_overrides(ctx);
+}
 fn set_style_overrides(ctx: &egui::Context) {
     let mut style = ctx.style();
     let style_mut = Arc::make_mut(&mut style);
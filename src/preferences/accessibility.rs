@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Factor by which outlines are thickened in high-contrast mode.
+pub const HIGH_CONTRAST_OUTLINE_SCALE: f32 = 2.0;
+
+/// Sticker colors used in high-contrast mode, in order of face ID.
+pub const HIGH_CONTRAST_FACE_COLORS: [egui::Color32; 8] = [
+    egui::Color32::from_rgb(0xff, 0x00, 0x00), // red
+    egui::Color32::from_rgb(0xff, 0x88, 0x00), // orange
+    egui::Color32::from_rgb(0xff, 0xff, 0xff), // white
+    egui::Color32::from_rgb(0xff, 0xff, 0x00), // yellow
+    egui::Color32::from_rgb(0x00, 0xcc, 0x00), // green
+    egui::Color32::from_rgb(0x00, 0x55, 0xff), // blue
+    egui::Color32::from_rgb(0xff, 0x00, 0xff), // magenta
+    egui::Color32::from_rgb(0x00, 0xff, 0xff), // cyan
+];
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct AccessibilityPreferences {
+    /// Whether to thicken outlines, use high-contrast sticker colors, and use
+    /// a high-contrast UI theme. This overrides other preferences without
+    /// changing them.
+    pub high_contrast: bool,
+}
@@ -12,6 +12,8 @@ pub struct ColorPreferences {
     #[serde(with = "hex_color")]
     pub background: egui::Color32,
     #[serde(with = "hex_color")]
+    pub sticker_gap: egui::Color32,
+    #[serde(with = "hex_color")]
     pub blind_face: egui::Color32,
     pub blindfold: bool,
 
@@ -56,4 +58,16 @@ impl ColorPreferences {
             })
             .collect()
     }
+
+    /// Returns the faces of `ty` whose colors differ from `defaults`.
+    pub fn modified_faces(&self, defaults: &Self, ty: PuzzleTypeEnum) -> Vec<Face> {
+        (0..ty.faces().len())
+            .map(|i| Face(i as _))
+            .filter(|&face| self[(ty, face)] != defaults[(ty, face)])
+            .collect()
+    }
+    /// Restores the color of a single face of `ty` from `defaults`.
+    pub fn reset_face(&mut self, defaults: &Self, ty: PuzzleTypeEnum, face: Face) {
+        self[(ty, face)] = defaults[(ty, face)];
+    }
 }
@@ -6,12 +6,24 @@ use serde::{Deserialize, Serialize};
 pub struct GfxPreferences {
     pub fps_limit: usize,
     pub msaa: bool,
+
+    /// Graphics backend to try first. Takes effect after restarting.
+    pub backend: GfxBackend,
+    /// Which kind of adapter to prefer. Takes effect after restarting.
+    pub power_preference: GfxPowerPreference,
+    /// Name of the graphics adapter to use, if it is available. Takes effect
+    /// after restarting.
+    pub adapter: Option<String>,
 }
 impl Default for GfxPreferences {
     fn default() -> Self {
         Self {
             fps_limit: 60,
             msaa: true,
+
+            backend: GfxBackend::default(),
+            power_preference: GfxPowerPreference::default(),
+            adapter: None,
         }
     }
 }
@@ -30,3 +42,59 @@ impl GfxPreferences {
         }
     }
 }
+
+/// Graphics API to use for rendering.
+#[derive(
+    Serialize, Deserialize, Debug, Display, EnumIter, Default, Copy, Clone, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum GfxBackend {
+    /// Any backend supported on this platform.
+    #[default]
+    Auto,
+    Vulkan,
+    Metal,
+    #[strum(serialize = "DirectX 12")]
+    Dx12,
+    #[strum(serialize = "DirectX 11")]
+    Dx11,
+    #[strum(serialize = "OpenGL")]
+    Gl,
+}
+impl GfxBackend {
+    /// Returns the set of `wgpu` backends to try.
+    pub fn to_wgpu(self) -> wgpu::Backends {
+        match self {
+            GfxBackend::Auto => wgpu::Backends::all(),
+            GfxBackend::Vulkan => wgpu::Backends::VULKAN,
+            GfxBackend::Metal => wgpu::Backends::METAL,
+            GfxBackend::Dx12 => wgpu::Backends::DX12,
+            GfxBackend::Dx11 => wgpu::Backends::DX11,
+            GfxBackend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+/// Kind of graphics adapter to prefer when there is more than one.
+#[derive(
+    Serialize, Deserialize, Debug, Display, EnumIter, Default, Copy, Clone, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum GfxPowerPreference {
+    /// Prefer a discrete GPU.
+    #[default]
+    #[strum(serialize = "High performance")]
+    HighPerformance,
+    /// Prefer an integrated GPU.
+    #[strum(serialize = "Low power")]
+    LowPower,
+}
+impl GfxPowerPreference {
+    /// Returns the equivalent `wgpu` power preference.
+    pub fn to_wgpu(self) -> wgpu::PowerPreference {
+        match self {
+            GfxPowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+            GfxPowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+        }
+    }
+}
@@ -4,13 +4,135 @@ use serde::{Deserialize, Serialize};
 #[serde(default)]
 pub struct InteractionPreferences {
     pub confirm_discard_only_when_scrambled: bool,
+    pub confirm_reset: ConfirmMode,
+    pub confirm_load_when_unsaved: bool,
+    pub reset_on_solve: bool,
+
+    pub show_recent_moves: bool,
+    pub recent_moves_count: usize,
+    pub show_sticker_labels: bool,
+    /// Whether to show the IDs, piece type, and color of the hovered sticker
+    /// in a tooltip.
+    pub show_hover_info: bool,
+    /// Maximum number of twists that can be undone or redone, or 0 for no
+    /// limit.
+    pub max_undo_depth: usize,
 
     pub drag_sensitivity: f32,
+    pub rotation_mode: RotationMode,
+    pub constrain_drag_to_axis: Option<DragAxis>,
+    pub pick_radius: f32,
+    pub highlight_piece_on_hover: bool,
+    /// Whether hovering over a sticker grips the layers that clicking it would
+    /// twist, while no axis is gripped using the keyboard.
+    pub grip_on_hover: bool,
+    /// Whether clicking a sticker twists its face relative to that face, like
+    /// turning a physical cube (so the same click on opposite faces turns them
+    /// in opposite directions), versus in a consistent direction for both
+    /// faces of an axis.
+    pub mirror_opposite_twist_direction: bool,
+    pub drag_threshold: f32,
     pub realign_on_release: bool,
     pub realign_on_keypress: bool,
+    pub twist_key_repeat: bool,
+    pub key_repeat_delay: f32,
+    pub key_repeat_rate: f32,
     pub smart_realign: bool,
 
+    /// Whether to slowly rotate the puzzle after a period with no input.
+    pub idle_spin_enabled: bool,
+    /// Speed of idle rotation, in degrees per second.
+    pub idle_spin_speed: f32,
+    /// Number of seconds without input before the puzzle starts rotating.
+    pub idle_spin_delay: f32,
+
     pub dynamic_twist_speed: bool,
+    pub dynamic_twist_ramp: f32,
+    pub dynamic_twist_max_multiplier: f32,
     pub twist_duration: f32,
     pub other_anim_duration: f32,
+    pub animate_geometry_changes: bool,
+    /// Whether to draw the axis or plane that a twist rotates around while it
+    /// is animating.
+    pub show_twist_rotation_guide: bool,
+}
+
+impl InteractionPreferences {
+    /// Returns the twist speed multiplier when `queued` twists are waiting
+    /// behind the current one:
+    ///
+    /// `min(dynamic_twist_max_multiplier, exp(dynamic_twist_ramp * queued))`
+    ///
+    /// So with no twists queued the speed is unchanged, and each additional
+    /// queued twist multiplies the speed by `exp(dynamic_twist_ramp)`.
+    pub fn dynamic_twist_speed_multiplier(&self, queued: usize) -> f32 {
+        (self.dynamic_twist_ramp * queued as f32)
+            .exp()
+            .min(self.dynamic_twist_max_multiplier)
+    }
+}
+
+/// When to show a confirmation dialog before a destructive action.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmMode {
+    /// Always ask for confirmation.
+    Always,
+    /// Ask for confirmation only when there are unsaved changes.
+    #[default]
+    WhenUnsaved,
+    /// Never ask for confirmation.
+    Never,
+}
+
+/// How dragging the mouse rotates the puzzle.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationMode {
+    /// Horizontal dragging rotates around the puzzle's vertical axis and
+    /// vertical dragging rotates around the screen's horizontal axis. Roll is
+    /// never changed.
+    #[default]
+    Turntable,
+    /// The puzzle rotates as if the cursor were dragging a point on a sphere
+    /// enclosing it, so circling around the edge of the viewport rolls the
+    /// puzzle. Drag sensitivity is ignored.
+    Trackball,
+}
+
+/// Axis to which mouse dragging may be constrained.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DragAxis {
+    /// Horizontal dragging only, which rotates around the vertical axis.
+    Yaw,
+    /// Vertical dragging only, which rotates around the horizontal axis.
+    Pitch,
+    /// Circular dragging around the center of the view, which rotates around
+    /// the axis pointing toward the viewer.
+    Roll,
+}
+impl DragAxis {
+    /// Returns the axis along which a drag delta is largest, or `None` if the
+    /// delta is zero.
+    pub fn dominant(delta: egui::Vec2) -> Option<Self> {
+        if delta == egui::Vec2::ZERO {
+            None
+        } else if delta.x.abs() >= delta.y.abs() {
+            Some(Self::Yaw)
+        } else {
+            Some(Self::Pitch)
+        }
+    }
+
+    /// Removes the component of a drag delta perpendicular to the axis.
+    pub fn constrain(self, mut delta: egui::Vec2) -> egui::Vec2 {
+        match self {
+            Self::Yaw => delta.y = 0.0,
+            Self::Pitch => delta.x = 0.0,
+            // Roll depends on the cursor position, not just the delta.
+            Self::Roll => (),
+        }
+        delta
+    }
 }
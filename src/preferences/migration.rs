@@ -159,12 +159,12 @@ mod v0 {
         }
     }
 }
-impl<T: Default + Clone> From<v0::WithPresets<T>> for WithPresets<T> {
+impl<T: Default + Clone, P: From<T>> From<v0::WithPresets<T>> for WithPresets<T, P> {
     fn from(p: v0::WithPresets<T>) -> Self {
         WithPresets {
             current: p.current,
             active_preset: p.active_preset.and_then(|preset_name| {
-                let value = p.presets.get(&preset_name)?.clone();
+                let value = p.presets.get(&preset_name)?.clone().into();
                 Some(Preset { preset_name, value })
             }),
             presets: p
@@ -172,7 +172,7 @@ impl<T: Default + Clone> From<v0::WithPresets<T>> for WithPresets<T> {
                 .into_iter()
                 .map(|(name, value)| Preset {
                     preset_name: name,
-                    value,
+                    value: value.into(),
                 })
                 .collect(),
         }
@@ -10,6 +10,7 @@ use std::collections::{btree_map, BTreeMap};
 use std::ops::{Index, IndexMut};
 use std::path::PathBuf;
 
+mod accessibility;
 mod colors;
 mod gfx;
 mod info;
@@ -25,8 +26,9 @@ mod persist_local;
 mod persist_web;
 mod view;
 
-use crate::commands::{Command, PuzzleCommand, PuzzleMouseCommand};
+use crate::commands::{Command, PuzzleCommand, PuzzleDragCommand, PuzzleMouseCommand};
 use crate::puzzle::{traits::*, ProjectionType, PuzzleTypeEnum};
+pub use accessibility::*;
 pub use colors::*;
 pub use gfx::*;
 pub use info::*;
@@ -68,14 +70,16 @@ pub struct Preferences {
     pub use_clipboard_fallback: bool,
 
     pub info: InfoPreferences,
+    pub accessibility: AccessibilityPreferences,
 
     pub gfx: GfxPreferences,
     pub interaction: InteractionPreferences,
     pub opacity: OpacityPreferences,
     pub outlines: OutlinePreferences,
 
-    pub view_3d: WithPresets<ViewPreferences>,
-    pub view_4d: WithPresets<ViewPreferences>,
+    pub view_3d: WithPresets<ViewPreferences, PartialViewPreferences>,
+    pub view_4d: WithPresets<ViewPreferences, PartialViewPreferences>,
+    pub default_view_presets: DefaultViewPresets,
 
     pub colors: ColorPreferences,
 
@@ -84,6 +88,10 @@ pub struct Preferences {
     pub global_keybinds: Vec<Keybind<Command>>,
     pub puzzle_keybinds: PerPuzzleFamily<PuzzleKeybindSets>,
     pub mousebinds: Vec<Mousebind<PuzzleMouseCommand>>,
+    /// Actions for dragging on the puzzle. A dragbind matches whenever its
+    /// modifiers are held, so that Shift can still lock the drag axis, and the
+    /// first matching dragbind is used.
+    pub dragbinds: Vec<Mousebind<PuzzleDragCommand>>,
 }
 impl Preferences {
     pub fn load(backup: Option<&Self>) -> Self {
@@ -152,12 +160,75 @@ impl Preferences {
         &mut self.view_presets(ty).current
     }
 
-    pub fn view_presets(&mut self, ty: impl PuzzleType) -> &mut WithPresets<ViewPreferences> {
+    pub fn view_presets(
+        &mut self,
+        ty: impl PuzzleType,
+    ) -> &mut WithPresets<ViewPreferences, PartialViewPreferences> {
         match ty.projection_type() {
             ProjectionType::_3D => &mut self.view_3d,
             ProjectionType::_4D => &mut self.view_4d,
         }
     }
+
+    /// Returns the sticker color for each face of a puzzle, accounting for
+    /// high-contrast mode.
+    pub fn face_colors_list(&self, ty: PuzzleTypeEnum) -> Vec<egui::Color32> {
+        if self.accessibility.high_contrast {
+            HIGH_CONTRAST_FACE_COLORS
+                .into_iter()
+                .cycle()
+                .take(ty.faces().len())
+                .collect()
+        } else {
+            self.colors.face_colors_list(ty)
+        }
+    }
+
+    /// Returns the view preset to load when switching to a puzzle type. A
+    /// preset associated with the exact puzzle takes precedence over one
+    /// associated with its family, which takes precedence over the global
+    /// default. Associations naming presets that no longer exist are skipped.
+    pub fn default_view_preset(
+        &self,
+        ty: PuzzleTypeEnum,
+    ) -> Option<&Preset<PartialViewPreferences>> {
+        let presets = match ty.projection_type() {
+            ProjectionType::_3D => &self.view_3d.presets,
+            ProjectionType::_4D => &self.view_4d.presets,
+        };
+        let defaults = &self.default_view_presets;
+        [
+            &defaults.per_puzzle[ty],
+            &defaults.per_family[ty],
+            &defaults.global,
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|name| presets.iter().find(|p| &p.preset_name == name))
+    }
+
+    /// Loads the default view preset for `ty` when switching to it from
+    /// `old_ty`, and returns the old view settings if they were replaced.
+    ///
+    /// Without a default view preset, the view is left alone. Each projection
+    /// type keeps its own view, which starts out as the one in `default.yaml`.
+    pub fn switch_puzzle_view(
+        &mut self,
+        old_ty: PuzzleTypeEnum,
+        ty: PuzzleTypeEnum,
+    ) -> Option<ViewPreferences> {
+        if ty == old_ty {
+            return None;
+        }
+        let preset = self.default_view_preset(ty).cloned()?;
+        let defaults = ViewPreferences::default_for_ndim(ty.projection_type().ndim());
+        let presets = self.view_presets(ty);
+        let new = presets.resolve_preset(&preset.value, &defaults);
+        let old = std::mem::replace(&mut presets.current, new);
+        presets.active_preset = Some(preset);
+        self.needs_save = true;
+        Some(old)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
@@ -214,11 +285,11 @@ impl PuzzleKeybindSets {
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(default)]
-pub struct WithPresets<T: Default> {
+pub struct WithPresets<T: Default, P = T> {
     #[serde(flatten)]
     pub current: T,
-    pub active_preset: Option<Preset<T>>,
-    pub presets: Vec<Preset<T>>,
+    pub active_preset: Option<Preset<P>>,
+    pub presets: Vec<Preset<P>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -237,6 +308,15 @@ impl<T: Default> Default for Preset<T> {
     }
 }
 
+/// Names of view presets to load by default when switching puzzles.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct DefaultViewPresets {
+    pub global: Option<String>,
+    pub per_family: PerPuzzleFamily<Option<String>>,
+    pub per_puzzle: PerPuzzle<Option<String>>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(transparent)]
 pub struct PerPuzzle<T> {
@@ -308,3 +388,39 @@ pub struct PieceFilter {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hidden_opacity: Option<f32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_puzzle_view() {
+        let ty_3d = PuzzleTypeEnum::Rubiks3D { layer_count: 3 };
+        let ty_4d = PuzzleTypeEnum::Rubiks4D { layer_count: 3 };
+
+        let mut prefs = DEFAULT_PREFS.clone();
+        assert_ne!(prefs.view_3d.current, prefs.view_4d.current);
+        assert_eq!(ViewPreferences::default_for_ndim(3), prefs.view_3d.current);
+        assert_eq!(ViewPreferences::default_for_ndim(4), prefs.view_4d.current);
+
+        // Custom view settings survive switching back and forth.
+        prefs.view_4d.current.scale = 2.5;
+        prefs.view_4d.current.fov_4d = 60.0;
+        let custom_4d = prefs.view_4d.current.clone();
+        assert_eq!(prefs.switch_puzzle_view(ty_4d, ty_3d), None);
+        assert_eq!(prefs.switch_puzzle_view(ty_3d, ty_4d), None);
+        assert_eq!(prefs.view_4d.current, custom_4d);
+        assert!(!prefs.needs_save);
+
+        // A default view preset replaces them.
+        let preset_name = prefs.view_4d.presets[0].preset_name.clone();
+        prefs.default_view_presets.global = Some(preset_name.clone());
+        let old = prefs.switch_puzzle_view(ty_3d, ty_4d);
+        assert_eq!(old, Some(custom_4d));
+        assert_eq!(
+            prefs.view_4d.active_preset.as_ref().map(|p| &p.preset_name),
+            Some(&preset_name),
+        );
+        assert!(prefs.needs_save);
+    }
+}
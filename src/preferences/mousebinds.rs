@@ -38,6 +38,26 @@ impl<C> Mousebind<C> {
     }
 }
 
+/// Returns the index of each mousebind that can never be used because an
+/// earlier mousebind on the same button always matches first. If
+/// `allow_extra_mods` is true, then a mousebind matches whenever all of its
+/// modifiers are held, even if other modifiers are held too; otherwise the
+/// modifiers must match exactly.
+pub fn shadowed_mousebinds<C>(binds: &[Mousebind<C>], allow_extra_mods: bool) -> Vec<usize> {
+    (0..binds.len())
+        .filter(|&i| {
+            binds[..i].iter().any(|earlier| {
+                earlier.button == binds[i].button
+                    && if allow_extra_mods {
+                        binds[i].mods().contains(earlier.mods())
+                    } else {
+                        binds[i].mods() == earlier.mods()
+                    }
+            })
+        })
+        .collect()
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum MouseButton {
@@ -9,6 +9,7 @@ pub struct OutlinePreferences {
     pub hovered_size: f32,
     pub selected_size: f32,
 
+    pub color_mode: OutlineColorMode,
     #[serde(with = "hex_color")]
     pub default_color: egui::Color32,
     #[serde(with = "hex_color")]
@@ -20,3 +21,14 @@ pub struct OutlinePreferences {
     #[serde(with = "hex_color")]
     pub selected_piece_color: egui::Color32,
 }
+
+/// Source of the outline color for pieces that are not hovered or selected.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlineColorMode {
+    /// Fixed color, set by `default_color`.
+    #[default]
+    Fixed,
+    /// Darkened version of the sticker color.
+    Sticker,
+}
@@ -1,6 +1,9 @@
 use cgmath::{Deg, Quaternion, Rotation3};
 use serde::{Deserialize, Serialize};
 
+use super::{WithPresets, DEFAULT_PREFS};
+use crate::serde_impl::hex_color;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(default)]
 pub struct ViewPreferences {
@@ -22,6 +25,9 @@ pub struct ViewPreferences {
     pub align_h: f32,
     /// Vertical alignment, from -1.0 to +1.0.
     pub align_v: f32,
+    /// Offset of the puzzle from its aligned position, in the same units as
+    /// the puzzle geometry so that it stays in place when zooming.
+    pub pan: [f32; 2],
 
     pub show_frontfaces: bool,
     pub show_backfaces: bool,
@@ -29,6 +35,12 @@ pub struct ViewPreferences {
 
     pub face_spacing: f32,
     pub sticker_spacing: f32,
+    /// Width of the gap around each sticker, as a fraction of the sticker
+    /// size. The gap is filled with the sticker gap color.
+    pub sticker_gap: f32,
+    /// How far to unfold the faces of a 3D puzzle into a flat net, from 0.0
+    /// (assembled) to 1.0 (flat).
+    pub unfold_amount: f32,
 
     pub outline_thickness: f32,
 
@@ -36,6 +48,14 @@ pub struct ViewPreferences {
     pub light_directional: f32,
     pub light_pitch: f32,
     pub light_yaw: f32,
+
+    /// Amount to darken stickers toward their edges, from 0.0 to 1.0.
+    pub ambient_occlusion: f32,
+
+    /// Source of the color of each sticker.
+    pub color_mode: ColorMode,
+
+    pub selection_glow: SelectionGlowPreferences,
 }
 impl Default for ViewPreferences {
     fn default() -> Self {
@@ -50,9 +70,12 @@ impl Default for ViewPreferences {
 
             align_h: 0.0,
             align_v: 0.0,
+            pan: [0.0, 0.0],
 
             face_spacing: 0.0,
             sticker_spacing: 0.0,
+            sticker_gap: 0.0,
+            unfold_amount: 0.0,
 
             show_frontfaces: true,
             show_backfaces: true,
@@ -64,11 +87,189 @@ impl Default for ViewPreferences {
             light_directional: 0.0,
             light_pitch: 0.0,
             light_yaw: 0.0,
+
+            ambient_occlusion: 0.0,
+
+            color_mode: ColorMode::default(),
+
+            selection_glow: SelectionGlowPreferences::default(),
+        }
+    }
+}
+
+/// Source of the color of each sticker.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+    /// Each sticker's own face color.
+    #[default]
+    Stickers,
+    /// One color for each piece type, so that every sticker on a piece has
+    /// the same color.
+    PieceType,
+}
+
+/// Halo drawn behind selected pieces, which fades out with distance from the
+/// edge of the piece.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(default)]
+pub struct SelectionGlowPreferences {
+    pub enabled: bool,
+    #[serde(with = "hex_color")]
+    pub color: egui::Color32,
+    /// Distance that the glow extends past the piece, in the same units as
+    /// outline size.
+    pub radius: f32,
+}
+impl Default for SelectionGlowPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: egui::Color32::WHITE,
+            radius: 16.0,
         }
     }
 }
 
+macro_rules! partial_view_preferences {
+    ($($field:ident: $type:ty),* $(,)?) => {
+        /// View settings for a preset. Settings that are not set are inherited
+        /// from the parent preset, or from the defaults if there is no parent.
+        #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+        #[serde(default)]
+        pub struct PartialViewPreferences {
+            /// Name of the preset to inherit settings from.
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub parent: Option<String>,
+            $(
+                #[serde(skip_serializing_if = "Option::is_none")]
+                pub $field: Option<$type>,
+            )*
+        }
+        impl From<ViewPreferences> for PartialViewPreferences {
+            fn from(value: ViewPreferences) -> Self {
+                Self {
+                    parent: None,
+                    $($field: Some(value.$field),)*
+                }
+            }
+        }
+        impl PartialViewPreferences {
+            /// Returns the full view settings, taking any settings that are not
+            /// set from `parent`.
+            pub fn resolve(&self, parent: &ViewPreferences) -> ViewPreferences {
+                ViewPreferences {
+                    $($field: self.$field.unwrap_or(parent.$field),)*
+                }
+            }
+            /// Returns settings that inherit from `parent` and resolve to
+            /// `value`. Only settings that differ from the parent are set.
+            pub fn diff(
+                value: &ViewPreferences,
+                parent_name: Option<String>,
+                parent: &ViewPreferences,
+            ) -> Self {
+                Self {
+                    parent: parent_name,
+                    $($field: (value.$field != parent.$field).then_some(value.$field),)*
+                }
+            }
+        }
+    };
+}
+partial_view_preferences! {
+    pitch: f32,
+    yaw: f32,
+    roll: f32,
+
+    scale: f32,
+    fov_3d: f32,
+    fov_4d: f32,
+
+    align_h: f32,
+    align_v: f32,
+    pan: [f32; 2],
+
+    show_frontfaces: bool,
+    show_backfaces: bool,
+    clip_4d: bool,
+
+    face_spacing: f32,
+    sticker_spacing: f32,
+    sticker_gap: f32,
+    unfold_amount: f32,
+
+    outline_thickness: f32,
+
+    light_ambient: f32,
+    light_directional: f32,
+    light_pitch: f32,
+    light_yaw: f32,
+
+    ambient_occlusion: f32,
+
+    color_mode: ColorMode,
+
+    selection_glow: SelectionGlowPreferences,
+}
+
+impl WithPresets<ViewPreferences, PartialViewPreferences> {
+    /// Returns the chain of presets that `preset` inherits from, starting with
+    /// `preset` itself. The chain stops at the first missing parent or cycle.
+    fn inheritance_chain<'a>(
+        &'a self,
+        preset: &'a PartialViewPreferences,
+    ) -> Vec<&'a PartialViewPreferences> {
+        let mut chain = vec![preset];
+        while let Some(parent_name) = &chain[chain.len() - 1].parent {
+            match self.presets.iter().find(|p| &p.preset_name == parent_name) {
+                Some(p) if !chain.iter().any(|&q| std::ptr::eq(q, &p.value)) => {
+                    chain.push(&p.value);
+                }
+                _ => break,
+            }
+        }
+        chain
+    }
+
+    /// Returns the full view settings for a preset by following its chain of
+    /// parents, falling back to `defaults` for settings that no preset sets.
+    pub fn resolve_preset(
+        &self,
+        preset: &PartialViewPreferences,
+        defaults: &ViewPreferences,
+    ) -> ViewPreferences {
+        self.inheritance_chain(preset)
+            .into_iter()
+            .rev()
+            .fold(defaults.clone(), |parent, p| p.resolve(&parent))
+    }
+
+    /// Returns whether `preset` inherits (directly or indirectly) from the
+    /// preset named `ancestor_name`.
+    pub fn inherits_from(&self, preset: &PartialViewPreferences, ancestor_name: &str) -> bool {
+        self.inheritance_chain(preset)
+            .iter()
+            .any(|p| p.parent.as_deref() == Some(ancestor_name))
+    }
+}
+
 impl ViewPreferences {
+    /// Returns the default view settings from `default.yaml` for a puzzle
+    /// with `ndim` dimensions. Puzzles with more than 4 dimensions use the 4D
+    /// settings with a smaller scale, since their projections take up more of
+    /// the viewport.
+    pub fn default_for_ndim(ndim: u8) -> Self {
+        match ndim {
+            0..=3 => DEFAULT_PREFS.view_3d.current.clone(),
+            4 => DEFAULT_PREFS.view_4d.current.clone(),
+            _ => Self {
+                scale: DEFAULT_PREFS.view_4d.current.scale / (ndim - 3) as f32,
+                ..DEFAULT_PREFS.view_4d.current.clone()
+            },
+        }
+    }
+
     pub fn view_angle(&self) -> Quaternion<f32> {
         Quaternion::from_angle_z(Deg(self.roll))
             * Quaternion::from_angle_x(Deg(self.pitch))
@@ -90,6 +291,10 @@ impl ViewPreferences {
             fov_4d: crate::util::mix(self.fov_4d, rhs.fov_4d, t),
             align_h: crate::util::mix(self.align_h, rhs.align_h, t),
             align_v: crate::util::mix(self.align_v, rhs.align_v, t),
+            pan: [
+                crate::util::mix(self.pan[0], rhs.pan[0], t),
+                crate::util::mix(self.pan[1], rhs.pan[1], t),
+            ],
             show_frontfaces: if t < 0.5 {
                 self.show_frontfaces
             } else {
@@ -103,11 +308,27 @@ impl ViewPreferences {
             clip_4d: if t < 0.5 { self.clip_4d } else { rhs.clip_4d },
             face_spacing: crate::util::mix(self.face_spacing, rhs.face_spacing, t),
             sticker_spacing: crate::util::mix(self.sticker_spacing, rhs.sticker_spacing, t),
+            sticker_gap: crate::util::mix(self.sticker_gap, rhs.sticker_gap, t),
+            unfold_amount: crate::util::mix(self.unfold_amount, rhs.unfold_amount, t),
             outline_thickness: crate::util::mix(self.outline_thickness, rhs.outline_thickness, t),
             light_ambient: crate::util::mix(self.light_ambient, rhs.light_ambient, t),
             light_directional: crate::util::mix(self.light_directional, rhs.light_directional, t),
             light_pitch: crate::util::mix(self.light_pitch, rhs.light_pitch, t),
             light_yaw: crate::util::mix(self.light_yaw, rhs.light_yaw, t),
+            ambient_occlusion: crate::util::mix(self.ambient_occlusion, rhs.ambient_occlusion, t),
+            color_mode: if t < 0.5 {
+                self.color_mode
+            } else {
+                rhs.color_mode
+            },
+            selection_glow: SelectionGlowPreferences {
+                radius: crate::util::mix(self.selection_glow.radius, rhs.selection_glow.radius, t),
+                ..if t < 0.5 {
+                    self.selection_glow
+                } else {
+                    rhs.selection_glow
+                }
+            },
         }
     }
 }
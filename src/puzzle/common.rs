@@ -1,17 +1,26 @@
-use cgmath::{One, Quaternion, Rotation};
+use cgmath::{InnerSpace, Matrix4, One, Point3, Quaternion, Rotation, Vector3};
 use enum_iterator::Sequence;
 use itertools::Itertools;
 use rand::Rng;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::ops::*;
 use std::str::FromStr;
-use strum::{Display, EnumIter, EnumMessage};
+use std::sync::Mutex;
+use strum::{Display, EnumIter, EnumMessage, IntoStaticStr};
 
 use super::*;
 
+/// Maximum number of applications of a single twist that `order_of_twist()`
+/// will try before giving up.
+pub const MAX_TWIST_ORDER: usize = 12;
+
+/// Version byte at the start of every serialized puzzle state.
+pub const STATE_FORMAT_VERSION: u8 = 1;
+
 #[delegatable_trait]
 #[enum_dispatch]
 pub trait PuzzleType {
@@ -77,6 +86,164 @@ pub trait PuzzleType {
     }
     fn canonicalize_twist(&self, twist: Twist) -> Twist;
 
+    /// Returns whether a twist rotates the whole puzzle, which is the case
+    /// when its layer mask covers every layer.
+    fn is_whole_puzzle_rotation(&self, twist: Twist) -> bool {
+        twist.layers == self.all_layers()
+    }
+    /// Returns every distinct single twist that rotates the whole puzzle, in
+    /// canonical form. Twists that have the same effect (such as `x` and
+    /// `L'` on every layer) are only included once.
+    ///
+    /// The result is computed once per puzzle type and then cached.
+    fn whole_puzzle_rotations(&self) -> &'static [Twist] {
+        lazy_static! {
+            static ref CACHE: Mutex<HashMap<PuzzleTypeEnum, &'static [Twist]>> =
+                Mutex::new(HashMap::new());
+        }
+
+        CACHE.lock().unwrap().entry(self.ty()).or_insert_with(|| {
+            let axes = (0..self.twist_axes().len() as u8).map(TwistAxis);
+            let directions = (0..self.twist_directions().len() as u8).map(TwistDirection);
+            let rotations = itertools::iproduct!(axes, directions)
+                .map(|(axis, direction)| {
+                    self.canonicalize_twist(Twist {
+                        axis,
+                        direction,
+                        layers: self.all_layers(),
+                    })
+                })
+                .unique_by(|&twist| {
+                    let mut state = Puzzle::new(self.ty());
+                    state.twist(twist).ok().map(|_| state)
+                })
+                .collect_vec();
+            Box::leak(rotations.into_boxed_slice())
+        })
+    }
+
+    /// Returns the number of times `twist` must be applied to return the
+    /// puzzle to its original state, or `None` if the twist is invalid or its
+    /// order exceeds `MAX_TWIST_ORDER`.
+    fn order_of_twist(&self, twist: Twist) -> Option<usize> {
+        let initial = Puzzle::new(self.ty());
+        let mut state = initial.clone();
+        for order in 1..=MAX_TWIST_ORDER {
+            state.twist(twist).ok()?;
+            if state == initial {
+                return Some(order);
+            }
+        }
+        None
+    }
+    /// Returns the single twist equivalent to applying `a` and then `b`, or
+    /// `None` if the composition is not a single twist. This includes the
+    /// case where `b` undoes `a`, since there is no identity twist.
+    fn compose_twists(&self, a: Twist, b: Twist) -> Option<Twist> {
+        let initial = Puzzle::new(self.ty());
+        let mut after_a = initial.clone();
+        after_a.twist(a).ok()?;
+        let mut target = after_a.clone();
+        target.twist(b).ok()?;
+
+        // Find the pieces that are moved overall and how they are moved. A
+        // single twist must move all of them the same way.
+        let transform_a = initial.twist_transform(a);
+        let transform_b = initial.twist_transform(b);
+        let mut moved = vec![];
+        let mut transform = None;
+        for piece in (0..self.pieces().len() as _).map(Piece) {
+            let piece_transform = match (
+                initial.is_piece_affected_by_twist(a, piece),
+                after_a.is_piece_affected_by_twist(b, piece),
+            ) {
+                (false, false) => continue,
+                (true, false) => transform_a,
+                (false, true) => transform_b,
+                (true, true) => transform_b * transform_a,
+            };
+            if transforms_approx_eq(piece_transform, Matrix4::one()) {
+                continue;
+            }
+            match transform {
+                None => transform = Some(piece_transform),
+                Some(t) if transforms_approx_eq(t, piece_transform) => (),
+                Some(_) => return None,
+            }
+            moved.push(piece);
+        }
+        // If nothing moved, then `b` undoes `a`.
+        let transform = transform?;
+
+        // Look for a twist with the same transform whose layers contain
+        // exactly the moved pieces.
+        let axes = (0..self.twist_axes().len() as u8).map(TwistAxis);
+        let directions = (0..self.twist_directions().len() as u8).map(TwistDirection);
+        itertools::iproduct!(axes, directions)
+            .map(|(axis, direction)| {
+                let mut layers = LayerMask(0);
+                for &piece in &moved {
+                    layers |= LayerMask(1 << initial.layer_from_twist_axis(axis, piece));
+                }
+                Twist {
+                    axis,
+                    direction,
+                    layers,
+                }
+            })
+            .filter(|&twist| transforms_approx_eq(initial.twist_transform(twist), transform))
+            .find(|&twist| {
+                let mut state = initial.clone();
+                state.twist(twist).is_ok() && state == target
+            })
+            .map(|twist| self.canonicalize_twist(twist))
+    }
+    /// Returns whether `reverse_twist()` actually undoes `twist`, and whether
+    /// it is equivalent to applying `twist` one fewer time than its order.
+    fn is_reverse_twist_consistent(&self, twist: Twist) -> bool {
+        let Some(order) = self.order_of_twist(twist) else {
+            return false;
+        };
+        let reverse = self.reverse_twist(twist);
+
+        let initial = Puzzle::new(self.ty());
+        let mut undone = initial.clone();
+        let mut repeated = initial.clone();
+        if undone.twist(twist).is_err() || undone.twist(reverse).is_err() {
+            return false;
+        }
+        for _ in 1..order {
+            if repeated.twist(twist).is_err() {
+                return false;
+            }
+        }
+        let mut reversed = initial.clone();
+        if reversed.twist(reverse).is_err() {
+            return false;
+        }
+        undone == initial && repeated == reversed
+    }
+    /// Returns whether the opposite of `twist_axis` is a different axis
+    /// pointing the other way, whose opposite is `twist_axis` again. Axes with
+    /// no opposite are always consistent.
+    fn is_opposite_twist_axis_consistent(&self, twist_axis: TwistAxis) -> bool {
+        let Some(opposite) = self.opposite_twist_axis(twist_axis) else {
+            return true;
+        };
+        if opposite == twist_axis || self.opposite_twist_axis(opposite) != Some(twist_axis) {
+            return false;
+        }
+
+        // Antiparallel axes divide the puzzle into the same layers, numbered
+        // in reverse.
+        let p = Puzzle::new(self.ty());
+        let last_layer = self.layer_count() - 1;
+        (0..self.pieces().len() as _).map(Piece).all(|piece| {
+            let layer = p.layer_from_twist_axis(twist_axis, piece);
+            p.layer_from_twist_axis(opposite, piece) == last_layer - layer
+        })
+    }
+
     fn reverse_twist_direction(&self, direction: TwistDirection) -> TwistDirection;
     fn chain_twist_directions(&self, dirs: &[TwistDirection]) -> Option<TwistDirection>;
 
@@ -120,6 +287,92 @@ pub trait PuzzleType {
             }
         }
     }
+
+    /// Returns the set of colors of the stickers on a piece.
+    fn piece_colors(&self, piece: Piece) -> HashSet<Face> {
+        self.info(piece)
+            .stickers
+            .iter()
+            .map(|&sticker| self.info(sticker).color)
+            .collect()
+    }
+    /// Returns the number of stickers of each color, indexed by face.
+    fn color_usage(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.faces().len()];
+        for sticker_info in self.stickers() {
+            counts[sticker_info.color.0 as usize] += 1;
+        }
+        counts
+    }
+    /// Returns the colors that are not used by any sticker.
+    fn unused_colors(&self) -> Vec<Face> {
+        self.color_usage()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count == 0)
+            .map(|(i, _)| Face(i as _))
+            .collect()
+    }
+    /// Returns the piece whose stickers have exactly the given colors. If
+    /// several pieces have the same colors (such as the centers of a 4x4x4),
+    /// returns the one with the lowest ID.
+    fn find_piece_by_colors(&self, colors: &[Face]) -> Option<Piece> {
+        let colors: HashSet<Face> = colors.iter().copied().collect();
+        (0..self.pieces().len() as _)
+            .map(Piece)
+            .find(|&piece| self.piece_colors(piece) == colors)
+    }
+    /// Returns all pieces that have at least the given colors, in order of ID.
+    fn find_pieces_with_colors(&self, colors: &[Face]) -> Vec<Piece> {
+        (0..self.pieces().len() as _)
+            .map(Piece)
+            .filter(|&piece| {
+                let piece_colors = self.piece_colors(piece);
+                colors.iter().all(|color| piece_colors.contains(color))
+            })
+            .collect()
+    }
+
+    /// Parses the output of `PuzzleState::sticker_pattern()` into the color
+    /// at each sticker position, checking that every color is valid and that
+    /// each color appears as many times as on a solved puzzle. Whitespace is
+    /// ignored.
+    fn parse_sticker_pattern(&self, pattern: &str) -> Result<Vec<Face>, String> {
+        let colors: Vec<Face> = pattern
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                (0..self.faces().len() as _)
+                    .map(Face)
+                    .find(|&face| self.info(face).symbol.chars().eq([c]))
+                    .ok_or_else(|| format!("invalid color {c:?}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let sticker_count = self.stickers().len();
+        if colors.len() != sticker_count {
+            return Err(format!(
+                "expected {sticker_count} stickers for {}; got {}",
+                self.name(),
+                colors.len(),
+            ));
+        }
+
+        let expected_counts = self.stickers().iter().map(|s| s.color).counts();
+        let counts = colors.iter().copied().counts();
+        for face in (0..self.faces().len() as _).map(Face) {
+            let expected = expected_counts.get(&face).copied().unwrap_or(0);
+            let got = counts.get(&face).copied().unwrap_or(0);
+            if got != expected {
+                return Err(format!(
+                    "expected {expected} stickers of color {}; got {got}",
+                    self.info(face).symbol,
+                ));
+            }
+        }
+
+        Ok(colors)
+    }
 }
 
 trait PuzzleTypeRefExt {
@@ -136,6 +389,28 @@ impl<'a, P: PuzzleType> PuzzleTypeRefExt for &'a P {
 #[enum_dispatch]
 pub trait PuzzleState: PuzzleType {
     fn twist(&mut self, twist: Twist) -> Result<(), &'static str>;
+    /// Applies a sequence of twists, producing the same state as calling
+    /// `twist()` for each one. Puzzles may override this to avoid the
+    /// per-twist overhead when reconstructing a state from a long sequence.
+    fn apply_sequence(&mut self, seq: &[Twist]) -> Result<(), &'static str> {
+        seq.iter().try_for_each(|&twist| self.twist(twist))
+    }
+    /// Returns `Ok(())` if the twist can be applied to the puzzle in its
+    /// current state, or the reason it is blocked. Puzzles with bandaging or
+    /// other constraints should override this; by default any twist with a
+    /// valid axis, direction, and layer mask is allowed.
+    fn can_twist(&self, twist: Twist) -> Result<(), TwistBlockedReason> {
+        if twist.axis.0 as usize >= self.twist_axes().len() {
+            Err(TwistBlockedReason::InvalidAxis)
+        } else if twist.direction.0 as usize >= self.twist_directions().len() {
+            Err(TwistBlockedReason::InvalidDirection)
+        } else if twist.layers == LayerMask(0) || twist.layers & !self.all_layers() != LayerMask(0)
+        {
+            Err(TwistBlockedReason::InvalidLayers)
+        } else {
+            Ok(())
+        }
+    }
     fn is_piece_affected_by_twist(&self, twist: Twist, piece: Piece) -> bool {
         twist.layers[self.layer_from_twist_axis(twist.axis, piece)]
     }
@@ -146,10 +421,24 @@ pub trait PuzzleState: PuzzleType {
             .collect()
     }
     fn layer_from_twist_axis(&self, twist_axis: TwistAxis, piece: Piece) -> u8;
+    /// Returns the transformation that a twist applies to the pieces it
+    /// moves, in puzzle space. 3D puzzles only use the upper-left 3x3 block.
+    fn twist_transform(&self, twist: Twist) -> Matrix4<f32>;
 
     fn rotation_candidates(&self) -> Vec<(Vec<Twist>, Quaternion<f32>)>;
-    fn nearest_rotation(&self, rot: Quaternion<f32>) -> (Vec<Twist>, Quaternion<f32>) {
+    /// Returns the full-puzzle rotation nearest to `rot`. If `axis` is
+    /// supplied, only rotations around that axis are considered.
+    fn nearest_rotation(
+        &self,
+        rot: Quaternion<f32>,
+        axis: Option<Vector3<f32>>,
+    ) -> (Vec<Twist>, Quaternion<f32>) {
         let inv_rot = rot.invert();
+        let axis = axis.map(|axis| axis.normalize());
+        let is_around_axis = |twist_rot: Quaternion<f32>| match axis {
+            Some(axis) => twist_rot.v.cross(axis).magnitude() <= 0.001 * twist_rot.v.magnitude(),
+            None => true,
+        };
 
         let mut nearest = (vec![], Quaternion::one());
         // If I understand correctly, the scalar part of a quaternion is the
@@ -158,6 +447,9 @@ pub trait PuzzleState: PuzzleType {
         // rotation than another.
         let mut score_of_nearest = rot.s.abs();
         for (twists, twist_rot) in self.rotation_candidates() {
+            if !is_around_axis(twist_rot) {
+                continue;
+            }
             let s = (inv_rot * twist_rot).s.abs();
 
             if s > score_of_nearest {
@@ -176,13 +468,157 @@ pub trait PuzzleState: PuzzleType {
         sticker: Sticker,
         p: StickerGeometryParams,
     ) -> Option<StickerGeometry>;
+    /// Returns the vertices of a shape showing what a twist rotates around,
+    /// after 4D projection but before 3D projection, or an empty list if it is
+    /// not visible. Two vertices form a line along the axis of rotation of a 3D
+    /// twist, and more than two form a polygon in the plane fixed by a 4D
+    /// twist.
+    fn twist_rotation_guide(&self, twist: Twist, p: StickerGeometryParams) -> Vec<Point3<f32>>;
+    /// Returns the center of a sticker and the unit normal of the facet that
+    /// it is on, in puzzle space (ignoring the view transform and any twist
+    /// animation). Both vectors have one component per dimension.
+    fn sticker_center_and_normal(
+        &self,
+        sticker: Sticker,
+        p: StickerGeometryParams,
+    ) -> (Vec<f32>, Vec<f32>);
+    /// Measures the angle between the facets of two stickers and the distance
+    /// between their centers, in puzzle space.
+    fn measure(&self, a: Sticker, b: Sticker, p: StickerGeometryParams) -> Measurement {
+        let (center_a, normal_a) = self.sticker_center_and_normal(a, p);
+        let (center_b, normal_b) = self.sticker_center_and_normal(b, p);
+
+        let dot: f32 = std::iter::zip(&normal_a, &normal_b)
+            .map(|(x, y)| x * y)
+            .sum();
+        let distance_squared: f32 = std::iter::zip(&center_a, &center_b)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum();
+        Measurement {
+            angle_deg: dot.clamp(-1.0, 1.0).acos().to_degrees(),
+            distance: distance_squared.sqrt(),
+        }
+    }
 
     fn is_solved(&self) -> bool;
+    /// Returns `Ok(())` if the state obeys every conservation law of the
+    /// puzzle that this method knows about, or the law that it violates. A
+    /// state that breaks one of these laws can never be solved by twisting.
+    /// By default no laws are checked.
+    fn check_legal(&self) -> Result<(), IllegalStateReason> {
+        Ok(())
+    }
+    /// Searches for a sequence of at most `max_depth` twists that puts at
+    /// least one more piece in its home position without disturbing any piece
+    /// that is already there, preferring shorter sequences. A piece only
+    /// counts as home if every one of its stickers is on the facet of its own
+    /// color, so this does not work on a puzzle that has been rotated. This is
+    /// a greedy bounded search, not a solver; it returns `None` if there is no
+    /// such sequence within `max_depth` twists.
+    fn simple_hint(&self, max_depth: usize) -> Option<Vec<Twist>> {
+        let solved_colors = Puzzle::new(self.ty()).sticker_pattern_colors();
+        let pieces_at_home = |puzzle: &Puzzle| {
+            let mut ret = vec![true; self.pieces().len()];
+            let colors = puzzle.sticker_pattern_colors();
+            for (i, (a, b)) in std::iter::zip(colors, &solved_colors).enumerate() {
+                if a != *b {
+                    ret[self.info(Sticker(i as _)).piece.0 as usize] = false;
+                }
+            }
+            ret
+        };
+
+        let initial = Puzzle::deserialize_state(self.ty(), &self.serialize_state()).ok()?;
+        let initial_at_home = pieces_at_home(&initial);
+        let is_progress = |puzzle: &Puzzle| {
+            let at_home = pieces_at_home(puzzle);
+            let pairs = || std::iter::zip(&initial_at_home, &at_home);
+            pairs().all(|(&old, &new)| !old || new) && pairs().any(|(&old, &new)| !old && new)
+        };
+
+        // Whole-puzzle rotations never help, because they move every piece.
+        let axes = (0..self.twist_axes().len() as u8).map(TwistAxis);
+        let directions = (0..self.twist_directions().len() as u8).map(TwistDirection);
+        let layer_masks = (1..self.all_layers().0).map(LayerMask);
+        let twists = itertools::iproduct!(axes, directions, layer_masks)
+            .map(|(axis, direction, layers)| {
+                self.canonicalize_twist(Twist {
+                    axis,
+                    direction,
+                    layers,
+                })
+            })
+            .unique_by(|&twist| {
+                let mut state = Puzzle::new(self.ty());
+                state.twist(twist).ok().map(|_| state)
+            })
+            .collect_vec();
+
+        (1..=max_depth).find_map(|depth| search_hint(&initial, &twists, depth, None, &is_progress))
+    }
+
+    /// Returns a compact binary encoding of the state of every piece, which
+    /// can be read back using `Puzzle::deserialize_state()`. The first byte
+    /// is always `STATE_FORMAT_VERSION`.
+    fn serialize_state(&self) -> Vec<u8>;
+
+    /// Returns the color at each sticker position, in the order of the
+    /// stickers that occupy those positions on a solved puzzle.
+    fn sticker_pattern_colors(&self) -> Vec<Face>;
+    /// Returns a string with the symbol of the color at each sticker
+    /// position, which can be read back using `Puzzle::from_sticker_pattern()`.
+    fn sticker_pattern(&self) -> String {
+        self.sticker_pattern_colors()
+            .into_iter()
+            .map(|color| self.info(color).symbol)
+            .collect()
+    }
 
     #[cfg(debug_assertions)]
     fn sticker_debug_info(&self, _s: &mut String, _sticker: Sticker) {}
 }
 
+/// Depth-first search for `PuzzleState::simple_hint()`, trying every sequence
+/// of exactly `depth` twists that starts from `state`.
+fn search_hint(
+    state: &Puzzle,
+    twists: &[Twist],
+    depth: usize,
+    prev_axis: Option<TwistAxis>,
+    is_progress: &impl Fn(&Puzzle) -> bool,
+) -> Option<Vec<Twist>> {
+    for &twist in twists {
+        // Consecutive twists on the same axis could be combined into one.
+        if Some(twist.axis) == prev_axis {
+            continue;
+        }
+        let mut next = state.clone();
+        if next.twist(twist).is_err() {
+            continue;
+        }
+        let rest = if depth <= 1 {
+            is_progress(&next).then(Vec::new)
+        } else {
+            search_hint(&next, twists, depth - 1, Some(twist.axis), is_progress)
+        };
+        if let Some(mut rest) = rest {
+            rest.insert(0, twist);
+            return Some(rest);
+        }
+    }
+    None
+}
+
+/// Returns whether two transforms are equal, ignoring floating-point error.
+fn transforms_approx_eq(a: Matrix4<f32>, b: Matrix4<f32>) -> bool {
+    const EPSILON: f32 = 0.0001;
+
+    let diff = a - b;
+    [diff.x, diff.y, diff.z, diff.w]
+        .iter()
+        .all(|column| column.magnitude2() < EPSILON)
+}
+
 /// Enumeration of all puzzle types.
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum PuzzleTypeEnum {
@@ -248,6 +684,39 @@ impl AsRef<str> for PuzzleTypeEnum {
     }
 }
 
+/// Reason that a twist cannot be applied to a puzzle.
+#[derive(Display, IntoStaticStr, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TwistBlockedReason {
+    #[strum(serialize = "invalid twist axis")]
+    InvalidAxis,
+    #[strum(serialize = "invalid twist direction")]
+    InvalidDirection,
+    #[strum(serialize = "invalid layer mask")]
+    InvalidLayers,
+}
+
+/// Conservation law violated by an unsolvable puzzle state.
+#[derive(Display, IntoStaticStr, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum IllegalStateReason {
+    #[strum(serialize = "a piece is in a position that it can never reach")]
+    MisplacedPiece,
+    #[strum(serialize = "a corner is twisted (corner orientations do not add up to a whole turn)")]
+    CornerTwist,
+    #[strum(serialize = "an edge is flipped (an odd number of middle edges are flipped)")]
+    EdgeFlip,
+    #[strum(serialize = "two pieces are swapped (the permutation parity is odd)")]
+    Parity,
+}
+
+/// Angle and distance between two stickers, measured in puzzle space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Measurement {
+    /// Angle between the normals of the stickers' facets, in degrees.
+    pub angle_deg: f32,
+    /// Distance between the centers of the stickers.
+    pub distance: f32,
+}
+
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Twist {
     pub axis: TwistAxis,
@@ -297,8 +766,12 @@ impl Twist {
 }
 
 /// Puzzle of any type.
+///
+/// Equality and hashing compare the position and orientation of every piece,
+/// so two puzzles that differ by a whole-puzzle rotation are not equal. Use
+/// [`Puzzle::rotation_invariant_key()`] to ignore whole-puzzle rotations.
 #[enum_dispatch(PuzzleType, PuzzleState)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Puzzle {
     /// 3D Rubik's cube.
     Rubiks3D(Rubiks3D),
@@ -322,6 +795,55 @@ impl Puzzle {
             }
         }
     }
+    /// Reconstructs a puzzle of a particular type from the output of
+    /// `serialize_state()`.
+    pub fn deserialize_state(ty: PuzzleTypeEnum, bytes: &[u8]) -> Result<Puzzle, String> {
+        ty.validate()?;
+        match ty {
+            PuzzleTypeEnum::Rubiks3D { layer_count } => {
+                Rubiks3D::deserialize_state(layer_count, bytes).map(Puzzle::Rubiks3D)
+            }
+            PuzzleTypeEnum::Rubiks4D { layer_count } => {
+                Rubiks4D::deserialize_state(layer_count, bytes).map(Puzzle::Rubiks4D)
+            }
+        }
+    }
+
+    /// Reconstructs a puzzle of a particular type from the output of
+    /// `sticker_pattern()`. Every piece in the pattern must match a piece of
+    /// the puzzle, but the resulting state is not necessarily reachable by
+    /// twisting.
+    pub fn from_sticker_pattern(ty: PuzzleTypeEnum, pattern: &str) -> Result<Puzzle, String> {
+        ty.validate()?;
+        match ty {
+            PuzzleTypeEnum::Rubiks3D { layer_count } => {
+                Rubiks3D::from_sticker_pattern(layer_count, pattern).map(Puzzle::Rubiks3D)
+            }
+            PuzzleTypeEnum::Rubiks4D { layer_count } => {
+                Rubiks4D::from_sticker_pattern(layer_count, pattern).map(Puzzle::Rubiks4D)
+            }
+        }
+    }
+
+    /// Returns a key that is the same for two puzzle states if and only if
+    /// one can be reached from the other by rotating the whole puzzle.
+    pub fn rotation_invariant_key(&self) -> Vec<u8> {
+        let rotations = self.whole_puzzle_rotations();
+
+        // Find every state reachable by whole-puzzle rotations and pick the
+        // least one.
+        let mut seen = HashSet::from([self.serialize_state()]);
+        let mut unprocessed = vec![self.clone()];
+        while let Some(puzzle) = unprocessed.pop() {
+            for &rot in rotations {
+                let mut rotated = puzzle.clone();
+                if rotated.twist(rot).is_ok() && seen.insert(rotated.serialize_state()) {
+                    unprocessed.push(rotated);
+                }
+            }
+        }
+        seen.into_iter().min().unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
@@ -551,7 +1073,7 @@ impl TwistMetric {
                     let is_same_axis =
                         prev_axis == Some(twist.axis) || opp.is_some() && prev_axis == opp;
                     if !is_same_axis {
-                        if twist.layers == puzzle.all_layers() {
+                        if puzzle.is_whole_puzzle_rotation(twist) {
                             prev_axis = None;
                         } else {
                             count += 1;
@@ -578,7 +1100,7 @@ impl TwistMetric {
         let mut prev_axis = None;
         let mut prev_layers = None;
         for twist in twists {
-            if twist.layers == puzzle.all_layers() {
+            if puzzle.is_whole_puzzle_rotation(twist) {
                 let opp = puzzle.opposite_twist_axis(twist.axis);
                 let is_same_axis =
                     prev_axis == Some(twist.axis) || opp.is_some() && prev_axis == opp;
@@ -662,6 +1184,15 @@ pub enum ProjectionType {
     _3D,
     _4D,
 }
+impl ProjectionType {
+    /// Returns the number of dimensions of the puzzle.
+    pub const fn ndim(self) -> u8 {
+        match self {
+            ProjectionType::_3D => 3,
+            ProjectionType::_4D => 4,
+        }
+    }
+}
 
 /// Bitmask selecting a subset of a puzzle's layers.
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
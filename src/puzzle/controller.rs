@@ -4,24 +4,26 @@ use anyhow::Result;
 use bitvec::bitvec;
 use bitvec::slice::BitSlice;
 use bitvec::vec::BitVec;
-use cgmath::{Deg, InnerSpace, One, Quaternion, Rotation, Rotation3};
+use cgmath::{Deg, InnerSpace, Matrix4, One, Quaternion, Rotation, Rotation3, Vector3};
 use instant::Duration;
 use num_enum::FromPrimitive;
+use rand::distributions::{Distribution, WeightedIndex};
 use std::borrow::Cow;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::{BitOr, BitOrAssign};
 use std::sync::Arc;
+use strum::IntoEnumIterator;
 
 /// If at least this much of a twist is animated in one frame, just skip the
 /// animation to reduce unnecessary flashing.
 const MIN_TWIST_DELTA: f32 = 1.0 / 3.0;
 
-/// Higher number means faster exponential increase in twist speed.
-const EXP_TWIST_FACTOR: f32 = 0.5;
-
 /// Higher number means slower exponential decay of view angle offset.
 const VIEW_ANGLE_OFFSET_DECAY_RATE: f32 = 0.02_f32;
 
+/// Brightness of outlines colored by their sticker, relative to the sticker.
+const STICKER_OUTLINE_BRIGHTNESS: f32 = 0.5;
+
 /// Interpolation functions.
 pub mod interpolate {
     use std::f32::consts::PI;
@@ -40,7 +42,10 @@ pub mod interpolate {
 
 use super::*;
 use crate::commands::PARTIAL_SCRAMBLE_MOVE_COUNT_MAX;
-use crate::preferences::{InteractionPreferences, Preferences, ViewPreferences};
+use crate::preferences::{
+    DragAxis, InteractionPreferences, Key, OutlineColorMode, Preferences, ViewPreferences,
+    HIGH_CONTRAST_OUTLINE_SCALE,
+};
 use crate::util;
 use interpolate::InterpolateFn;
 
@@ -71,22 +76,49 @@ pub struct PuzzleController {
 
     /// Whether the puzzle has been scrambled.
     scramble_state: ScrambleState,
+    /// Sticker pattern that the puzzle started from, if it was loaded from one.
+    initial_pattern: Option<String>,
     /// Scramble twists.
     scramble: Vec<Twist>,
     /// Undo history.
     undo_buffer: Vec<HistoryEntry>,
     /// Redo history.
     redo_buffer: Vec<HistoryEntry>,
+    /// Puzzle state at the start of the undo history, if older entries have
+    /// been dropped because of the undo depth limit. In that case the scramble
+    /// no longer leads to the start of the undo history.
+    history_base: Option<Puzzle>,
+    /// Number of twists dropped from the start of the undo history, in each
+    /// twist metric.
+    dropped_twist_counts: HashMap<TwistMetric, usize>,
+    /// Maximum number of entries that can be undone or redone, or 0 for no
+    /// limit.
+    max_undo_depth: usize,
+    /// Saved states that can be returned to.
+    bookmarks: Vec<Bookmark>,
+    /// ID to give the next bookmark.
+    next_bookmark_id: u32,
+    /// Twists being played back at a constant rate.
+    replay: Option<Replay>,
 
     /// Sticker that the user is hovering over.
     hovered_sticker: Option<Sticker>,
     /// Twists from the hovered sticker.
     hovered_twists: Option<ClickTwists>,
 
-    /// Grip, which controls which pieces will be twisted.
+    /// Grips that are tied to a held key.
+    held_grips: HashMap<Key, Grip>,
+    /// Grip toggled using the puzzle controls.
+    toggled_grip: Grip,
+    /// Combination of `held_grips` and `toggled_grip`, which controls which
+    /// pieces will be twisted.
     grip: Grip,
-    /// Set of selected stickers.
-    selection: HashSet<Sticker>,
+    /// Grip from the hovered sticker, if grip on hover is enabled.
+    hover_grip: Grip,
+    /// Selected pieces and stickers.
+    selection: Selection,
+    /// Colors to draw stickers with instead of their own colors.
+    sticker_color_overrides: HashMap<Sticker, Face>,
     /// Last used filter.
     last_filter: String,
     /// Set of non-hidden pieces.
@@ -97,6 +129,10 @@ pub struct PuzzleController {
     /// Opacity of hidden pieces preview when hovering over a piece filter
     /// buton.
     hidden_pieces_preview_opacity: Option<f32>,
+    /// Set of colors whose stickers are not hidden.
+    visible_colors: BitVec,
+    /// Piece type to show exclusively, hiding all other pieces.
+    isolated_piece_type: Option<PieceType>,
 
     /// Piece states, such as whether a piece is hidden. All values are
     /// represented as `f32` for animation.
@@ -136,19 +172,32 @@ impl PuzzleController {
             is_unsaved_in_local_storage: true,
 
             scramble_state: ScrambleState::None,
+            initial_pattern: None,
             scramble: vec![],
             undo_buffer: vec![],
             redo_buffer: vec![],
+            history_base: None,
+            dropped_twist_counts: HashMap::new(),
+            max_undo_depth: 0,
+            bookmarks: vec![],
+            next_bookmark_id: 0,
+            replay: None,
 
             hovered_sticker: None,
             hovered_twists: None,
 
+            held_grips: HashMap::new(),
+            toggled_grip: Grip::default(),
             grip: Grip::default(),
-            selection: HashSet::new(),
+            hover_grip: Grip::default(),
+            selection: Selection::default(),
+            sticker_color_overrides: HashMap::new(),
             last_filter: "".to_string(),
             visible_pieces: bitvec![1; ty.pieces().len()],
             visible_pieces_preview: None,
             hidden_pieces_preview_opacity: None,
+            visible_colors: bitvec![1; ty.faces().len()],
+            isolated_piece_type: None,
 
             visual_piece_states: vec![VisualPieceState::default(); ty.pieces().len()],
 
@@ -156,17 +205,60 @@ impl PuzzleController {
             cached_geometry_params: None,
         }
     }
-    /// Resets the puzzle.
+    /// Constructs a new PuzzleController starting from a sticker pattern. The
+    /// pattern counts as a partial scramble.
+    pub fn from_sticker_pattern(ty: PuzzleTypeEnum, pattern: &str) -> Result<Self, String> {
+        let mut ret = Self::new(ty);
+        ret.puzzle = Puzzle::from_sticker_pattern(ty, pattern)?;
+        ret.initial_pattern = Some(ret.puzzle.sticker_pattern());
+        ret.scramble_state = ScrambleState::Partial;
+        Ok(ret)
+    }
+    /// Resets the puzzle, along with everything else about it, such as the
+    /// selection and sticker color overrides.
     pub fn reset(&mut self) {
         *self = Self::new(self.ty());
     }
 
+    /// Returns the sticker pattern that the puzzle started from, or `None` if
+    /// it started solved.
+    pub fn initial_pattern(&self) -> Option<&str> {
+        self.initial_pattern.as_deref()
+    }
     /// Returns whether the puzzle has been scrambled, solved, etc..
     pub fn scramble_state(&self) -> ScrambleState {
         self.scramble_state
     }
     /// Reset and then scramble some number of moves.
     pub fn scramble_n(&mut self, n: usize) -> Result<(), &'static str> {
+        let ty = self.ty();
+        self.scramble_n_with(n, || Twist::from_rng(ty))
+    }
+    /// Reset and then scramble some number of moves, choosing each twist axis
+    /// with probability proportional to its weight in `axis_weights`. Axes
+    /// with zero weight are never twisted.
+    pub fn scramble_n_weighted(
+        &mut self,
+        n: usize,
+        axis_weights: &[f32],
+    ) -> Result<(), &'static str> {
+        let ty = self.ty();
+        if axis_weights.len() != ty.twist_axes().len() {
+            return Err("Wrong number of twist axis weights");
+        }
+        let axis_distr = WeightedIndex::new(axis_weights)
+            .map_err(|_| "Twist axis weights must be nonnegative and not all zero")?;
+        let mut rng = rand::thread_rng();
+        self.scramble_n_with(n, || Twist {
+            axis: TwistAxis(axis_distr.sample(&mut rng) as _),
+            ..Twist::from_rng(ty)
+        })
+    }
+    fn scramble_n_with(
+        &mut self,
+        n: usize,
+        mut random_twist: impl FnMut() -> Twist,
+    ) -> Result<(), &'static str> {
         self.reset();
 
         // Set a reasonable limit on the number of moves.
@@ -175,9 +267,18 @@ impl PuzzleController {
             return Err("Cannot scramble more than 10,000 moves");
         }
 
+        // Whole-puzzle rotations don't scramble anything, so skip them unless
+        // they are the only twists the puzzle has.
+        let rotations = self.whole_puzzle_rotations();
+        let skip_rotations = self.layer_count() > 1;
+
         // Use a `while` loop instead of a `for` loop because moves may cancel.
         while self.undo_buffer.len() < n {
-            self.twist(Twist::from_rng(self.ty()))?;
+            let twist = random_twist();
+            if skip_rotations && rotations.contains(&self.canonicalize_twist(twist)) {
+                continue;
+            }
+            self.twist(twist)?;
         }
         self.add_scramble_marker(ScrambleState::Partial);
         Ok(())
@@ -192,6 +293,13 @@ impl PuzzleController {
     /// Marks the puzzle as scrambled.
     pub fn add_scramble_marker(&mut self, new_scramble_state: ScrambleState) {
         self.skip_twist_animations();
+        if let Some(base) = self.history_base.take() {
+            // The old scramble no longer leads to the start of the undo
+            // history, so start the new scramble from there instead.
+            self.initial_pattern = Some(base.sticker_pattern());
+            self.scramble.clear();
+            self.dropped_twist_counts.clear();
+        }
         self.scramble
             .extend(self.undo_buffer.drain(..).filter_map(HistoryEntry::twist));
         if new_scramble_state == ScrambleState::None {
@@ -206,6 +314,21 @@ impl PuzzleController {
     pub fn twist(&mut self, twist: Twist) -> Result<(), &'static str> {
         self._twist(twist, true)
     }
+    /// Adds a twist to the back of the twist queue and returns the pieces it
+    /// moves and how it moves them. The result is the same whether or not the
+    /// twist is animated.
+    pub fn apply_twist(&mut self, mut twist: Twist) -> Result<TwistResult, &'static str> {
+        twist.layers &= self.all_layers(); // Restrict layer mask.
+
+        // Queued twists have already been applied to `self.puzzle`, so this
+        // is the state that the new twist will be applied to.
+        let result = TwistResult {
+            moved: self.puzzle.pieces_affected_by_twist(twist),
+            transform: self.puzzle.twist_transform(twist),
+        };
+        self.twist(twist)?;
+        Ok(result)
+    }
     /// Adds a twist to the back of the twist queue. Does not cancel adjacent
     /// twists.
     pub fn twist_no_collapse(&mut self, twist: Twist) -> Result<(), &'static str> {
@@ -213,24 +336,58 @@ impl PuzzleController {
     }
     fn _twist(&mut self, mut twist: Twist, collapse: bool) -> Result<(), &'static str> {
         twist.layers &= self.all_layers(); // Restrict layer mask.
-        if twist.layers == LayerMask(0) {
-            return Err("invalid layer mask");
-        }
+        self.puzzle.can_twist(twist).map_err(<&str>::from)?;
 
         self.mark_unsaved();
         self.redo_buffer.clear();
         // Canonicalize twist.
         twist = self.canonicalize_twist(twist);
-        if collapse && self.undo_buffer.last() == Some(&self.reverse_twist(twist).into()) {
+        if collapse && self.has_undo() && self.is_reverse_of_last(twist) {
             // This twist is the reverse of the last one, so just undo the last
             // one.
             self.undo()
         } else {
             self.animate_twist(twist)?;
             self.undo_buffer.push(twist.into());
+            self.enforce_undo_depth();
             Ok(())
         }
     }
+    /// Applies a sequence of twists instantly and adds them to the undo
+    /// history, without canceling adjacent twists. This is much faster than
+    /// calling `twist_no_collapse()` for each one. Twists that cannot be
+    /// applied are skipped, and the reason for each is returned.
+    pub fn twist_sequence_instantly(
+        &mut self,
+        twists: impl IntoIterator<Item = Twist>,
+    ) -> Vec<&'static str> {
+        let mut errors = vec![];
+        let mut seq = vec![];
+        for mut twist in twists {
+            twist.layers &= self.all_layers(); // Restrict layer mask.
+            match self.puzzle.can_twist(twist) {
+                Ok(()) => seq.push(self.canonicalize_twist(twist)),
+                Err(e) => errors.push(<&str>::from(e)),
+            }
+        }
+        if seq.is_empty() {
+            return errors;
+        }
+
+        // Queued animations hold intermediate states, so finish them first.
+        self.skip_twist_animations();
+        if let Err(e) = self.puzzle.apply_sequence(&seq) {
+            errors.push(e);
+            return errors;
+        }
+        self.mark_unsaved();
+        self.redo_buffer.clear();
+        self.undo_buffer
+            .extend(seq.into_iter().map(HistoryEntry::from));
+        self.enforce_undo_depth();
+        self.cached_geometry = None;
+        errors
+    }
     /// Applies the transient rotation to the puzzle.
     pub fn apply_transient_rotation(&mut self) {
         if let Some((twists, rot)) = self.view_angle.transient_rotation.take() {
@@ -238,7 +395,7 @@ impl PuzzleController {
             for twist in twists {
                 self.mark_unsaved();
 
-                if self.undo_buffer.last() == Some(&self.reverse_twist(twist).into()) {
+                if self.has_undo() && self.is_reverse_of_last(twist) {
                     // This twist is the reverse of the last one, so just undo the last one.
                     self.redo_buffer.extend(self.undo_buffer.pop());
                 } else {
@@ -248,6 +405,7 @@ impl PuzzleController {
                 if self.puzzle.twist(twist).is_err() {
                     log::error!("error applying transient rotation twist {:?}", twist);
                 }
+                self.enforce_undo_depth();
             }
             // Remove this rotation from `current`.
             self.view_angle.current = self.view_angle.current * rot.invert();
@@ -315,43 +473,151 @@ impl PuzzleController {
         self.puzzle.ty()
     }
 
-    /// Returns the puzzle grip.
+    /// Returns the puzzle grip, which is what opacity, outlines, and other
+    /// visual feedback should use.
+    ///
+    /// The keyboard grip (see [`Self::keyboard_grip()`]) wins whenever it
+    /// grips anything, even if it only grips layers. Otherwise, if grip on
+    /// hover is enabled, the layers that clicking the hovered sticker would
+    /// twist are gripped.
     pub fn grip(&self) -> &Grip {
+        if self.grip == Grip::default() {
+            &self.hover_grip
+        } else {
+            &self.grip
+        }
+    }
+    /// Returns the grip from held grip keys and the puzzle controls, which is
+    /// what keyboard twists and the puzzle controls should use. This ignores
+    /// the hover grip.
+    pub fn keyboard_grip(&self) -> &Grip {
         &self.grip
     }
-    /// Sets the puzzle grip.
-    pub fn set_grip(&mut self, grip: Grip, prefs: &InteractionPreferences) {
+    /// Returns the grip toggled using the puzzle controls.
+    pub fn toggled_grip(&self) -> &Grip {
+        &self.toggled_grip
+    }
+    /// Sets the grip toggled using the puzzle controls.
+    pub fn set_toggled_grip(&mut self, grip: Grip, prefs: &InteractionPreferences) {
+        self.toggled_grip = grip;
+        self.update_grip(prefs);
+    }
+    /// Clears the grip toggled using the puzzle controls. Held grips are
+    /// unaffected, since they are released along with their keys.
+    pub fn clear_grip(&mut self) {
+        self.toggled_grip = Grip::default();
+        self.grip = self.combined_grip();
+    }
+    /// Returns the keys that currently have a grip tied to them.
+    pub fn held_grip_keys(&self) -> impl '_ + Iterator<Item = &Key> {
+        self.held_grips.keys()
+    }
+    /// Grips `grip` for as long as `key` is held.
+    pub fn set_held_grip(&mut self, key: Key, grip: Grip, prefs: &InteractionPreferences) {
+        self.held_grips.insert(key, grip);
+        self.update_grip(prefs);
+    }
+    /// Releases the grips tied to keys for which `remove_if` returns `true`.
+    pub fn release_held_grips(
+        &mut self,
+        mut remove_if: impl FnMut(Key) -> bool,
+        prefs: &InteractionPreferences,
+    ) {
+        self.held_grips.retain(|&k, _v| !remove_if(k));
+        self.update_grip(prefs);
+    }
+    fn combined_grip(&self) -> Grip {
+        let mut ret = self.held_grips.values().fold(Grip::default(), |a, b| a | b);
+        ret.axes.extend(&self.toggled_grip.axes);
+        if ret.layers.is_none() {
+            ret.layers = self.toggled_grip.layers;
+        }
+        ret
+    }
+    fn update_grip(&mut self, prefs: &InteractionPreferences) {
+        let grip = self.combined_grip();
         if grip != self.grip && !grip.axes.is_empty() && prefs.realign_on_keypress {
             self.unfreeze_view_angle_offset();
-        } else {
-            self.apply_transient_rotation();
         }
         self.grip = grip;
     }
 
-    /// Sets the view angle offset. Consider calling
-    /// `freeze_view_angle_offset()` as well.
+    /// Adds a turntable rotation to the view angle offset: yaw (in degrees)
+    /// around the puzzle's vertical axis and pitch (in degrees) around the
+    /// screen's horizontal axis. Consider calling `freeze_view_angle_offset()`
+    /// as well.
     pub fn add_view_angle_offset(&mut self, offset: [f32; 2], view_prefs: &ViewPreferences) {
         let prefs_view_angle = view_prefs.view_angle();
-        let offset =
-            Quaternion::from_angle_x(Deg(offset[1])) * Quaternion::from_angle_y(Deg(offset[0]));
+        let pitch = Quaternion::from_angle_x(Deg(offset[1]));
+        let yaw = Quaternion::from_angle_y(Deg(offset[0]));
         self.view_angle.current =
-            prefs_view_angle.invert() * offset * prefs_view_angle * self.view_angle.current;
+            prefs_view_angle.invert() * pitch * prefs_view_angle * self.view_angle.current * yaw;
+    }
+    /// Adds a roll (in degrees) around the axis pointing toward the viewer to
+    /// the view angle offset. Consider calling `freeze_view_angle_offset()` as
+    /// well.
+    pub fn add_view_angle_roll(&mut self, roll: f32, view_prefs: &ViewPreferences) {
+        let prefs_view_angle = view_prefs.view_angle();
+        let roll = Quaternion::from_angle_z(Deg(roll));
+        self.view_angle.current =
+            prefs_view_angle.invert() * roll * prefs_view_angle * self.view_angle.current;
+    }
+    /// Adds a trackball rotation to the view angle offset, rotating the point
+    /// on a virtual sphere under the cursor at `from` to the point under `to`.
+    /// Cursor positions are relative to the center of the viewport, in units
+    /// of its smaller dimension, with Y pointing down. Consider calling
+    /// `freeze_view_angle_offset()` as well.
+    pub fn add_trackball_rotation(
+        &mut self,
+        from: [f32; 2],
+        to: [f32; 2],
+        view_prefs: &ViewPreferences,
+    ) {
+        // Map a cursor position onto a unit sphere inscribed in the viewport,
+        // blending into a hyperbolic sheet outside it so that dragging near
+        // the edge still rotates smoothly.
+        let sphere_point = |[x, y]: [f32; 2]| {
+            let p = cgmath::vec2(x * 2.0, -y * 2.0);
+            let r2 = p.magnitude2();
+            let z = if r2 <= 0.5 {
+                (1.0 - r2).sqrt()
+            } else {
+                0.5 / r2.sqrt()
+            };
+            p.extend(z).normalize()
+        };
+        let rotation = Quaternion::from_arc(sphere_point(from), sphere_point(to), None);
+
+        let prefs_view_angle = view_prefs.view_angle();
+        self.view_angle.current =
+            prefs_view_angle.invert() * rotation * prefs_view_angle * self.view_angle.current;
     }
     /// Freezes the view angle offset, so that it will not animate back to zero
     /// automatically. It can still be changed with `set_view_angle_offset()`.
     pub fn freeze_view_angle_offset(&mut self) {
         self.view_angle.is_frozen = true;
     }
+    /// Sets the axis that the view angle offset is being dragged around, so
+    /// that smart realign respects the same constraint.
+    pub fn set_drag_axis(&mut self, axis: Option<DragAxis>) {
+        self.view_angle.drag_axis = axis;
+    }
     /// Unfreezes the view angle offset and begins animating it to the nearest
     /// compatible orientation.
     pub fn unfreeze_view_angle_offset(&mut self) {
         self.apply_transient_rotation();
         self.view_angle.is_frozen = false;
     }
-    fn update_transient_rotation(&mut self, interaction_prefs: &InteractionPreferences) {
-        if interaction_prefs.smart_realign {
-            let nearest_twists = self.puzzle.nearest_rotation(self.view_angle.current);
+    fn update_transient_rotation(&mut self, prefs: &Preferences) {
+        if prefs.interaction.smart_realign {
+            // Find the drag axis in the same space as `current`.
+            let view_angle = prefs.view(self.ty()).view_angle() * self.view_angle.current;
+            let axis = self.view_angle.drag_axis.map(|axis| match axis {
+                DragAxis::Yaw => Vector3::unit_y(),
+                DragAxis::Pitch => view_angle.invert().rotate_vector(Vector3::unit_x()),
+                DragAxis::Roll => view_angle.invert().rotate_vector(Vector3::unit_z()),
+            });
+            let nearest_twists = self.puzzle.nearest_rotation(self.view_angle.current, axis);
             self.view_angle.transient_rotation =
                 (!nearest_twists.0.is_empty()).then_some(nearest_twists);
         } else {
@@ -359,6 +625,34 @@ impl PuzzleController {
         }
     }
 
+    /// Rotates the camera to the nearest orientation in the puzzle's symmetry
+    /// group, so that a face of the puzzle points directly toward the viewer,
+    /// and animates to it. Unlike realigning, this does not twist the puzzle.
+    pub fn snap_camera_to_symmetry(&mut self, view_prefs: &mut ViewPreferences) {
+        let inv_view_angle = (view_prefs.view_angle() * self.view_angle.current).invert();
+        let closeness = |rot: &Quaternion<f32>| (inv_view_angle * rot).s.abs();
+        let target = std::iter::once(Quaternion::one())
+            .chain(self.rotation_candidates().into_iter().map(|(_, rot)| rot))
+            .max_by(|a, b| closeness(a).total_cmp(&closeness(b)))
+            .unwrap_or_else(Quaternion::one);
+
+        // Every symmetry is a combination of 90-degree rotations, so search
+        // for angles that produce it, preferring small ones.
+        const ANGLES: [f32; 4] = [0.0, 90.0, -90.0, 180.0];
+        let mut new_view_prefs = view_prefs.clone();
+        let found = itertools::iproduct!(ANGLES, ANGLES, ANGLES).any(|(pitch, yaw, roll)| {
+            new_view_prefs.pitch = pitch;
+            new_view_prefs.yaw = yaw;
+            new_view_prefs.roll = roll;
+            (new_view_prefs.view_angle().invert() * target).s.abs() > 0.999
+        });
+        if found {
+            let old = std::mem::replace(view_prefs, new_view_prefs);
+            self.view_angle.current = Quaternion::one();
+            self.animate_from_view_settings(old);
+        }
+    }
+
     /// Adds an animation to the view settings animation queue.
     pub fn animate_from_view_settings(&mut self, view_prefs: ViewPreferences) {
         self.view_settings_anim.queue.push_back(view_prefs);
@@ -372,16 +666,21 @@ impl PuzzleController {
         } else {
             self.next_displayed() // puzzle state after the twist
         };
-        let piece = self.info(sticker).piece;
-        self.grip
-            .has_piece(puzzle_state, piece)
-            .unwrap_or_else(|| self.is_visible(piece))
+        let sticker_info = self.info(sticker);
+        let piece = sticker_info.piece;
+        // Ignore the hover grip here so that it doesn't prevent hovering
+        // other stickers.
+        self.grip.has_piece(puzzle_state, piece).unwrap_or_else(|| {
+            let is_piece_hidden = self.logical_piece_state(piece).hidden;
+            !is_piece_hidden && self.is_color_visible(sticker_info.color)
+        })
     }
 
     /// Sets the hovered stickers, in order from front to back.
     pub fn update_hovered_sticker(
         &mut self,
         stickers_under_cursor: impl IntoIterator<Item = (Sticker, ClickTwists)>,
+        prefs: &InteractionPreferences,
     ) {
         let hovered = stickers_under_cursor
             .into_iter()
@@ -389,6 +688,14 @@ impl PuzzleController {
 
         self.hovered_sticker = hovered.map(|(sticker, _twists)| sticker);
         self.hovered_twists = hovered.map(|(_sticker, twists)| twists);
+
+        self.hover_grip = match self.hovered_twists.and_then(|twists| twists.cw) {
+            Some(twist) if prefs.grip_on_hover => Grip {
+                axes: HashSet::from_iter([twist.axis]),
+                layers: Some(twist.layers),
+            },
+            _ => Grip::default(),
+        };
     }
     pub(crate) fn hovered_sticker(&self) -> Option<Sticker> {
         self.hovered_sticker
@@ -419,17 +726,39 @@ impl PuzzleController {
             Cow::Borrowed(old_view_prefs)
         }
     }
-    pub(crate) fn geometry(&mut self, prefs: &Preferences) -> Arc<Vec<ProjectedStickerGeometry>> {
+    /// Returns the parameters for generating sticker geometry for the current
+    /// frame.
+    pub(crate) fn geometry_params(&mut self, prefs: &Preferences) -> StickerGeometryParams {
         let view_prefs = self.view_prefs(prefs);
-
-        self.update_transient_rotation(&prefs.interaction);
-
-        let params = StickerGeometryParams::new(
+        StickerGeometryParams::new(
             &view_prefs,
             self.ty(),
             self.current_twist(),
             self.view_angle.current * self.view_angle.queued_delta,
-        );
+        )
+    }
+    /// Returns the vertices of the rotation guide for the twist currently
+    /// being animated, after 3D projection, along with the progress of the
+    /// animation. Returns `None` if no twist is animating or the guide is not
+    /// visible.
+    pub(crate) fn twist_rotation_guide(
+        &mut self,
+        prefs: &Preferences,
+    ) -> Option<(Vec<cgmath::Point3<f32>>, f32)> {
+        let (twist, progress) = self.current_twist()?;
+        let params = self.geometry_params(prefs);
+        let verts = self
+            .displayed()
+            .twist_rotation_guide(twist, params)
+            .into_iter()
+            .map(|v| params.project_3d(v))
+            .collect::<Option<Vec<_>>>()?;
+        (!verts.is_empty()).then_some((verts, progress))
+    }
+    pub(crate) fn geometry(&mut self, prefs: &Preferences) -> Arc<Vec<ProjectedStickerGeometry>> {
+        self.update_transient_rotation(prefs);
+
+        let params = self.geometry_params(prefs);
 
         if self.cached_geometry_params != Some(params) {
             // Invalidate the cache.
@@ -444,9 +773,8 @@ impl PuzzleController {
             // Project stickers.
             let mut sticker_geometries: Vec<ProjectedStickerGeometry> = vec![];
             for sticker in (0..self.stickers().len() as _).map(Sticker) {
-                let piece = self.info(sticker).piece;
-                let vis_piece = self.visual_piece_state(piece);
-                if !self.is_sticker_hoverable(sticker) && vis_piece.opacity(prefs) == 0.0 {
+                let vis_sticker = self.visual_sticker_state(sticker);
+                if !self.is_sticker_hoverable(sticker) && vis_sticker.opacity(prefs) == 0.0 {
                     continue;
                 }
 
@@ -542,7 +870,7 @@ impl PuzzleController {
             let offset = &mut self.view_angle.current;
 
             let decay_multiplier = VIEW_ANGLE_OFFSET_DECAY_RATE.powf(delta.as_secs_f32());
-            let new_offset = Quaternion::one().slerp(*offset, decay_multiplier);
+            let new_offset = util::slerp(Quaternion::one(), *offset, decay_multiplier);
             if offset.s == new_offset.s {
                 // Stop the animation once we're not making any more progress.
                 *offset = Quaternion::one();
@@ -551,21 +879,46 @@ impl PuzzleController {
             }
         }
 
+        // Start the next twist of the replay once the previous one is done.
+        let next_replay_twist = match &mut self.replay {
+            Some(replay) if !replay.paused && self.twist_anim.queue.is_empty() => {
+                Some(replay.queue.pop_front())
+            }
+            _ => None,
+        };
+        match next_replay_twist {
+            Some(Some(twist)) => {
+                if let Err(e) = self.twist_no_collapse(twist) {
+                    log::warn!("error replaying twist {:?}: {}", twist, e);
+                    self.replay = None;
+                }
+            }
+            Some(None) => self.replay = None, // The replay is done.
+            None => (),
+        }
+        let replay_paused = self.replay.as_ref().map_or(false, |r| r.paused);
+
         // Animate twist.
         let anim = &mut self.twist_anim;
         if anim.queue.is_empty() {
             anim.queue_max = 0;
             self.view_angle.queued_delta = Quaternion::one();
-        } else {
+        } else if !replay_paused {
             // Update queue_max.
             anim.queue_max = std::cmp::max(anim.queue_max, anim.queue.len());
-            // Twist exponentially faster if there are/were more twists in the
-            // queue.
-            let speed_mod = match prefs.dynamic_twist_speed {
-                true => ((anim.queue.len() - 1) as f32 * EXP_TWIST_FACTOR).exp(),
-                false => 1.0,
+            let mut twist_delta = match &self.replay {
+                // Replay every twist at the same speed.
+                Some(replay) => delta.as_secs_f32() * replay.twists_per_second(),
+                None => {
+                    // Twist exponentially faster if there are/were more twists
+                    // in the queue.
+                    let speed_mod = match prefs.dynamic_twist_speed {
+                        true => prefs.dynamic_twist_speed_multiplier(anim.queue.len() - 1),
+                        false => 1.0,
+                    };
+                    base_speed * speed_mod
+                }
             };
-            let mut twist_delta = base_speed * speed_mod;
             // Cap the twist delta at 1.0, and also handle the case where
             // something went wrong with the calculation (e.g., division by
             // zero).
@@ -589,17 +942,21 @@ impl PuzzleController {
         for piece in (0..self.pieces().len() as _).map(Piece) {
             let logical_state = self.logical_piece_state(piece);
 
-            let gripped = self.grip.has_piece(&self.puzzle, piece);
+            let gripped = self.grip().has_piece(&self.puzzle, piece);
             let hidden = logical_state.preview_hidden.unwrap_or(logical_state.hidden);
             let stickers = &self.info(piece).stickers;
+            let hovered = stickers.iter().any(|&s| Some(s) == self.hovered_sticker);
             let target = VisualPieceState {
                 gripped: (gripped == Some(true)) as u8 as f32,
                 ungripped: (gripped == Some(false)) as u8 as f32,
                 hidden: hidden as u8 as f32,
-                selected: stickers.iter().any(|s| self.selection.contains(s)) as u8 as f32,
-                hovered: stickers.iter().any(|&s| Some(s) == self.hovered_sticker) as u8 as f32,
+                selected: (self.selection.has_piece(piece)
+                    || stickers.iter().any(|&s| self.selection.has_sticker(s)))
+                    as u8 as f32,
+                hovered: hovered as u8 as f32,
 
                 hidden_opacity_override: self.hidden_pieces_preview_opacity,
+                hovered_sticker: None,
             };
 
             /// Adds or subtracts up to `delta` to reach `target`. Returns
@@ -632,6 +989,17 @@ impl PuzzleController {
                 // Highlight hovered sticker instantly for better responsiveness.
                 changed |= approach_target(&mut current.hovered, target.hovered, f32::INFINITY);
             }
+            // Keep the previous hovered sticker while the highlight fades out.
+            if hovered {
+                let hovered_sticker = match prefs.interaction.highlight_piece_on_hover {
+                    true => None,
+                    false => self.hovered_sticker,
+                };
+                if current.hovered_sticker != hovered_sticker {
+                    current.hovered_sticker = hovered_sticker;
+                    changed = true;
+                }
+            }
             if current.hidden_opacity_override != target.hidden_opacity_override {
                 // I don't know how to animate this easily, so don't bother trying.
                 current.hidden_opacity_override = target.hidden_opacity_override;
@@ -649,18 +1017,35 @@ impl PuzzleController {
     }
     /// Returns the logical state for a piece.
     pub fn logical_piece_state(&self, piece: Piece) -> LogicalPieceState {
+        let isolated_away = self.isolated_piece_type.map_or(false, |piece_type| {
+            self.info(piece).piece_type != piece_type
+        });
         LogicalPieceState {
-            hidden: !self.visible_pieces[piece.0 as usize],
+            hidden: !self.visible_pieces[piece.0 as usize] || isolated_away,
             preview_hidden: self
                 .visible_pieces_preview
                 .as_ref()
-                .map(|bits| !bits[piece.0 as usize]),
+                .map(|bits| !bits[piece.0 as usize] || isolated_away),
         }
     }
     /// Returns the visual state for a piece.
     pub fn visual_piece_state(&self, piece: Piece) -> VisualPieceState {
         self.visual_piece_states[piece.0 as usize]
     }
+    /// Returns the visual state for a sticker, which is the visual state of
+    /// its piece except that stickers of hidden colors are always hidden and
+    /// only the hovered sticker is highlighted if the whole piece is not.
+    pub fn visual_sticker_state(&self, sticker: Sticker) -> VisualPieceState {
+        let sticker_info = self.info(sticker);
+        let mut ret = self.visual_piece_state(sticker_info.piece);
+        if !self.is_color_visible(sticker_info.color) {
+            ret.hidden = 1.0;
+        }
+        if ret.hovered_sticker.map_or(false, |s| s != sticker) {
+            ret.hovered = 0.0;
+        }
+        ret
+    }
 
     pub fn last_filter(&self) -> &str {
         &self.last_filter
@@ -704,29 +1089,89 @@ impl PuzzleController {
         !self.visible_pieces.all()
     }
 
-    /// Returns the set of selected stickers
-    pub fn selection(&self) -> &HashSet<Sticker> {
+    /// Returns the piece type being shown exclusively, if any.
+    pub fn isolated_piece_type(&self) -> Option<PieceType> {
+        self.isolated_piece_type
+    }
+    /// Hides all pieces not of the given type, or shows them again if `None`.
+    /// This does not modify the set of visible pieces, so pieces hidden by the
+    /// user stay hidden.
+    pub fn isolate_piece_type(&mut self, piece_type: Option<PieceType>) {
+        if self.isolated_piece_type != piece_type {
+            self.isolated_piece_type = piece_type;
+            // Hidden pieces may be culled from the geometry.
+            self.cached_geometry = None;
+        }
+    }
+
+    /// Returns whether stickers of a color are visible.
+    pub fn is_color_visible(&self, color: Face) -> bool {
+        self.visible_colors[color.0 as usize]
+    }
+    /// Sets whether stickers of a color are visible. Stickers of a hidden color
+    /// are drawn like stickers on hidden pieces, regardless of whether their
+    /// piece is hidden.
+    pub fn set_color_visible(&mut self, color: Face, visible: bool) {
+        if self.is_color_visible(color) != visible {
+            self.visible_colors.set(color.0 as usize, visible);
+            // Hidden stickers may be culled from the geometry.
+            self.cached_geometry = None;
+        }
+    }
+
+    /// Returns the selected pieces and stickers.
+    pub fn selection(&self) -> &Selection {
         &self.selection
     }
     /// Toggles whether a sticker is selected.
     pub fn toggle_select(&mut self, sticker: Sticker) {
-        if self.selection.contains(&sticker) {
-            self.deselect(sticker)
-        } else {
-            self.select(sticker)
-        }
-    }
-    /// Selects a sticker.
-    pub fn select(&mut self, sticker: Sticker) {
-        self.selection.insert(sticker);
+        self.selection.toggle_sticker(sticker);
     }
-    /// Deselects a sticker.
-    pub fn deselect(&mut self, sticker: Sticker) {
-        self.selection.remove(&sticker);
+    /// Selects or deselects a set of whole pieces.
+    pub fn set_pieces_selected(&mut self, pieces: impl IntoIterator<Item = Piece>, selected: bool) {
+        for piece in pieces {
+            if selected {
+                self.selection.add_piece(piece);
+            } else {
+                self.selection.remove_piece(piece);
+            }
+        }
     }
-    /// Deselects all stickers.
+    /// Deselects all pieces and stickers.
     pub fn deselect_all(&mut self) {
-        self.selection = HashSet::new();
+        self.selection.clear();
+    }
+    /// Returns every selected sticker, including the stickers of selected
+    /// pieces.
+    fn selected_stickers(&self) -> Vec<Sticker> {
+        (0..self.stickers().len() as _)
+            .map(Sticker)
+            .filter(|&sticker| {
+                self.selection.has_sticker(sticker)
+                    || self.selection.has_piece(self.info(sticker).piece)
+            })
+            .collect()
+    }
+
+    /// Returns the color that a sticker is drawn with, which may have been
+    /// changed using `recolor_selection()`.
+    pub fn sticker_color(&self, sticker: Sticker) -> Face {
+        match self.sticker_color_overrides.get(&sticker) {
+            Some(&color) => color,
+            None => self.info(sticker).color,
+        }
+    }
+    /// Draws every selected sticker with a different color, or with its own
+    /// color again if `color` is `None`. This only changes how the stickers
+    /// look, not the puzzle state, so it is not part of the undo history and
+    /// is not saved in the log file.
+    pub fn recolor_selection(&mut self, color: Option<Face>) {
+        for sticker in self.selected_stickers() {
+            match color {
+                Some(color) => self.sticker_color_overrides.insert(sticker, color),
+                None => self.sticker_color_overrides.remove(&sticker),
+            };
+        }
     }
 
     /// Skips the animations for all twists in the queue.
@@ -746,6 +1191,9 @@ impl PuzzleController {
     /// Undoes one twist. Returns an error if there was nothing to undo or the
     /// twist could not be applied to the puzzle.
     pub fn undo(&mut self) -> Result<(), &'static str> {
+        if !self.has_undo() && self.history_base.is_some() {
+            return Err("Cannot undo past the undo depth limit");
+        }
         if let Some(entry) = self.undo_buffer.pop() {
             self.mark_unsaved();
             match entry {
@@ -755,6 +1203,7 @@ impl PuzzleController {
                 }
             }
             self.redo_buffer.push(entry);
+            self.enforce_undo_depth();
             Ok(())
         } else {
             Err("Nothing to undo")
@@ -769,12 +1218,43 @@ impl PuzzleController {
                 HistoryEntry::Twist(twist) => self.animate_twist(twist)?,
             }
             self.undo_buffer.push(entry);
+            self.enforce_undo_depth();
             Ok(())
         } else {
             Err("Nothing to redo")
         }
     }
 
+    /// Returns the number of twists done since the scramble, which is the
+    /// current position in the undo history.
+    pub fn history_position(&self) -> usize {
+        self.undo_buffer.len()
+    }
+    /// Returns the total number of twists in the undo history, including ones
+    /// that have been undone and can be redone.
+    pub fn history_len(&self) -> usize {
+        self.undo_buffer.len() + self.redo_buffer.len()
+    }
+    /// Undoes or redoes twists until the history position is `index`, clamped
+    /// to the length of the history. If `animate` is `false`, then all twists
+    /// are applied instantly.
+    ///
+    /// This takes time proportional to the distance between `index` and the
+    /// current position, so scrubbing back and forth is cheap.
+    pub fn goto_move(&mut self, index: usize, animate: bool) -> Result<(), &'static str> {
+        let index = index.min(self.history_len());
+        while self.undo_buffer.len() > index {
+            self.undo()?;
+        }
+        while self.undo_buffer.len() < index {
+            self.redo()?;
+        }
+        if !animate {
+            self.skip_twist_animations();
+        }
+        Ok(())
+    }
+
     /// Marks the puzzle as saved
     pub fn mark_saved(&mut self) {
         self.is_unsaved = false;
@@ -841,20 +1321,32 @@ impl PuzzleController {
 
     /// Returns the number of twists applied to the puzzle, not including the scramble.
     pub fn twist_count(&self, metric: TwistMetric) -> usize {
-        metric.count_twists(
-            self,
-            self.undo_buffer
-                .iter()
-                .copied()
-                .filter_map(HistoryEntry::twist),
-        )
+        let dropped_count = self.dropped_twist_counts.get(&metric).copied();
+        dropped_count.unwrap_or(0)
+            + metric.count_twists(
+                self,
+                self.undo_buffer
+                    .iter()
+                    .copied()
+                    .filter_map(HistoryEntry::twist),
+            )
     }
     /// Returns the moves used to scramble the puzzle.
     pub fn scramble(&self) -> &[Twist] {
         &self.scramble
     }
+    /// Returns the sticker pattern of the state at the start of the undo
+    /// history, if older entries have been dropped because of the undo depth
+    /// limit. In that case the scramble and initial pattern no longer lead to
+    /// the start of the undo history.
+    pub fn history_base_pattern(&self) -> Option<String> {
+        self.history_base
+            .as_ref()
+            .map(|base| base.sticker_pattern())
+    }
     /// Returns the twists and other actions applied to the puzzle, not
-    /// including the scramble.
+    /// including the scramble or any entries dropped because of the undo depth
+    /// limit.
     pub fn undo_buffer(&self) -> &[HistoryEntry] {
         &self.undo_buffer
     }
@@ -862,6 +1354,175 @@ impl PuzzleController {
     pub fn redo_buffer(&self) -> &[HistoryEntry] {
         &self.redo_buffer
     }
+
+    /// Sets the maximum number of entries that can be undone or redone, or 0
+    /// for no limit. Lowering the limit takes effect immediately.
+    pub fn set_max_undo_depth(&mut self, max_undo_depth: usize) {
+        if self.max_undo_depth != max_undo_depth {
+            self.max_undo_depth = max_undo_depth;
+            self.enforce_undo_depth();
+        }
+    }
+    /// Drops the undo and redo entries furthest from the current position that
+    /// are past the undo depth limit. Dropped undo entries are applied to the
+    /// state at the start of the undo history.
+    fn enforce_undo_depth(&mut self) {
+        if self.max_undo_depth == 0 {
+            return;
+        }
+
+        let excess = self.undo_buffer.len().saturating_sub(self.max_undo_depth);
+        if excess > 0 {
+            let mut base = match self.history_base.take() {
+                Some(base) => base,
+                None => {
+                    // Undo the whole history to find the state at its start.
+                    let reversed: Vec<Twist> = self
+                        .undo_buffer
+                        .iter()
+                        .rev()
+                        .filter_map(|&entry| entry.twist())
+                        .map(|twist| self.reverse_twist(twist))
+                        .collect();
+                    let mut state = self.puzzle.clone();
+                    if let Err(e) = state.apply_sequence(&reversed) {
+                        log::error!("error finding start of undo history: {}", e);
+                    }
+                    state
+                }
+            };
+
+            let dropped: Vec<Twist> = self
+                .undo_buffer
+                .drain(..excess)
+                .filter_map(HistoryEntry::twist)
+                .collect();
+            if let Err(e) = base.apply_sequence(&dropped) {
+                log::error!("error applying dropped undo history: {}", e);
+            }
+            self.history_base = Some(base);
+
+            for metric in TwistMetric::iter() {
+                let count = metric.count_twists(&*self, dropped.iter().copied());
+                *self.dropped_twist_counts.entry(metric).or_default() += count;
+            }
+
+            // Bookmarks store the whole twist history, so remove the dropped
+            // twists from the ones that include them.
+            let start = self.scramble.len();
+            let end = start + dropped.len();
+            for bookmark in &mut self.bookmarks {
+                if bookmark.twists.get(start..end) == Some(&dropped[..]) {
+                    bookmark.twists.drain(start..end);
+                }
+            }
+        }
+
+        let excess = self.redo_buffer.len().saturating_sub(self.max_undo_depth);
+        self.redo_buffer.drain(..excess);
+    }
+    /// Returns an estimate of the number of bytes of memory used by the
+    /// scramble and the undo and redo history, not including bookmarks.
+    pub fn history_memory_estimate(&self) -> usize {
+        std::mem::size_of::<Twist>() * self.scramble.capacity()
+            + std::mem::size_of::<HistoryEntry>()
+                * (self.undo_buffer.capacity() + self.redo_buffer.capacity())
+    }
+    fn is_reverse_of_last(&self, twist: Twist) -> bool {
+        self.undo_buffer.last() == Some(&self.reverse_twist(twist).into())
+    }
+
+    /// Returns every twist applied to the puzzle, including the scramble.
+    fn twist_history(&self) -> Vec<Twist> {
+        itertools::chain(
+            self.scramble.iter().copied(),
+            self.undo_buffer.iter().filter_map(|&entry| entry.twist()),
+        )
+        .collect()
+    }
+    /// Starts playing back a sequence of twists, animating each one for the
+    /// same amount of time regardless of the twist speed preferences. Twists
+    /// are added to the undo history as they are played. This replaces any
+    /// replay already in progress.
+    pub fn start_replay(&mut self, twists: impl IntoIterator<Item = Twist>, moves_per_second: f32) {
+        self.replay = Some(Replay {
+            queue: twists.into_iter().collect(),
+            moves_per_second,
+            speed_multiplier: 1.0,
+            paused: false,
+        });
+    }
+    /// Stops the replay in progress, if any. The twist currently being
+    /// animated still finishes.
+    pub fn stop_replay(&mut self) {
+        self.replay = None;
+    }
+    /// Returns the replay in progress, if any.
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+    /// Returns the replay in progress, if any, so that it can be paused,
+    /// resumed, or sped up.
+    pub fn replay_mut(&mut self) -> Option<&mut Replay> {
+        self.replay.as_mut()
+    }
+
+    /// Returns the saved bookmarks, in the order they were made.
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+    /// Saves the current state of the puzzle as a bookmark.
+    pub fn bookmark(&mut self, name: String) -> BookmarkId {
+        let id = BookmarkId(self.next_bookmark_id);
+        self.next_bookmark_id += 1;
+        let twists = self.twist_history();
+        self.bookmarks.push(Bookmark { id, name, twists });
+        id
+    }
+    /// Renames a bookmark.
+    pub fn rename_bookmark(&mut self, id: BookmarkId, name: String) {
+        if let Some(bookmark) = self.bookmarks.iter_mut().find(|b| b.id == id) {
+            bookmark.name = name;
+        }
+    }
+    /// Deletes a bookmark.
+    pub fn remove_bookmark(&mut self, id: BookmarkId) {
+        self.bookmarks.retain(|b| b.id != id);
+    }
+    /// Returns the puzzle to the state saved in a bookmark by undoing twists
+    /// back to where the current history and the bookmark diverge and then
+    /// redoing the bookmark's twists. These are added to the undo history, so
+    /// restoring a bookmark can itself be undone.
+    pub fn restore_bookmark(&mut self, id: BookmarkId) -> Result<(), &'static str> {
+        let bookmark_twists = self
+            .bookmarks
+            .iter()
+            .find(|b| b.id == id)
+            .ok_or("No such bookmark")?
+            .twists
+            .clone();
+        let current_twists = self.twist_history();
+
+        let common_len = std::iter::zip(&current_twists, &bookmark_twists)
+            .take_while(|(a, b)| a == b)
+            .count();
+        if common_len < self.scramble.len() {
+            return Err("Bookmark is from a different scramble");
+        }
+
+        let sequence: Vec<Twist> = itertools::chain(
+            current_twists[common_len..]
+                .iter()
+                .rev()
+                .map(|&twist| self.reverse_twist(twist)),
+            bookmark_twists[common_len..].iter().copied(),
+        )
+        .collect();
+        match self.twist_sequence_instantly(sequence).first() {
+            Some(&e) => Err(e),
+            None => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -878,16 +1539,55 @@ impl TwistAnimationState {
     fn proceed(&mut self, delta_t: f32) -> Option<Quaternion<f32>> {
         self.progress += delta_t;
         if self.progress >= 1.0 {
-            self.progress = 0.0;
-            self.queue
+            let ret = self
+                .queue
                 .pop_front()
-                .map(|anim| anim.view_angle_offset_delta)
+                .map(|anim| anim.view_angle_offset_delta);
+            // Carry leftover progress over to the next twist so that the total
+            // duration of a sequence of twists doesn't depend on frame rate.
+            if self.queue.is_empty() {
+                self.progress = 0.0;
+            } else {
+                self.progress = (self.progress - 1.0).min(1.0);
+            }
+            ret
         } else {
             None
         }
     }
 }
 
+/// Playback of a sequence of twists at a constant rate.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// Twists that have not been started yet.
+    queue: VecDeque<Twist>,
+    /// Number of twists to play per second, before the speed multiplier.
+    pub moves_per_second: f32,
+    /// Multiplier for the replay speed.
+    pub speed_multiplier: f32,
+    /// Whether playback is paused, including the twist being animated.
+    pub paused: bool,
+}
+impl Replay {
+    /// Returns the number of twists that have not been started yet.
+    pub fn remaining(&self) -> usize {
+        self.queue.len()
+    }
+    fn twists_per_second(&self) -> f32 {
+        self.moves_per_second * self.speed_multiplier
+    }
+}
+
+/// Effect of a twist applied using `PuzzleController::apply_twist()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwistResult {
+    /// Pieces moved by the twist.
+    pub moved: Vec<Piece>,
+    /// Transformation applied to the moved pieces, in puzzle space.
+    pub transform: Matrix4<f32>,
+}
+
 #[derive(Debug, Clone)]
 struct TwistAnimation {
     /// Puzzle state before twist.
@@ -959,6 +1659,10 @@ pub struct ViewAngleAnimState {
     /// Whether to freeze the view angle offset, versus animating it back to
     /// zero.
     is_frozen: bool,
+    /// Axis that the view angle offset was last dragged around, if the drag
+    /// was constrained. Smart realign only rotates the puzzle around this
+    /// axis.
+    drag_axis: Option<DragAxis>,
 }
 impl Default for ViewAngleAnimState {
     fn default() -> Self {
@@ -968,6 +1672,7 @@ impl Default for ViewAngleAnimState {
 
             transient_rotation: None,
             is_frozen: false,
+            drag_axis: None,
         }
     }
 }
@@ -994,6 +1699,20 @@ impl HistoryEntry {
     }
 }
 
+/// ID of a bookmark, unique within a `PuzzleController`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BookmarkId(u32);
+
+/// Saved puzzle state that can be returned to later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub id: BookmarkId,
+    pub name: String,
+    /// Every twist applied to the puzzle when the bookmark was made, including
+    /// the scramble.
+    twists: Vec<Twist>,
+}
+
 /// Whether the puzzle has been scrambled.
 #[derive(FromPrimitive, Debug, Default, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
@@ -1009,6 +1728,40 @@ pub enum ScrambleState {
     Solved = 3,
 }
 
+/// Set of selected pieces and stickers. Selecting a piece is not the same as
+/// selecting all of its stickers; they are drawn with different outlines.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub pieces: HashSet<Piece>,
+    pub stickers: HashSet<Sticker>,
+}
+impl Selection {
+    /// Returns whether a whole piece is selected.
+    pub fn has_piece(&self, piece: Piece) -> bool {
+        self.pieces.contains(&piece)
+    }
+    /// Returns whether a sticker is selected on its own.
+    pub fn has_sticker(&self, sticker: Sticker) -> bool {
+        self.stickers.contains(&sticker)
+    }
+
+    pub fn add_piece(&mut self, piece: Piece) {
+        self.pieces.insert(piece);
+    }
+    pub fn remove_piece(&mut self, piece: Piece) {
+        self.pieces.remove(&piece);
+    }
+    pub fn toggle_sticker(&mut self, sticker: Sticker) {
+        if !self.stickers.remove(&sticker) {
+            self.stickers.insert(sticker);
+        }
+    }
+    pub fn clear(&mut self) {
+        self.pieces.clear();
+        self.stickers.clear();
+    }
+}
+
 /// Which parts of the puzzle to twist.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Grip {
@@ -1104,14 +1857,29 @@ pub struct VisualPieceState {
     pub hovered: f32,
 
     hidden_opacity_override: Option<f32>,
+    /// Sticker to highlight when hovered, or `None` to highlight the whole
+    /// piece.
+    hovered_sticker: Option<Sticker>,
 }
 impl VisualPieceState {
-    pub fn outline_color(self, prefs: &Preferences, is_sticker_selected: bool) -> egui::Rgba {
+    pub fn outline_color(
+        self,
+        prefs: &Preferences,
+        sticker_color: egui::Rgba,
+        is_sticker_selected: bool,
+    ) -> egui::Rgba {
         let pr = &prefs.outlines;
 
         let hidden_or_ungripped = f32::max(self.hidden, self.ungripped);
 
-        let mut ret = egui::Rgba::from(pr.default_color);
+        let mut ret = match pr.color_mode {
+            OutlineColorMode::Fixed => egui::Rgba::from(pr.default_color),
+            OutlineColorMode::Sticker => egui::Rgba::from_rgb(
+                sticker_color.r() * STICKER_OUTLINE_BRIGHTNESS,
+                sticker_color.g() * STICKER_OUTLINE_BRIGHTNESS,
+                sticker_color.b() * STICKER_OUTLINE_BRIGHTNESS,
+            ),
+        };
         // In order from lowest to highest priority:
         ret = util::mix(ret, egui::Rgba::from(pr.hidden_color), hidden_or_ungripped);
         ret = util::mix(ret, egui::Rgba::from(pr.hovered_color), self.hovered);
@@ -1136,6 +1904,9 @@ impl VisualPieceState {
         ret = util::mix(ret, pr.hidden_size, hidden_or_ungripped);
         ret = util::mix(ret, pr.selected_size, self.selected);
         ret = util::mix(ret, pr.hovered_size, self.hovered);
+        if prefs.accessibility.high_contrast {
+            ret *= HIGH_CONTRAST_OUTLINE_SCALE;
+        }
         ret
     }
     pub fn opacity(self, prefs: &Preferences) -> f32 {
@@ -1164,3 +1935,275 @@ impl VisualPieceState {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_twist_animation_frame_rate_independence() {
+        let prefs = InteractionPreferences {
+            dynamic_twist_speed: false,
+            twist_duration: 0.2,
+            ..Default::default()
+        };
+        let twist_count = 10;
+        let expected = prefs.twist_duration * twist_count as f32;
+
+        for fps in [30.0, 60.0, 144.0] {
+            let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+            let twist = puzzle.notation_scheme().parse_twist("R").unwrap();
+            for _ in 0..twist_count {
+                puzzle.twist_no_collapse(twist).unwrap();
+            }
+
+            let frame = Duration::from_secs_f32(1.0 / fps);
+            let mut elapsed = 0.0;
+            while puzzle.current_twist().is_some() {
+                puzzle.update_geometry(frame, &prefs);
+                elapsed += frame.as_secs_f32();
+            }
+            assert!(
+                (elapsed - expected).abs() <= frame.as_secs_f32() + 0.001,
+                "{twist_count} twists took {elapsed}s at {fps} FPS instead of {expected}s",
+            );
+        }
+    }
+
+    #[test]
+    fn test_replay_constant_rate() {
+        let prefs = InteractionPreferences {
+            dynamic_twist_speed: true,
+            dynamic_twist_ramp: 0.5,
+            dynamic_twist_max_multiplier: 100.0,
+            twist_duration: 0.2,
+            ..Default::default()
+        };
+        let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+        let twist = puzzle.notation_scheme().parse_twist("R").unwrap();
+        puzzle.start_replay(vec![twist; 10], 4.0);
+
+        let frame = Duration::from_secs_f32(1.0 / 60.0);
+        let mut elapsed = 0.0;
+        while puzzle.replay().is_some() || puzzle.current_twist().is_some() {
+            puzzle.update_geometry(frame, &prefs);
+            elapsed += frame.as_secs_f32();
+        }
+        assert_eq!(puzzle.history_position(), 10);
+        // Each twist takes 0.25s, plus up to a frame between twists.
+        assert!(
+            (elapsed - 2.5).abs() <= 11.0 * frame.as_secs_f32(),
+            "replay took {elapsed}s instead of 2.5s",
+        );
+    }
+
+    #[test]
+    fn test_scramble_weighted() {
+        let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+        assert!(puzzle.scramble_n_weighted(10, &[0.0; 6]).is_err());
+
+        // Only twist R. Canonicalization may turn some of those into L twists.
+        let r = puzzle.twist_axis_from_name("R").unwrap();
+        let l = puzzle.opposite_twist_axis(r).unwrap();
+        let mut weights = [0.0; 6];
+        weights[r.0 as usize] = 1.0;
+        puzzle.scramble_n_weighted(20, &weights).unwrap();
+        assert_eq!(puzzle.scramble_state(), ScrambleState::Partial);
+        assert!(puzzle
+            .scramble()
+            .iter()
+            .all(|twist| twist.axis == r || twist.axis == l));
+    }
+
+    #[test]
+    fn test_apply_twist() {
+        let ty = PuzzleTypeEnum::Rubiks3D { layer_count: 3 };
+        let mut animated = PuzzleController::new(ty);
+        let mut instant = PuzzleController::new(ty);
+        let notation = animated.notation_scheme();
+        let twists = ["R", "{1-2}U", "F'"].map(|s| notation.parse_twist(s).unwrap());
+        for twist in twists {
+            let expected_moved = (0..animated.pieces().len() as _)
+                .map(Piece)
+                .filter(|&piece| {
+                    twist.layers[animated.latest().layer_from_twist_axis(twist.axis, piece)]
+                })
+                .collect::<Vec<_>>();
+
+            let animated_result = animated.apply_twist(twist).unwrap();
+            let instant_result = instant.apply_twist(twist).unwrap();
+            instant.skip_twist_animations();
+            assert_eq!(animated_result, instant_result);
+            assert_eq!(animated_result.moved, expected_moved);
+        }
+        assert_eq!(animated.latest(), instant.latest());
+    }
+
+    #[test]
+    fn test_scramble_skips_rotations() {
+        let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+        let notation = puzzle.notation_scheme();
+        let twists = ["x", "R", "y2", "U'"].map(|s| notation.parse_twist(s).unwrap());
+        let mut i = 0;
+        puzzle
+            .scramble_n_with(2, || {
+                i += 1;
+                twists[(i - 1) % twists.len()]
+            })
+            .unwrap();
+        let expected = [twists[1], twists[3]].map(|t| puzzle.canonicalize_twist(t));
+        assert_eq!(puzzle.scramble(), &expected);
+    }
+
+    #[test]
+    fn test_restore_bookmark() {
+        let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+        let [r, u, f, l] =
+            ["R", "U", "F", "L"].map(|name| puzzle.notation_scheme().parse_twist(name).unwrap());
+
+        puzzle.twist(r).unwrap();
+        puzzle.twist(u).unwrap();
+        let id = puzzle.bookmark("branch".to_string());
+        let bookmarked = puzzle.latest().clone();
+
+        puzzle.undo().unwrap();
+        puzzle.twist(f).unwrap();
+        puzzle.twist(l).unwrap();
+        let before_restore = puzzle.latest().clone();
+        let history_len = puzzle.history_len();
+
+        puzzle.restore_bookmark(id).unwrap();
+        assert_eq!(*puzzle.latest(), bookmarked);
+        // Undo L and F, then redo U.
+        assert_eq!(puzzle.history_len(), history_len + 3);
+
+        for _ in 0..3 {
+            puzzle.undo().unwrap();
+        }
+        assert_eq!(*puzzle.latest(), before_restore);
+    }
+
+    #[test]
+    fn test_undo_depth_limit() {
+        let ty = PuzzleTypeEnum::Rubiks3D { layer_count: 3 };
+        let mut puzzle = PuzzleController::new(ty);
+        let [r, u] = ["R", "U"].map(|name| puzzle.notation_scheme().parse_twist(name).unwrap());
+        let twists = [r, u].repeat(5);
+
+        puzzle.set_max_undo_depth(3);
+        for &twist in &twists {
+            puzzle.twist_no_collapse(twist).unwrap();
+        }
+        let latest = puzzle.latest().clone();
+        assert_eq!(puzzle.undo_buffer().len(), 3);
+        assert_eq!(puzzle.twist_count(TwistMetric::Stm), 10);
+
+        // Undo stops at the limit, at the state after the dropped twists.
+        for _ in 0..3 {
+            puzzle.undo().unwrap();
+        }
+        assert!(!puzzle.has_undo());
+        assert!(puzzle.undo().is_err());
+        let mut expected = Puzzle::new(ty);
+        expected.apply_sequence(&twists[..7]).unwrap();
+        assert_eq!(*puzzle.latest(), expected);
+        assert_eq!(puzzle.redo_buffer().len(), 3);
+
+        for _ in 0..3 {
+            puzzle.redo().unwrap();
+        }
+        assert_eq!(*puzzle.latest(), latest);
+
+        // The log file starts from the state after the dropped twists.
+        let log = crate::logfile::serialize(&puzzle, crate::logfile::LogFileFormat::Hsc).unwrap();
+        let (loaded, warnings) = crate::logfile::deserialize(&log).unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(loaded.latest().sticker_pattern(), latest.sticker_pattern());
+        assert_eq!(loaded.undo_buffer().len(), 3);
+    }
+
+    #[test]
+    fn test_recolor_selection() {
+        let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+        let color = Face(0);
+        let piece = Piece(0);
+        let piece_stickers = puzzle.info(piece).stickers.to_vec();
+        let mut other_stickers = (0..puzzle.stickers().len() as _)
+            .map(Sticker)
+            .filter(|&s| puzzle.info(s).piece != piece && puzzle.info(s).color != color);
+        let (selected_sticker, unselected_sticker) = (
+            other_stickers.next().unwrap(),
+            other_stickers.next().unwrap(),
+        );
+
+        puzzle.set_pieces_selected([piece], true);
+        puzzle.toggle_select(selected_sticker);
+        puzzle.recolor_selection(Some(color));
+        for &sticker in piece_stickers.iter().chain([&selected_sticker]) {
+            assert_eq!(puzzle.sticker_color(sticker), color);
+        }
+        assert_ne!(puzzle.sticker_color(unselected_sticker), color);
+
+        // Clearing overrides only affects the selection.
+        puzzle.deselect_all();
+        puzzle.set_pieces_selected([piece], true);
+        puzzle.recolor_selection(None);
+        for &sticker in &piece_stickers {
+            assert_eq!(puzzle.sticker_color(sticker), puzzle.info(sticker).color);
+        }
+        assert_eq!(puzzle.sticker_color(selected_sticker), color);
+
+        puzzle.reset();
+        assert_ne!(puzzle.sticker_color(selected_sticker), color);
+    }
+
+    #[test]
+    fn test_grip_combination() {
+        use winit::event::VirtualKeyCode;
+
+        let prefs = InteractionPreferences {
+            grip_on_hover: true,
+            ..Default::default()
+        };
+        let mut puzzle = PuzzleController::new(PuzzleTypeEnum::Rubiks3D { layer_count: 3 });
+        let [r, u] = ["R", "U"].map(|name| puzzle.notation_scheme().parse_twist(name).unwrap());
+        let grip = |axes: &[Twist], layers| Grip {
+            axes: axes.iter().map(|t| t.axis).collect(),
+            layers,
+        };
+
+        let click_twists = ClickTwists {
+            cw: Some(r),
+            ccw: None,
+            recenter: None,
+        };
+        puzzle.update_hovered_sticker([(Sticker(0), click_twists)], &prefs);
+        let hover_grip = grip(&[r], Some(r.layers));
+        assert_eq!(puzzle.grip(), &hover_grip);
+        assert_eq!(puzzle.keyboard_grip(), &Grip::default());
+
+        // A keyboard grip wins over the hover grip even if it only grips
+        // layers.
+        let shift = Key::Vk(VirtualKeyCode::LShift);
+        puzzle.set_held_grip(shift, Grip::with_layers(LayerMask(2)), &prefs);
+        assert_eq!(puzzle.grip(), &Grip::with_layers(LayerMask(2)));
+
+        // Axes from held and toggled grips are combined, but held layers take
+        // priority over toggled layers.
+        puzzle.set_toggled_grip(grip(&[u], Some(LayerMask(1))), &prefs);
+        assert_eq!(puzzle.grip(), &grip(&[u], Some(LayerMask(2))));
+        let a = Key::Vk(VirtualKeyCode::A);
+        puzzle.set_held_grip(a, Grip::with_axis(r.axis), &prefs);
+        assert_eq!(puzzle.grip(), &grip(&[r, u], Some(LayerMask(2))));
+
+        puzzle.release_held_grips(|k| k == shift, &prefs);
+        assert_eq!(puzzle.grip(), &grip(&[r, u], Some(LayerMask(1))));
+        puzzle.release_held_grips(|k| k == a, &prefs);
+        assert_eq!(puzzle.grip(), &grip(&[u], Some(LayerMask(1))));
+
+        // Clearing the toggled grip sticks, and the hover grip shows through.
+        puzzle.clear_grip();
+        assert_eq!(puzzle.keyboard_grip(), &Grip::default());
+        assert_eq!(puzzle.grip(), &hover_grip);
+    }
+}
@@ -0,0 +1,101 @@
+//! Export of puzzle geometry for rendering in other programs.
+
+use std::fmt::{self, Write};
+
+use super::*;
+use crate::preferences::Preferences;
+
+/// Puzzle geometry in Wavefront OBJ format.
+#[derive(Debug, Default, Clone)]
+pub struct ObjExport {
+    /// Contents of the `.obj` file.
+    pub obj: String,
+    /// Contents of the `.mtl` file, which defines one material per color.
+    pub mtl: String,
+}
+
+/// Exports the puzzle as it is currently displayed, after 4D projection but
+/// before 3D perspective projection. Each piece is a group, and each sticker
+/// uses the material of its color. Hidden pieces and colors are only included
+/// if `include_hidden` is `true`.
+///
+/// `mtl_file_name` is the name that the `.obj` file should use to refer to the
+/// `.mtl` file.
+pub fn export_obj(
+    puzzle: &mut PuzzleController,
+    prefs: &Preferences,
+    mtl_file_name: &str,
+    include_hidden: bool,
+) -> ObjExport {
+    let params = puzzle.geometry_params(prefs);
+    let face_colors = prefs.face_colors_list(puzzle.ty());
+
+    let mut ret = ObjExport::default();
+    write_obj(&mut ret.obj, puzzle, params, mtl_file_name, include_hidden)
+        .and_then(|()| write_mtl(&mut ret.mtl, puzzle.faces(), &face_colors))
+        .expect("error writing to string");
+    ret
+}
+
+fn write_obj(
+    out: &mut String,
+    puzzle: &PuzzleController,
+    params: StickerGeometryParams,
+    mtl_file_name: &str,
+    include_hidden: bool,
+) -> fmt::Result {
+    writeln!(out, "# {} exported from {}", puzzle.name(), crate::TITLE)?;
+    writeln!(out, "mtllib {mtl_file_name}")?;
+
+    // OBJ vertex indices start at 1.
+    let mut vertex_offset = 1;
+    for piece in (0..puzzle.pieces().len() as _).map(Piece) {
+        let is_piece_hidden = puzzle.logical_piece_state(piece).hidden;
+        if is_piece_hidden && !include_hidden {
+            continue;
+        }
+
+        writeln!(out, "g piece_{}", piece.0)?;
+        for &sticker in &puzzle.info(piece).stickers {
+            let color = puzzle.info(sticker).color;
+            if !puzzle.is_color_visible(color) && !include_hidden {
+                continue;
+            }
+            let geom = match puzzle.displayed().sticker_geometry(sticker, params) {
+                Some(g) => g,
+                None => continue, // invisible; skip this sticker
+            };
+
+            let material = material_name(puzzle.info(puzzle.sticker_color(sticker)));
+            writeln!(out, "usemtl {material}")?;
+            for v in &geom.verts {
+                writeln!(out, "v {} {} {}", v.x, v.y, v.z)?;
+            }
+            for indices in &geom.polygon_indices {
+                write!(out, "f")?;
+                for &i in indices.iter() {
+                    write!(out, " {}", vertex_offset + i as usize)?;
+                }
+                writeln!(out)?;
+            }
+            vertex_offset += geom.verts.len();
+        }
+    }
+
+    Ok(())
+}
+
+fn write_mtl(out: &mut String, faces: &[FaceInfo], face_colors: &[egui::Color32]) -> fmt::Result {
+    for (face, &color) in faces.iter().zip(face_colors) {
+        // OBJ colors are linear.
+        let color = egui::Rgba::from(color);
+        writeln!(out, "newmtl {}", material_name(face))?;
+        writeln!(out, "Kd {} {} {}", color.r(), color.g(), color.b())?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn material_name(face: &FaceInfo) -> String {
+    format!("face_{}", face.symbol)
+}
@@ -7,7 +7,7 @@ use std::cmp::Ordering;
 
 use super::{ClickTwists, PuzzleType, PuzzleTypeEnum, Sticker, Twist};
 use crate::preferences::ViewPreferences;
-use crate::util::{self, IterCyclicPairsExt};
+use crate::util::{self, Aabb, IterCyclicPairsExt};
 
 const W_NEAR_CLIPPING_DIVISOR: f32 = 0.1;
 const Z_NEAR_CLIPPING_DIVISOR: f32 = 0.0;
@@ -32,6 +32,10 @@ pub struct StickerGeometryParams {
     /// `(sticker width) / (puzzle diameter)`. Ranges from 0.0 to 1.0.
     pub sticker_scale: f32,
 
+    /// How far the faces are unfolded into a flat net, from 0.0 (assembled)
+    /// to 1.0 (flat).
+    pub unfold_amount: f32,
+
     /// 4D FOV, in degrees.
     pub fov_4d: f32,
     /// 3D FOV, in degrees.
@@ -104,6 +108,8 @@ impl StickerGeometryParams {
             face_scale,
             sticker_scale,
 
+            unfold_amount: view_prefs.unfold_amount,
+
             fov_4d: view_prefs.fov_4d,
             fov_3d: view_prefs.fov_3d,
             w_factor_4d: (view_prefs.fov_4d.to_radians() / 2.0).tan(),
@@ -244,6 +250,43 @@ impl ProjectedStickerGeometry {
             .find(|polygon| polygon.contains_point(point))
             .map(|polygon| polygon.twists)
     }
+
+    /// Returns the bounding box of the sticker in screen space, ignoring depth.
+    pub(crate) fn bounds_2d(&self) -> Aabb<2> {
+        Aabb {
+            min: [self.min_bound.x, self.min_bound.y],
+            max: [self.max_bound.x, self.max_bound.y],
+        }
+    }
+
+    /// Returns whether the sticker is facing forward and its center is within
+    /// a rectangle.
+    pub(crate) fn is_in_rect(&self, min: Point2<f32>, max: Point2<f32>) -> bool {
+        let center_x = (self.min_bound.x + self.max_bound.x) / 2.0;
+        let center_y = (self.min_bound.y + self.max_bound.y) / 2.0;
+        !self.front_polygons.is_empty()
+            && (min.x..=max.x).contains(&center_x)
+            && (min.y..=max.y).contains(&center_y)
+    }
+
+    /// Returns the average of the vertices of the sticker's front-facing
+    /// polygons, or `None` if the sticker is not facing forward.
+    pub(crate) fn front_centroid(&self) -> Option<Point2<f32>> {
+        let verts = self
+            .front_polygons
+            .iter()
+            .flat_map(|polygon| &polygon.verts);
+        let count = verts.clone().count();
+        let sum = verts.fold(Vector2::zero(), |sum, v| sum + vec2(v.x, v.y));
+        (count > 0).then(|| Point2::from_vec(sum / count as f32))
+    }
+    /// Returns whether any of the sticker's polygons covers a point.
+    pub(crate) fn covers_point(&self, point: Point2<f32>) -> bool {
+        self.front_polygons
+            .iter()
+            .chain(&*self.back_polygons)
+            .any(|polygon| polygon.contains_point(point))
+    }
 }
 
 #[derive(Debug, Clone)]
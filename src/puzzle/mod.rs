@@ -4,13 +4,18 @@
 mod common;
 
 pub mod controller;
+pub mod export;
 pub mod geometry;
 pub mod notation;
+pub mod permutations;
 pub mod rubiks_3d;
 pub mod rubiks_4d;
+#[allow(unused)] // Only used by tests for now.
+pub mod selftest;
 
 pub use common::*;
 pub use controller::*;
+pub use export::*;
 pub use geometry::*;
 pub use notation::*;
 pub use rubiks_3d::Rubiks3D;
@@ -22,6 +27,8 @@ pub mod traits {
 
 #[cfg(test)]
 mod tests {
+    use instant::Instant;
+    use itertools::Itertools;
     use std::collections::HashSet;
 
     use super::*;
@@ -92,6 +99,94 @@ mod tests {
         }
     }
 
+    /// Test that every twist is undone by its reverse.
+    pub(super) fn test_twist_reverse_consistency(p: &impl PuzzleType) {
+        for twist in iter_all_twists(p) {
+            assert!(
+                p.is_reverse_twist_consistent(twist),
+                "Reverse of {:?} does not undo it for {}",
+                twist,
+                p.name(),
+            );
+        }
+    }
+
+    /// Test basic invariants for every puzzle type.
+    #[test]
+    fn test_all_puzzles_self_consistent() {
+        for report in selftest::validate_all() {
+            assert!(
+                report.is_ok(),
+                "{} is inconsistent: {:#?}",
+                report.ty.name(),
+                report.failures,
+            );
+        }
+    }
+
+    const APPLY_SEQUENCE_PUZZLE_TYPES: [PuzzleTypeEnum; 3] = [
+        PuzzleTypeEnum::Rubiks3D { layer_count: 3 },
+        PuzzleTypeEnum::Rubiks3D { layer_count: 7 },
+        PuzzleTypeEnum::Rubiks4D { layer_count: 3 },
+    ];
+    const APPLY_SEQUENCE_LEN: usize = 500;
+
+    /// Test that applying a sequence of twists at once produces the same state
+    /// as applying them one at a time.
+    #[test]
+    fn test_apply_sequence() {
+        for ty in APPLY_SEQUENCE_PUZZLE_TYPES {
+            let seq = (0..APPLY_SEQUENCE_LEN)
+                .map(|_| Twist::from_rng(ty))
+                .collect_vec();
+
+            let mut individual = Puzzle::new(ty);
+            for &twist in &seq {
+                individual.twist(twist).unwrap();
+            }
+
+            let mut batched = Puzzle::new(ty);
+            batched.apply_sequence(&seq).unwrap();
+
+            assert_eq!(individual, batched, "Mismatch for {}", ty.name());
+        }
+    }
+
+    /// Report how long it takes to apply a sequence of twists one at a time
+    /// versus all at once. Run with `cargo test --release bench_apply_sequence
+    /// -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_apply_sequence() {
+        const ITERATIONS: u32 = 20;
+
+        for ty in APPLY_SEQUENCE_PUZZLE_TYPES {
+            let seq = (0..APPLY_SEQUENCE_LEN)
+                .map(|_| Twist::from_rng(ty))
+                .collect_vec();
+
+            let start = Instant::now();
+            for _ in 0..ITERATIONS {
+                let mut p = Puzzle::new(ty);
+                for &twist in &seq {
+                    p.twist(twist).unwrap();
+                }
+            }
+            let individual = start.elapsed() / ITERATIONS;
+
+            let start = Instant::now();
+            for _ in 0..ITERATIONS {
+                Puzzle::new(ty).apply_sequence(&seq).unwrap();
+            }
+            let batched = start.elapsed() / ITERATIONS;
+
+            eprintln!(
+                "{}: {individual:?} individually, {batched:?} batched",
+                ty.name(),
+            );
+        }
+    }
+
     fn iter_all_twists(p: &impl PuzzleType) -> impl Iterator<Item = Twist> {
         itertools::iproduct!(
             (0..p.twist_axes().len() as _).map(TwistAxis),
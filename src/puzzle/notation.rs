@@ -10,19 +10,29 @@ pub struct NotationScheme {
     pub(super) direction_names: Vec<TwistDirectionName>,
     pub(super) block_suffix: Option<String>,
     pub(super) aliases: Vec<(String, Alias)>,
+    /// Symbols for whole-puzzle rotations, each with a twist that it stands
+    /// for. A symbol may appear multiple times with equivalent twists (such as
+    /// `x` for both `R` and `L'` on every layer); the first one is used when
+    /// parsing. Unlike aliases, rotation symbols must match the whole twist
+    /// and do not take a direction suffix, so every direction is listed
+    /// separately.
+    ///
+    /// In 3D, rotations are named `x`, `y`, and `z` after the axis they rotate
+    /// around. In 4D there is no single axis to rotate around, so rotations
+    /// are named after the plane they rotate in: `xy` rotates from the X axis
+    /// toward the Y axis, and `xy2` rotates twice as far.
+    pub(super) rotations: Vec<(String, Twist)>,
     // TODO: flag to allow chaining directions (e.g., "Rxyx'y")
 }
 
 #[derive(Debug, Copy, Clone)]
 pub(super) enum Alias {
     AxisLayers(TwistAxis, LayerMask),
-    EntireTwist(Twist),
 }
 impl Alias {
     fn matches(self, twist: Twist) -> bool {
         match self {
             Alias::AxisLayers(axis, layers) => axis == twist.axis && layers == twist.layers,
-            Alias::EntireTwist(t) => t == twist,
         }
     }
 }
@@ -62,16 +72,16 @@ impl NotationScheme {
     }
 
     pub fn format_twist(&self, f: &mut fmt::Formatter<'_>, twist: Twist) -> fmt::Result {
-        // First, try searching for a relevant alias.
+        // Whole-puzzle rotations have their own symbols.
+        if let Some((rotation_str, _)) = self.rotations.iter().find(|(_, t)| *t == twist) {
+            return write!(f, "{rotation_str}");
+        }
+
+        // Next, try searching for a relevant alias.
         for (alias_str, alias) in &self.aliases {
             if alias.matches(twist) {
                 write!(f, "{alias_str}")?;
-                match alias {
-                    Alias::AxisLayers(..) => {
-                        return self.format_direction(f, twist.axis, twist.direction)
-                    }
-                    Alias::EntireTwist(..) => return Ok(()),
-                }
+                return self.format_direction(f, twist.axis, twist.direction);
             }
         }
 
@@ -115,6 +125,11 @@ impl NotationScheme {
     pub fn parse_twist(&self, s: &str) -> Result<Twist, String> {
         const GENERIC_ERR_MSG: &str = "error parsing twist";
 
+        // Check for whole-puzzle rotations.
+        if let Some(&(_, twist)) = self.rotations.iter().find(|(r, _)| r == s) {
+            return Ok(twist);
+        }
+
         // Check for aliases.
         let matching_alias = strip_any_prefix(
             s,
@@ -132,13 +147,6 @@ impl NotationScheme {
                         layers,
                     })
                 }
-                Alias::EntireTwist(twist) => {
-                    if remaining.is_empty() {
-                        Ok(twist)
-                    } else {
-                        Err(GENERIC_ERR_MSG.to_string())
-                    }
-                }
             }
         } else {
             // Parse layer mask if present.
@@ -167,6 +175,28 @@ impl NotationScheme {
         }
     }
 
+    /// Parses a sequence of twists separated by whitespace. Commutators
+    /// `[A, B]` expand to `A B A' B'` and conjugates `[A: B]` expand to
+    /// `A B A'`, where `A` and `B` are themselves sequences. `reverse_twist` is
+    /// used to invert sequences.
+    pub fn parse_sequence(
+        &self,
+        s: &str,
+        reverse_twist: impl Fn(Twist) -> Twist,
+    ) -> Result<Vec<Twist>, String> {
+        let mut parser = SequenceParser {
+            scheme: self,
+            reverse_twist,
+            s,
+            pos: 0,
+        };
+        let ret = parser.parse_sequence()?;
+        match parser.peek() {
+            None => Ok(ret),
+            Some(c) => Err(format!("unexpected {c:?} at column {}", parser.column())),
+        }
+    }
+
     fn parse_twist_direction(
         &self,
         axis: TwistAxis,
@@ -213,6 +243,103 @@ impl NotationScheme {
     }
 }
 
+/// Recursive-descent parser for twist sequences with commutators and
+/// conjugates.
+struct SequenceParser<'a, F> {
+    scheme: &'a NotationScheme,
+    reverse_twist: F,
+    s: &'a str,
+    /// Byte index of the next character to parse.
+    pos: usize,
+}
+impl<F: Fn(Twist) -> Twist> SequenceParser<'_, F> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+    /// Returns the 1-indexed column of the next character to parse.
+    fn column(&self) -> usize {
+        self.s[..self.pos].chars().count() + 1
+    }
+    fn skip_whitespace(&mut self) {
+        let rest = &self.s[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Parses twists and brackets up to the end of the string or the next
+    /// unmatched `,`, `:`, or `]`.
+    fn parse_sequence(&mut self) -> Result<Vec<Twist>, String> {
+        let mut ret = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(',' | ':' | ']') => return Ok(ret),
+                Some('[') => ret.extend(self.parse_bracket()?),
+                Some(_) => ret.push(self.parse_twist()?),
+            }
+        }
+    }
+    /// Parses a commutator or conjugate, starting at its opening `[`.
+    fn parse_bracket(&mut self) -> Result<Vec<Twist>, String> {
+        let start_column = self.column();
+        let unmatched_err = || format!("unmatched '[' at column {start_column}");
+
+        self.pos += 1; // Skip `[`.
+        let a = self.parse_sequence()?;
+        let separator = match self.peek() {
+            Some(c @ (',' | ':')) => c,
+            Some(_) => {
+                return Err(format!(
+                    "expected ',' or ':' in bracket at column {start_column}",
+                ))
+            }
+            None => return Err(unmatched_err()),
+        };
+        self.pos += 1; // Skip separator.
+        let b = self.parse_sequence()?;
+        match self.peek() {
+            Some(']') => self.pos += 1,
+            Some(c) => return Err(format!("unexpected {c:?} at column {}", self.column())),
+            None => return Err(unmatched_err()),
+        }
+
+        let inv = |seq: &[Twist]| {
+            seq.iter()
+                .rev()
+                .map(|&t| (self.reverse_twist)(t))
+                .collect_vec()
+        };
+        Ok(match separator {
+            ',' => [a.clone(), b.clone(), inv(&a), inv(&b)].concat(),
+            _ => [a.clone(), b, inv(&a)].concat(),
+        })
+    }
+    /// Parses a single twist. Layer masks in braces may contain characters
+    /// that would otherwise end the twist.
+    fn parse_twist(&mut self) -> Result<Twist, String> {
+        let column = self.column();
+        let rest = &self.s[self.pos..];
+        let mut in_braces = false;
+        let len = rest
+            .find(|c: char| match c {
+                '{' => {
+                    in_braces = true;
+                    false
+                }
+                '}' => {
+                    in_braces = false;
+                    false
+                }
+                _ => !in_braces && (c.is_whitespace() || "[],:".contains(c)),
+            })
+            .unwrap_or(rest.len());
+        let token = &rest[..len];
+        self.pos += len;
+        self.scheme
+            .parse_twist(token)
+            .map_err(|e| format!("{e} {token:?} at column {column}"))
+    }
+}
+
 fn strip_any_prefix<'a, 'b, T>(
     s: &'a str,
     possible_prefixes: impl IntoIterator<Item = (T, impl 'b + AsRef<str>)>,
@@ -0,0 +1,109 @@
+//! Random permutation generation.
+//!
+//! A permutation of length `n` is represented as a `Vec<usize>` containing
+//! each of `0..n` exactly once, where `perm[i]` is the element moved to
+//! position `i`.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Returns a uniformly random permutation of length `n`.
+#[allow(unused)] // Not used by any scrambler yet.
+pub fn random(n: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..n).collect();
+    perm.shuffle(rng);
+    perm
+}
+
+/// Returns a permutation of length `n` chosen uniformly from those with the
+/// given parity, or `None` if there are no such permutations (i.e., an odd
+/// permutation of fewer than 2 elements).
+#[allow(unused)] // Not used by any scrambler yet.
+pub fn random_with_parity(n: usize, rng: &mut impl Rng, even: bool) -> Option<Vec<usize>> {
+    if n < 2 {
+        return even.then(|| (0..n).collect());
+    }
+
+    // Fisher-Yates shuffle all but the last two positions, tracking parity.
+    // Every swap of two distinct elements flips the parity.
+    let mut perm: Vec<usize> = (0..n).collect();
+    let mut is_even = true;
+    for i in 0..n - 2 {
+        let j = rng.gen_range(i..n);
+        if i != j {
+            perm.swap(i, j);
+            is_even = !is_even;
+        }
+    }
+    // The remaining two elements can be ordered in exactly one way for each
+    // parity, so fixing up the parity here keeps the distribution uniform.
+    if is_even != even {
+        perm.swap(n - 2, n - 1);
+    }
+    Some(perm)
+}
+
+/// Returns whether a permutation is even.
+pub fn is_even(perm: &[usize]) -> bool {
+    let mut seen = vec![false; perm.len()];
+    let mut cycle_count = 0;
+    for start in 0..perm.len() {
+        if !seen[start] {
+            cycle_count += 1;
+            let mut i = start;
+            while !seen[i] {
+                seen[i] = true;
+                i = perm[i];
+            }
+        }
+    }
+    (perm.len() - cycle_count) % 2 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_random_permutation_parity() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        assert_eq!(random_with_parity(0, &mut rng, true), Some(vec![]));
+        assert_eq!(random_with_parity(1, &mut rng, false), None);
+
+        for &even in &[true, false] {
+            // There are 12 permutations of 4 elements with each parity.
+            const SAMPLES: usize = 12_000;
+            let mut counts = HashMap::new();
+            for _ in 0..SAMPLES {
+                let perm = random_with_parity(4, &mut rng, even).unwrap();
+                assert_eq!(is_even(&perm), even);
+                *counts.entry(perm).or_insert(0) += 1;
+            }
+            assert_eq!(counts.len(), 12);
+            // Expected count is 1000 with a standard deviation of about 30.
+            for (perm, count) in counts {
+                assert!(
+                    (850..=1150).contains(&count),
+                    "{perm:?} occurred {count} times"
+                );
+            }
+        }
+
+        let mut counts = HashMap::new();
+        for _ in 0..6_000 {
+            *counts.entry(random(3, &mut rng)).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 6);
+        for (perm, count) in counts {
+            assert!(
+                (850..=1150).contains(&count),
+                "{perm:?} occurred {count} times"
+            );
+        }
+    }
+}
@@ -5,7 +5,8 @@ use itertools::Itertools;
 use num_enum::FromPrimitive;
 use serde::{de::Error, Deserialize, Deserializer};
 use smallvec::smallvec;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut, RangeInclusive};
 use std::sync::Mutex;
 use strum::IntoEnumIterator;
@@ -120,10 +121,6 @@ fn puzzle_description(layer_count: u8) -> &'static Rubiks3DDescription {
         let mut aliases = vec![];
         {
             use FaceEnum::*;
-            let all_layers = LayerMask::all_layers(layer_count);
-            aliases.push(("x".to_string(), Alias::AxisLayers(R.into(), all_layers)));
-            aliases.push(("y".to_string(), Alias::AxisLayers(U.into(), all_layers)));
-            aliases.push(("z".to_string(), Alias::AxisLayers(F.into(), all_layers)));
 
             if let Some(slice_layers) = LayerMask::slice_layers(layer_count) {
                 aliases.push(("M".to_string(), Alias::AxisLayers(L.into(), slice_layers)));
@@ -143,6 +140,24 @@ fn puzzle_description(layer_count: u8) -> &'static Rubiks3DDescription {
         // Try to match longer aliases first.
         aliases.sort_by_key(|(s, _)| -(s.len() as isize));
 
+        // Add full-puzzle rotations, which can be written using either face on
+        // the axis.
+        let mut rotations = vec![];
+        let all_layers = LayerMask::all_layers(layer_count);
+        for (axis_char, face) in [('x', FaceEnum::R), ('y', FaceEnum::U), ('z', FaceEnum::F)] {
+            for dir in TwistDirectionEnum::iter() {
+                let rotation_string = format!("{axis_char}{}", dir.symbol());
+                for (face, dir) in [(face, dir), (face.opposite(), dir.rev())] {
+                    let twist = Twist {
+                        axis: face.into(),
+                        direction: dir.into(),
+                        layers: all_layers,
+                    };
+                    rotations.push((rotation_string.clone(), twist));
+                }
+            }
+        }
+
         let notation = NotationScheme {
             axis_names: FaceEnum::iter()
                 .map(|f| f.symbol_upper().to_string())
@@ -152,6 +167,7 @@ fn puzzle_description(layer_count: u8) -> &'static Rubiks3DDescription {
                 .collect(),
             block_suffix: Some("w".to_string()),
             aliases,
+            rotations,
         };
 
         // It's not like we'll ever clear the cache anyway, so just leak it
@@ -218,8 +234,10 @@ impl PuzzleType for Rubiks3DDescription {
     fn family_max_layer_count(&self) -> u8 {
         MAX_LAYER_COUNT
     }
-    fn projection_radius_3d(&self, _p: StickerGeometryParams) -> f32 {
-        3.0_f32.sqrt()
+    fn projection_radius_3d(&self, p: StickerGeometryParams) -> f32 {
+        // The farthest points of the flat net are the outer corners of the back
+        // and down faces, at (±1, ±4, 1).
+        crate::util::mix(3.0_f32.sqrt(), 18.0_f32.sqrt(), p.unfold_amount)
     }
     fn scramble_moves_count(&self) -> usize {
         10 * self.layer_count as usize // TODO pulled from thin air; probably insufficient for big cubes
@@ -344,6 +362,11 @@ impl PartialEq for Rubiks3D {
         self.piece_states == other.piece_states
     }
 }
+impl Hash for Rubiks3D {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.piece_states.hash(state);
+    }
+}
 impl Index<Piece> for Rubiks3D {
     type Output = PieceState;
 
@@ -363,14 +386,34 @@ impl PuzzleState for Rubiks3D {
         }
         Ok(())
     }
+    fn apply_sequence(&mut self, seq: &[Twist]) -> Result<(), &'static str> {
+        // This puzzle uses the default `can_twist()`, which has no bandaging
+        // and so gives the same answer in every state. Check every twist up
+        // front to avoid leaving the sequence half-applied.
+        for &twist in seq {
+            self.can_twist(twist).map_err(<&str>::from)?;
+        }
+        // Follow each piece through the whole sequence, instead of finding the
+        // pieces affected by each twist.
+        for piece in (0..self.pieces().len() as _).map(Piece) {
+            let mut piece_state = self[piece];
+            for &twist in seq {
+                let face: FaceEnum = twist.axis.into();
+                let location = self.piece_location_in_state(piece, piece_state);
+                if twist.layers[self.layer_from_location(face, location)] {
+                    piece_state = piece_state.twist(face, twist.direction.into());
+                }
+            }
+            self[piece] = piece_state;
+        }
+        Ok(())
+    }
     fn layer_from_twist_axis(&self, twist_axis: TwistAxis, piece: Piece) -> u8 {
-        let face: FaceEnum = twist_axis.into();
-        let face_coord = match face.sign() {
-            Sign::Pos => self.layer_count() - 1,
-            Sign::Neg => 0,
-        };
-        let piece_coord = self.piece_location(piece)[face.axis() as usize];
-        u8::abs_diff(face_coord, piece_coord)
+        self.layer_from_location(twist_axis.into(), self.piece_location(piece))
+    }
+    fn twist_transform(&self, twist: Twist) -> Matrix4<f32> {
+        let twist_axis: FaceEnum = twist.axis.into();
+        twist_axis.twist_matrix(twist.direction.into(), 1.0).into()
     }
 
     fn rotation_candidates(&self) -> Vec<(Vec<Twist>, Quaternion<f32>)> {
@@ -445,17 +488,21 @@ impl PuzzleState for Rubiks3D {
         let piece = self.info(sticker).piece;
         let face = self.sticker_face(sticker);
 
-        let mut transform = p.view_transform;
+        let mut transform = Matrix3::identity();
         if let Some((twist, progress)) = p.twist_animation {
             if self.is_piece_affected_by_twist(twist, piece) {
                 let twist_axis: FaceEnum = twist.axis.into();
-                let twist_transform = twist_axis.twist_matrix(twist.direction.into(), progress);
-                transform = transform * twist_transform;
+                transform = twist_axis.twist_matrix(twist.direction.into(), progress);
             }
         }
 
+        // Move the face toward its place in the net.
+        let (unfold_rotation, unfold_offset) = face.unfold_transform(p.unfold_amount);
+        let transform = p.view_transform * unfold_rotation * transform;
+        let offset = p.view_transform * unfold_offset;
+
         // Compute the center of the sticker.
-        let center = transform.transform_point(self.sticker_center_3d(sticker, p));
+        let center = transform.transform_point(self.sticker_center_3d(sticker, p)) + offset;
 
         // Compute the vectors that span the plane of the sticker.
         let [u_span_axis, v_span_axis] = face.parallel_axes();
@@ -494,6 +541,26 @@ impl PuzzleState for Rubiks3D {
         ))
     }
 
+    fn twist_rotation_guide(&self, twist: Twist, p: StickerGeometryParams) -> Vec<Point3<f32>> {
+        // Extend the axis a little past the surface of the puzzle.
+        let twist_axis: FaceEnum = twist.axis.into();
+        let v = twist_axis.vector() * 1.5;
+        vec![
+            p.view_transform.transform_point(Point3::from_vec(v)),
+            p.view_transform.transform_point(Point3::from_vec(-v)),
+        ]
+    }
+
+    fn sticker_center_and_normal(
+        &self,
+        sticker: Sticker,
+        p: StickerGeometryParams,
+    ) -> (Vec<f32>, Vec<f32>) {
+        let center: [f32; 3] = self.sticker_center_3d(sticker, p).into();
+        let normal: [f32; 3] = self.sticker_face(sticker).vector().into();
+        (center.to_vec(), normal.to_vec())
+    }
+
     fn is_solved(&self) -> bool {
         let mut color_per_facet = vec![None; self.faces().len()];
         for (i, sticker) in self.stickers().iter().enumerate() {
@@ -507,6 +574,158 @@ impl PuzzleState for Rubiks3D {
         }
         true
     }
+    /// Checks the three conservation laws of the 3x3x3: corner orientations
+    /// add up to a whole turn, an even number of edges are flipped, and the
+    /// total permutation parity of corners, edges, and centers is even. Larger
+    /// odd cubes obey the same laws for their corners, middle edges, and
+    /// fixed centers; even cubes only obey the corner orientation law.
+    fn check_legal(&self) -> Result<(), IllegalStateReason> {
+        let n = self.layer_count();
+        if n < 2 {
+            return Ok(());
+        }
+        let mid = (n - 1) / 2;
+        let is_extreme = |c: u8| c == 0 || c == n - 1;
+
+        // The direction and color of each sticker, grouped by the location of
+        // its piece. Colors are used to identify pieces because on bigger
+        // cubes, pieces with the same colors are interchangeable.
+        let mut slots: HashMap<[u8; 3], Vec<(FaceEnum, FaceEnum)>> = HashMap::new();
+        for (i, sticker_info) in self.stickers().iter().enumerate() {
+            let location = self.piece_location(sticker_info.piece);
+            let facing = self.sticker_face(Sticker(i as _));
+            slots
+                .entry(location)
+                .or_default()
+                .push((facing, sticker_info.color.into()));
+        }
+
+        // Locations with `extreme_count` coordinates on the outside of the
+        // puzzle and the rest in the middle layer.
+        let locations_of_class = |extreme_count: usize| -> Vec<[u8; 3]> {
+            slots
+                .keys()
+                .copied()
+                .filter(|loc| {
+                    loc.iter().all(|&c| is_extreme(c) || c == mid)
+                        && loc.iter().filter(|&&c| is_extreme(c)).count() == extreme_count
+                })
+                .sorted()
+                .collect()
+        };
+        // Location of the piece with the given colors on a solved puzzle.
+        let home_location = |stickers: &[(FaceEnum, FaceEnum)]| {
+            let mut ret = [mid; 3];
+            for &(_, color) in stickers {
+                ret[color.axis() as usize] = match color.sign() {
+                    Sign::Pos => n - 1,
+                    Sign::Neg => 0,
+                };
+            }
+            ret
+        };
+        // Returns whether the pieces at `locations` are permuted evenly.
+        let is_permutation_even = |locations: &[[u8; 3]]| {
+            let perm: Vec<usize> = locations
+                .iter()
+                .map(|loc| locations.binary_search(&home_location(&slots[loc])))
+                .collect::<Result<_, _>>()
+                .map_err(|_| IllegalStateReason::MisplacedPiece)?;
+            if perm.iter().unique().count() != perm.len() {
+                return Err(IllegalStateReason::MisplacedPiece);
+            }
+            Ok(super::permutations::is_even(&perm))
+        };
+
+        // Orientation is measured using the sticker whose color is on the
+        // first of these axes, and compared against the first of these axes
+        // that the piece's location is on the outside of.
+        let axis_priority = |axis: Axis| match axis {
+            Axis::Y => 0,
+            Axis::Z => 1,
+            Axis::X => 2,
+        };
+        let reference_facing = |loc: &[u8; 3]| {
+            slots[loc]
+                .iter()
+                .min_by_key(|(_, color)| axis_priority(color.axis()))
+                .map(|&(facing, _)| facing.axis())
+                .expect("piece with no stickers")
+        };
+
+        // Each corner is twisted some number of thirds of a turn from its
+        // reference sticker pointing along the Y axis. Which direction counts
+        // as positive depends on the handedness of the corner's location.
+        let corners = locations_of_class(3);
+        let corners_even = is_permutation_even(&corners)?;
+        let total_corner_twist: usize = corners
+            .iter()
+            .map(|loc| {
+                let twist = (reference_facing(loc) as usize + 2) % 3;
+                let is_left_handed = loc.iter().filter(|&&c| c == 0).count() % 2 == 1;
+                if is_left_handed {
+                    (3 - twist) % 3
+                } else {
+                    twist
+                }
+            })
+            .sum();
+        if total_corner_twist % 3 != 0 {
+            return Err(IllegalStateReason::CornerTwist);
+        }
+
+        if n % 2 == 0 {
+            return Ok(());
+        }
+
+        let edges = locations_of_class(2);
+        let edges_even = is_permutation_even(&edges)?;
+        let flipped_edge_count = edges
+            .iter()
+            .filter(|loc| {
+                let location_axis = Axis::iter()
+                    .filter(|&axis| is_extreme(loc[axis as usize]))
+                    .min_by_key(|&axis| axis_priority(axis));
+                Some(reference_facing(loc)) != location_axis
+            })
+            .count();
+        if flipped_edge_count % 2 != 0 {
+            return Err(IllegalStateReason::EdgeFlip);
+        }
+
+        let centers = locations_of_class(1);
+        let centers_even = is_permutation_even(&centers)?;
+        if corners_even ^ edges_even ^ centers_even {
+            Ok(())
+        } else {
+            Err(IllegalStateReason::Parity)
+        }
+    }
+
+    fn serialize_state(&self) -> Vec<u8> {
+        let piece_data = self.piece_states.iter().flat_map(|s| s.0.map(|f| f as u8));
+        std::iter::once(STATE_FORMAT_VERSION)
+            .chain(piece_data)
+            .collect()
+    }
+
+    fn sticker_pattern_colors(&self) -> Vec<Face> {
+        let color_at: HashMap<([u8; 3], FaceEnum), Face> = (0..self.stickers().len() as _)
+            .map(Sticker)
+            .map(|sticker| {
+                let sticker_info = self.info(sticker);
+                let location = self.piece_location(sticker_info.piece);
+                ((location, self.sticker_face(sticker)), sticker_info.color)
+            })
+            .collect();
+        self.stickers()
+            .iter()
+            .map(|sticker_info| {
+                let location = self.desc.piece_locations[sticker_info.piece.0 as usize];
+                color_at[&(location, FaceEnum::from(sticker_info.color))]
+            })
+            .collect()
+    }
 }
 #[delegate_to_methods]
 #[delegate(PuzzleType, target_ref = "desc")]
@@ -517,12 +736,111 @@ impl Rubiks3D {
         Self { desc, piece_states }
     }
 
+    /// Reconstructs a puzzle from the output of `serialize_state()`.
+    pub fn deserialize_state(layer_count: u8, bytes: &[u8]) -> Result<Self, String> {
+        let data = match bytes.split_first() {
+            Some((&STATE_FORMAT_VERSION, data)) => data,
+            Some((version, _)) => return Err(format!("unknown state format version {version}")),
+            None => return Err("empty puzzle state".to_string()),
+        };
+        if !LAYER_COUNT_RANGE.contains(&layer_count) {
+            return Err(format!("invalid layer count {layer_count}"));
+        }
+
+        let mut ret = Self::new(layer_count);
+        let expected_len = ret.piece_states.len() * 3;
+        if data.len() != expected_len {
+            return Err(format!(
+                "expected {expected_len} bytes of piece data; got {}",
+                data.len(),
+            ));
+        }
+        let face_count = ret.faces().len();
+        // Only rotations are reachable; this also rejects reflections.
+        let orientations = PieceState::all_orientations();
+        for (i, (piece_state, chunk)) in ret
+            .piece_states
+            .iter_mut()
+            .zip(data.chunks_exact(3))
+            .enumerate()
+        {
+            for (face, &byte) in piece_state.0.iter_mut().zip(chunk) {
+                if byte as usize >= face_count {
+                    return Err(format!("invalid face {byte} for piece {i}"));
+                }
+                *face = FaceEnum::from(byte);
+            }
+            if !orientations.contains(piece_state) {
+                return Err(format!("invalid orientation for piece {i}"));
+            }
+        }
+        Ok(ret)
+    }
+    /// Reconstructs a puzzle from the output of `sticker_pattern()`.
+    pub fn from_sticker_pattern(layer_count: u8, pattern: &str) -> Result<Self, String> {
+        let mut ret = Self::new(layer_count);
+        let desc = ret.desc;
+        let colors = desc.parse_sticker_pattern(pattern)?;
+
+        // Colors at each piece location, along with the direction they face.
+        let mut slots: HashMap<[u8; 3], Vec<(FaceEnum, Face)>> = HashMap::new();
+        for (sticker_info, &color) in desc.stickers().iter().zip(&colors) {
+            let location = desc.piece_locations[sticker_info.piece.0 as usize];
+            let facing = sticker_info.color.into();
+            slots.entry(location).or_default().push((facing, color));
+        }
+
+        // Pieces that have not been placed yet, grouped by their colors.
+        let color_key =
+            |colors: HashSet<Face>| colors.into_iter().map(|c| c.0).sorted().collect_vec();
+        let mut unplaced: HashMap<Vec<u8>, Vec<Piece>> = HashMap::new();
+        for piece in (0..desc.pieces().len() as _).map(Piece) {
+            let key = color_key(desc.piece_colors(piece));
+            unplaced.entry(key).or_default().push(piece);
+        }
+
+        let orientations = PieceState::all_orientations();
+        for &location in &desc.piece_locations {
+            let slot = slots.get(&location).map_or(&[][..], |slot| slot.as_slice());
+            let no_match = || format!("no piece matches the colors at {location:?}");
+            let key = color_key(slot.iter().map(|&(_, color)| color).collect());
+            let candidates = unplaced.get_mut(&key).ok_or_else(no_match)?;
+            let (index, piece_state) = candidates
+                .iter()
+                .enumerate()
+                .find_map(|(i, &piece)| {
+                    let piece_state = orientations.iter().copied().find(|&piece_state| {
+                        ret.piece_location_in_state(piece, piece_state) == location
+                            && desc.info(piece).stickers.iter().all(|&sticker| {
+                                let color = desc.info(sticker).color;
+                                let facing = piece_state.transform_face(color.into());
+                                slot.contains(&(facing, color))
+                            })
+                    })?;
+                    Some((i, piece_state))
+                })
+                .ok_or_else(no_match)?;
+            let piece = candidates.swap_remove(index);
+            ret[piece] = piece_state;
+        }
+        Ok(ret)
+    }
+
     fn desc(&self) -> &Rubiks3DDescription {
         self.desc
     }
 
+    fn layer_from_location(&self, face: FaceEnum, location: [u8; 3]) -> u8 {
+        let face_coord = match face.sign() {
+            Sign::Pos => self.layer_count() - 1,
+            Sign::Neg => 0,
+        };
+        u8::abs_diff(face_coord, location[face.axis() as usize])
+    }
     fn piece_location(&self, piece: Piece) -> [u8; 3] {
-        let piece_state = self[piece];
+        self.piece_location_in_state(piece, self[piece])
+    }
+    fn piece_location_in_state(&self, piece: Piece, piece_state: PieceState) -> [u8; 3] {
         let initial_location = self.desc.piece_locations[piece.0 as usize];
         let mut ret = [0_u8; 3];
         for (i, axis) in Axis::iter().enumerate() {
@@ -536,12 +854,7 @@ impl Rubiks3D {
     }
     fn sticker_face(&self, sticker: Sticker) -> FaceEnum {
         let sticker_info = self.info(sticker);
-        let original_face: FaceEnum = sticker_info.color.into();
-        let current_face = self[sticker_info.piece][original_face.axis()];
-        match original_face.sign() {
-            Sign::Pos => current_face,
-            Sign::Neg => current_face.opposite(),
-        }
+        self[sticker_info.piece].transform_face(sticker_info.color.into())
     }
 
     fn piece_center_3d(&self, piece: Piece, p: StickerGeometryParams) -> Point3<f32> {
@@ -591,6 +904,33 @@ impl IndexMut<Axis> for PieceState {
     }
 }
 impl PieceState {
+    /// Returns every orientation that a piece can be rotated into.
+    fn all_orientations() -> Vec<Self> {
+        let mut ret = vec![Self::default()];
+        let mut i = 0;
+        while let Some(&piece_state) = ret.get(i) {
+            for face in FaceEnum::iter() {
+                for direction in TwistDirectionEnum::iter() {
+                    let new_state = piece_state.twist(face, direction);
+                    if !ret.contains(&new_state) {
+                        ret.push(new_state);
+                    }
+                }
+            }
+            i += 1;
+        }
+        ret
+    }
+    /// Returns the direction faced by the sticker that initially faced
+    /// `original_face`.
+    fn transform_face(self, original_face: FaceEnum) -> FaceEnum {
+        let current_face = self[original_face.axis()];
+        match original_face.sign() {
+            Sign::Pos => current_face,
+            Sign::Neg => current_face.opposite(),
+        }
+    }
+
     #[must_use]
     fn rotate(mut self, from: Axis, to: Axis) -> Self {
         let diff = (from as u8 ^ to as u8) << 1;
@@ -742,6 +1082,33 @@ impl FaceEnum {
         } * self.sign().float())
     }
 
+    /// Returns the rotation and offset that move this face toward its place in
+    /// a flat net of the cube, where `amount` ranges from 0.0 (assembled) to
+    /// 1.0 (flat). The net is a cross of the front, left, right, up, and down
+    /// faces, with the back face attached to the up face.
+    fn unfold_transform(self, amount: f32) -> (Matrix3<f32>, Vector3<f32>) {
+        use FaceEnum::*;
+
+        // Rotates `face` about the edge it shares with `base`.
+        let hinge = |face: FaceEnum, base: FaceEnum| {
+            let rotation =
+                Matrix3::from_axis_angle(face.vector().cross(base.vector()), Deg(90.0 * amount));
+            let pivot = face.vector() + base.vector();
+            (rotation, pivot - rotation * pivot)
+        };
+        let (rotation, offset) = match self {
+            F => (Matrix3::identity(), Vector3::zero()),
+            B => {
+                let (r1, o1) = hinge(B, U);
+                let (r2, o2) = hinge(U, F);
+                (r2 * r1, r2 * o1 + o2)
+            }
+            _ => hinge(self, F),
+        };
+        // Center the net vertically.
+        (rotation, offset - Vector3::unit_y() * amount)
+    }
+
     /// Returns the axes parallel to this face (all except the perpendicular
     /// axis).
     fn parallel_axes(self) -> [Axis; 2] {
@@ -757,9 +1124,7 @@ impl FaceEnum {
         Quaternion::from_axis_angle(self.vector(), angle)
     }
     fn twist_matrix(self, direction: TwistDirectionEnum, progress: f32) -> Matrix3<f32> {
-        Quaternion::one()
-            .slerp(self.twist_rotation(direction), progress)
-            .into()
+        crate::util::slerp(Quaternion::one(), self.twist_rotation(direction), progress).into()
     }
 }
 
@@ -954,6 +1319,326 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rubiks_3d_whole_puzzle_rotations() {
+        let p = Rubiks3D::new(3);
+        let rotations = p.whole_puzzle_rotations();
+        // x, y, and z each in three directions
+        assert_eq!(rotations.len(), 9);
+        assert!(rotations.iter().all(|&t| p.is_whole_puzzle_rotation(t)));
+
+        // Together they reach all 24 orientations of the cube.
+        let mut seen = HashSet::from([Puzzle::Rubiks3D(p.clone())]);
+        let mut unprocessed = vec![Puzzle::Rubiks3D(p)];
+        while let Some(puzzle) = unprocessed.pop() {
+            for &rot in rotations {
+                let mut rotated = puzzle.clone();
+                rotated.twist(rot).unwrap();
+                if seen.insert(rotated.clone()) {
+                    unprocessed.push(rotated);
+                }
+            }
+        }
+        assert_eq!(seen.len(), 24);
+    }
+
+    #[test]
+    fn test_rubiks_3d_rotation_notation() {
+        for layer_count in 1..=4 {
+            let p = Rubiks3D::new(layer_count);
+            let notation = p.notation_scheme();
+            for &rot in p.whole_puzzle_rotations() {
+                let s = notation.twist_to_string(rot);
+                assert!(s.starts_with(&['x', 'y', 'z'][..]), "{s:?}");
+                let parsed = notation.parse_twist(&s).unwrap();
+                assert_eq!(p.canonicalize_twist(parsed), rot, "{s:?}");
+            }
+        }
+
+        let p = Rubiks3D::new(3);
+        let notation = p.notation_scheme();
+        let twist = |s| notation.parse_twist(s).unwrap();
+        assert_eq!(notation.twist_to_string(twist("{1-3}L'")), "x");
+        assert_eq!(notation.twist_to_string(twist("3Rw2")), "x2");
+        assert!(notation.parse_twist("xw").is_err());
+    }
+
+    #[test]
+    fn test_rubiks_3d_check_legal() {
+        for layer_count in 1..=5 {
+            let mut p = Rubiks3D::new(layer_count);
+            assert_eq!(Ok(()), p.check_legal());
+            let ty = p.ty();
+            for _ in 0..100 {
+                p.twist(Twist::from_rng(ty)).unwrap();
+            }
+            assert_eq!(Ok(()), p.check_legal(), "{layer_count}x{layer_count}");
+        }
+
+        let p = Rubiks3D::new(3);
+        // Returns the puzzle with `piece` moved to the solved location of
+        // `target`, using the first orientation that does so other than the
+        // current one.
+        let move_piece = |puzzle: &Rubiks3D, piece: Piece, target: Piece| {
+            let mut ret = puzzle.clone();
+            let location = p.piece_location(target);
+            ret[piece] = PieceState::all_orientations()
+                .into_iter()
+                .find(|&s| s != puzzle[piece] && p.piece_location_in_state(piece, s) == location)
+                .unwrap();
+            ret
+        };
+        let piece = |faces: &[FaceEnum]| {
+            let colors: Vec<Face> = faces.iter().map(|&f| f.into()).collect();
+            p.find_piece_by_colors(&colors).unwrap()
+        };
+        use FaceEnum::{F, R, U};
+
+        let ufr = piece(&[U, F, R]);
+        let twisted_corner = move_piece(&p, ufr, ufr);
+        assert_eq!(
+            Err(IllegalStateReason::CornerTwist),
+            twisted_corner.check_legal()
+        );
+
+        let uf = piece(&[U, F]);
+        let flipped_edge = move_piece(&p, uf, uf);
+        assert_eq!(
+            Err(IllegalStateReason::EdgeFlip),
+            flipped_edge.check_legal()
+        );
+
+        let (u, r) = (piece(&[U]), piece(&[R]));
+        let swapped_centers = move_piece(&move_piece(&p, u, r), r, u);
+        assert_eq!(
+            Err(IllegalStateReason::Parity),
+            swapped_centers.check_legal()
+        );
+    }
+
+    #[test]
+    fn test_rubiks_3d_simple_hint() {
+        let p = Rubiks3D::new(3);
+        // There is nothing to place on a solved puzzle.
+        assert_eq!(p.simple_hint(2), None);
+
+        let mut scrambled = p.clone();
+        let twist = p.notation_scheme().parse_twist("R").unwrap();
+        scrambled.twist(twist).unwrap();
+        assert_eq!(scrambled.simple_hint(0), None);
+
+        // Only undoing the twist places a piece without moving any others.
+        let hint = scrambled.simple_hint(2).unwrap();
+        assert_eq!(hint, vec![p.canonicalize_twist(p.reverse_twist(twist))]);
+        scrambled.apply_sequence(&hint).unwrap();
+        assert!(scrambled.is_solved());
+    }
+
+    #[test]
+    fn test_rubiks_3d_compose_twists() {
+        let p = Rubiks3D::new(3);
+        let twist = |s| p.notation_scheme().parse_twist(s).unwrap();
+
+        assert_eq!(Some(twist("R2")), p.compose_twists(twist("R"), twist("R")));
+        assert_eq!(Some(twist("R'")), p.compose_twists(twist("R2"), twist("R")));
+        assert_eq!(None, p.compose_twists(twist("R"), twist("R'")));
+        assert_eq!(None, p.compose_twists(twist("R"), twist("U")));
+        assert_eq!(
+            Some(p.canonicalize_twist(twist("{2}R'"))),
+            p.compose_twists(twist("R"), twist("{1-2}R'")),
+        );
+    }
+
+    #[test]
+    fn test_rubiks_3d_find_piece_by_colors() {
+        use FaceEnum::{F, L, R, U};
+
+        let p = Rubiks3D::new(3);
+        let corner = p
+            .find_piece_by_colors(&[R.into(), U.into(), F.into()])
+            .unwrap();
+        assert_eq!([2, 2, 2], p.piece_location(corner));
+        assert_eq!(None, p.find_piece_by_colors(&[R.into(), L.into()]));
+
+        assert_eq!(9, p.find_pieces_with_colors(&[R.into()]).len());
+        assert_eq!(
+            vec![corner],
+            p.find_pieces_with_colors(&[F.into(), U.into(), R.into()]),
+        );
+    }
+
+    #[test]
+    fn test_rubiks_3d_state_serialization() {
+        let mut p = Rubiks3D::new(3);
+        for s in ["R", "U'", "F2", "L"] {
+            p.twist(p.notation_scheme().parse_twist(s).unwrap())
+                .unwrap();
+        }
+        let bytes = p.serialize_state();
+        assert_eq!(Ok(p.clone()), Rubiks3D::deserialize_state(3, &bytes));
+
+        assert!(Rubiks3D::deserialize_state(3, &bytes[..bytes.len() - 1]).is_err());
+        assert!(Rubiks3D::deserialize_state(4, &bytes).is_err());
+        let mut bad_version = bytes.clone();
+        bad_version[0] = STATE_FORMAT_VERSION + 1;
+        assert!(Rubiks3D::deserialize_state(3, &bad_version).is_err());
+        let mut bad_face = bytes.clone();
+        bad_face[1] = 6;
+        assert!(Rubiks3D::deserialize_state(3, &bad_face).is_err());
+        let mut bad_orientation = bytes.clone();
+        bad_orientation[2] = bad_orientation[1];
+        assert!(Rubiks3D::deserialize_state(3, &bad_orientation).is_err());
+        let mut reflected = Rubiks3D::new(3).serialize_state();
+        // [L, U, F] has all axes distinct but is a reflection.
+        reflected[1..4].copy_from_slice(&[FaceEnum::L as u8, FaceEnum::U as u8, FaceEnum::F as u8]);
+        assert!(Rubiks3D::deserialize_state(3, &reflected).is_err());
+
+        assert!(Rubiks3D::deserialize_state(0, &[STATE_FORMAT_VERSION]).is_err());
+        assert!(Rubiks3D::deserialize_state(MAX_LAYER_COUNT + 1, &bytes).is_err());
+    }
+
+    #[test]
+    fn test_rubiks_3d_nearest_rotation_around_axis() {
+        let p = Rubiks3D::new(3);
+        let rot = Quaternion::from_angle_y(Deg(80.0));
+        assert_eq!(p.nearest_rotation(rot, None).0.len(), 1);
+        assert_eq!(p.nearest_rotation(rot, Some(Vector3::unit_y())).0.len(), 1);
+        assert!(p
+            .nearest_rotation(rot, Some(Vector3::unit_x()))
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rubiks_3d_can_twist() {
+        let p = Rubiks3D::new(3);
+        let twist = p.notation_scheme().parse_twist("R").unwrap();
+        assert_eq!(Ok(()), p.can_twist(twist));
+        let no_layers = Twist {
+            layers: LayerMask(0),
+            ..twist
+        };
+        assert_eq!(
+            Err(TwistBlockedReason::InvalidLayers),
+            p.can_twist(no_layers)
+        );
+        let extra_layers = Twist {
+            layers: LayerMask(0b1000),
+            ..twist
+        };
+        assert_eq!(
+            Err(TwistBlockedReason::InvalidLayers),
+            p.can_twist(extra_layers)
+        );
+    }
+
+    #[test]
+    fn test_rubiks_3d_sticker_pattern() {
+        let solved = Rubiks3D::new(4).sticker_pattern();
+        assert!(Rubiks3D::from_sticker_pattern(4, &solved)
+            .unwrap()
+            .is_solved());
+
+        let mut p = Rubiks3D::new(4);
+        for s in ["R", "U'", "F2", "L", "B"] {
+            p.twist(p.notation_scheme().parse_twist(s).unwrap())
+                .unwrap();
+        }
+        let pattern = p.sticker_pattern();
+        let imported = Rubiks3D::from_sticker_pattern(4, &pattern).unwrap();
+        assert_eq!(pattern, imported.sticker_pattern());
+
+        assert!(Rubiks3D::from_sticker_pattern(3, &pattern).is_err());
+        assert!(Rubiks3D::from_sticker_pattern(4, &pattern.replacen('R', "X", 1)).is_err());
+        assert!(Rubiks3D::from_sticker_pattern(4, &pattern.replacen('R', "U", 1)).is_err());
+    }
+
+    #[test]
+    fn test_rubiks_3d_sequence_parsing() {
+        let p = Rubiks3D::new(3);
+        let parse = |s: &str| {
+            p.notation_scheme()
+                .parse_sequence(s, |t| p.reverse_twist(t))
+        };
+        let twists = |s: &str| {
+            s.split_whitespace()
+                .map(|t| p.notation_scheme().parse_twist(t).unwrap())
+                .collect_vec()
+        };
+
+        assert_eq!(parse("  R U2 {1,3}F' "), Ok(twists("R U2 {1,3}F'")));
+        assert_eq!(parse("[R, U]"), Ok(twists("R U R' U'")));
+        assert_eq!(parse("[F: R U R']"), Ok(twists("F R U R' F'")));
+        assert_eq!(parse("D [F: [R, U]]"), Ok(twists("D F R U R' U' F'")));
+        assert_eq!(parse("[{1,3}R,U]"), Ok(twists("{1,3}R U {1,3}R' U'")));
+
+        assert!(parse("[R U]").unwrap_err().contains("column 1"));
+        assert!(parse("R [U, [F, R]")
+            .unwrap_err()
+            .contains("unmatched '[' at column 3"));
+        assert!(parse("R U]").unwrap_err().contains("column 4"));
+        assert!(parse("R Q").unwrap_err().contains("column 3"));
+    }
+
+    #[test]
+    fn test_rubiks_3d_state_keys() {
+        let solved = Puzzle::Rubiks3D(Rubiks3D::new(3));
+        let twist = |mut p: Puzzle, s: &str| {
+            p.twist(p.notation_scheme().parse_twist(s).unwrap())
+                .unwrap();
+            p
+        };
+
+        // A move followed by its inverse returns to an equal state.
+        let p = twist(twist(solved.clone(), "R"), "R'");
+        assert_eq!(p, solved);
+        let hash = |p: &Puzzle| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            p.hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(&p), hash(&solved));
+
+        // Whole-puzzle rotations are only ignored by the rotation-invariant key.
+        let rotated = twist(solved.clone(), "x");
+        assert_ne!(rotated, solved);
+        assert_eq!(
+            rotated.rotation_invariant_key(),
+            solved.rotation_invariant_key()
+        );
+        let r = twist(solved.clone(), "R");
+        let l = twist(solved.clone(), "L");
+        assert_ne!(r.rotation_invariant_key(), solved.rotation_invariant_key());
+        // The centers are pieces too, so no rotation turns R into L.
+        assert_ne!(r.rotation_invariant_key(), l.rotation_invariant_key());
+        let r_rotated = twist(twist(r.clone(), "y"), "x");
+        assert_ne!(r_rotated, r);
+        assert_eq!(
+            r_rotated.rotation_invariant_key(),
+            r.rotation_invariant_key()
+        );
+    }
+
+    #[test]
+    fn test_rubiks_3d_unfold_transform() {
+        let mut centers = vec![];
+        for face in FaceEnum::iter() {
+            let (rotation, offset) = face.unfold_transform(1.0);
+            // Every face ends up facing forward in the plane of the front face.
+            assert!((rotation * face.vector() - Vector3::unit_z()).magnitude() < 1e-5);
+            let center = rotation * face.vector() + offset;
+            assert!(
+                (center.z - 1.0).abs() < 1e-5,
+                "{face:?} center is {center:?}"
+            );
+            centers.push(center);
+        }
+        // No two faces overlap.
+        for (a, b) in centers.iter().tuple_combinations() {
+            assert!((a - b).magnitude() > 1.0);
+        }
+    }
+
     fn twist_comparison_key(p: &Rubiks3D, twist: Twist) -> impl PartialEq {
         const SOME_PROGRESS: f32 = 0.1;
 
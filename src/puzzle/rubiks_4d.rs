@@ -5,7 +5,8 @@ use itertools::Itertools;
 use num_enum::FromPrimitive;
 use serde::{de::Error, Deserialize, Deserializer};
 use smallvec::smallvec;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut, RangeInclusive};
 use std::sync::Mutex;
 use strum::IntoEnumIterator;
@@ -142,25 +143,29 @@ fn puzzle_description(layer_count: u8) -> &'static Rubiks4DDescription {
             aliases.push(("P".to_string(), Alias::AxisLayers(O.into(), slice_layers)));
         }
 
-        // Add 90-degree full-puzzle rotation aliases.
+        // Try to match longer aliases first.
+        aliases.sort_by_key(|(s, _)| -(s.len() as isize));
+
+        // Add 90-degree and 180-degree full-puzzle rotations, which can be
+        // written using either face on the axis.
+        let mut rotations = vec![];
         let all_layers = LayerMask::all_layers(layer_count);
         for (ax1, ax2) in itertools::iproduct!(Axis::iter(), Axis::iter()) {
             if let Some((dir, face)) = TwistDirectionEnum::from_face_twist_plane(ax1, ax2) {
-                let alias_string = format!("{}{}", ax1.symbol_lower(), ax2.symbol_lower());
-
-                let mut twist = Twist {
-                    axis: face.into(),
-                    direction: dir.into(),
-                    layers: all_layers,
-                };
-                aliases.push((alias_string.clone(), Alias::EntireTwist(twist)));
-
-                twist.direction = dir.double().unwrap().into();
-                aliases.push((alias_string + "2", Alias::EntireTwist(twist)));
+                let rotation_string = format!("{}{}", ax1.symbol_lower(), ax2.symbol_lower());
+                let opposite = face.opposite();
+                for (suffix, dir) in [("", dir), ("2", dir.double().unwrap())] {
+                    for (face, dir) in [(face, dir), (opposite, dir.mirror(opposite.axis()))] {
+                        let twist = Twist {
+                            axis: face.into(),
+                            direction: dir.into(),
+                            layers: all_layers,
+                        };
+                        rotations.push((format!("{rotation_string}{suffix}"), twist));
+                    }
+                }
             }
         }
-        // Try to match longer aliases first.
-        aliases.sort_by_key(|(s, _)| -(s.len() as isize));
 
         let notation = NotationScheme {
             axis_names: FaceEnum::iter()
@@ -175,6 +180,7 @@ fn puzzle_description(layer_count: u8) -> &'static Rubiks4DDescription {
                 .collect(),
             block_suffix: None,
             aliases,
+            rotations,
         };
 
         // It's not like we'll ever clear the cache anyway, so just leak it
@@ -399,6 +405,11 @@ impl PartialEq for Rubiks4D {
         self.piece_states == other.piece_states
     }
 }
+impl Hash for Rubiks4D {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.piece_states.hash(state);
+    }
+}
 impl Index<Piece> for Rubiks4D {
     type Output = PieceState;
 
@@ -418,14 +429,34 @@ impl PuzzleState for Rubiks4D {
         }
         Ok(())
     }
+    fn apply_sequence(&mut self, seq: &[Twist]) -> Result<(), &'static str> {
+        // This puzzle uses the default `can_twist()`, which has no bandaging
+        // and so gives the same answer in every state. Check every twist up
+        // front to avoid leaving the sequence half-applied.
+        for &twist in seq {
+            self.can_twist(twist).map_err(<&str>::from)?;
+        }
+        // Follow each piece through the whole sequence, instead of finding the
+        // pieces affected by each twist.
+        for piece in (0..self.pieces().len() as _).map(Piece) {
+            let mut piece_state = self[piece];
+            for &twist in seq {
+                let face: FaceEnum = twist.axis.into();
+                let location = self.piece_location_in_state(piece, piece_state);
+                if twist.layers[self.layer_from_location(face, location)] {
+                    piece_state = piece_state.twist(face, twist.direction.into());
+                }
+            }
+            self[piece] = piece_state;
+        }
+        Ok(())
+    }
     fn layer_from_twist_axis(&self, twist_axis: TwistAxis, piece: Piece) -> u8 {
-        let face: FaceEnum = twist_axis.into();
-        let face_coord = match face.sign() {
-            Sign::Pos => self.layer_count() - 1,
-            Sign::Neg => 0,
-        };
-        let piece_coord = self.piece_location(piece)[face.axis() as usize];
-        u8::abs_diff(face_coord, piece_coord)
+        self.layer_from_location(twist_axis.into(), self.piece_location(piece))
+    }
+    fn twist_transform(&self, twist: Twist) -> Matrix4<f32> {
+        let twist_axis: FaceEnum = twist.axis.into();
+        twist_axis.twist_matrix(twist.direction.into(), 1.0)
     }
 
     fn rotation_candidates(&self) -> Vec<(Vec<Twist>, Quaternion<f32>)> {
@@ -528,6 +559,32 @@ impl PuzzleState for Rubiks4D {
         )
     }
 
+    fn twist_rotation_guide(&self, twist: Twist, p: StickerGeometryParams) -> Vec<Point3<f32>> {
+        // The fixed plane is spanned by the normal of the twisted facet and
+        // the axis of the 3D rotation within that facet.
+        let twist_axis: FaceEnum = twist.axis.into();
+        let direction: TwistDirectionEnum = twist.direction.into();
+        let normal = twist_axis.vector();
+        let axis = twist_axis.basis_matrix() * direction.vector3_f32().normalize().extend(0.0);
+
+        let project = |point_4d| Some(p.view_transform.transform_point(p.project_4d(point_4d)?));
+        [normal + axis, normal - axis, -normal - axis, -normal + axis]
+            .into_iter()
+            .map(project)
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default()
+    }
+
+    fn sticker_center_and_normal(
+        &self,
+        sticker: Sticker,
+        p: StickerGeometryParams,
+    ) -> (Vec<f32>, Vec<f32>) {
+        let center: [f32; 4] = self.sticker_center_4d(sticker, p).into();
+        let normal: [f32; 4] = self.sticker_face(sticker).vector().into();
+        (center.to_vec(), normal.to_vec())
+    }
+
     fn is_solved(&self) -> bool {
         let mut color_per_facet = vec![None; self.faces().len()];
         for (i, sticker) in self.stickers().iter().enumerate() {
@@ -541,6 +598,31 @@ impl PuzzleState for Rubiks4D {
         }
         true
     }
+
+    fn serialize_state(&self) -> Vec<u8> {
+        let piece_data = self.piece_states.iter().flat_map(|s| s.0.map(|f| f as u8));
+        std::iter::once(STATE_FORMAT_VERSION)
+            .chain(piece_data)
+            .collect()
+    }
+
+    fn sticker_pattern_colors(&self) -> Vec<Face> {
+        let color_at: HashMap<([u8; 4], FaceEnum), Face> = (0..self.stickers().len() as _)
+            .map(Sticker)
+            .map(|sticker| {
+                let sticker_info = self.info(sticker);
+                let location = self.piece_location(sticker_info.piece);
+                ((location, self.sticker_face(sticker)), sticker_info.color)
+            })
+            .collect();
+        self.stickers()
+            .iter()
+            .map(|sticker_info| {
+                let location = self.desc.piece_locations[sticker_info.piece.0 as usize];
+                color_at[&(location, FaceEnum::from(sticker_info.color))]
+            })
+            .collect()
+    }
 }
 #[delegate_to_methods]
 #[delegate(PuzzleType, target_ref = "desc")]
@@ -551,12 +633,111 @@ impl Rubiks4D {
         Self { desc, piece_states }
     }
 
+    /// Reconstructs a puzzle from the output of `serialize_state()`.
+    pub fn deserialize_state(layer_count: u8, bytes: &[u8]) -> Result<Self, String> {
+        let data = match bytes.split_first() {
+            Some((&STATE_FORMAT_VERSION, data)) => data,
+            Some((version, _)) => return Err(format!("unknown state format version {version}")),
+            None => return Err("empty puzzle state".to_string()),
+        };
+        if !LAYER_COUNT_RANGE.contains(&layer_count) {
+            return Err(format!("invalid layer count {layer_count}"));
+        }
+
+        let mut ret = Self::new(layer_count);
+        let expected_len = ret.piece_states.len() * 4;
+        if data.len() != expected_len {
+            return Err(format!(
+                "expected {expected_len} bytes of piece data; got {}",
+                data.len(),
+            ));
+        }
+        let face_count = ret.faces().len();
+        // Only rotations are reachable; this also rejects reflections.
+        let orientations = PieceState::all_orientations();
+        for (i, (piece_state, chunk)) in ret
+            .piece_states
+            .iter_mut()
+            .zip(data.chunks_exact(4))
+            .enumerate()
+        {
+            for (face, &byte) in piece_state.0.iter_mut().zip(chunk) {
+                if byte as usize >= face_count {
+                    return Err(format!("invalid face {byte} for piece {i}"));
+                }
+                *face = FaceEnum::from(byte);
+            }
+            if !orientations.contains(piece_state) {
+                return Err(format!("invalid orientation for piece {i}"));
+            }
+        }
+        Ok(ret)
+    }
+    /// Reconstructs a puzzle from the output of `sticker_pattern()`.
+    pub fn from_sticker_pattern(layer_count: u8, pattern: &str) -> Result<Self, String> {
+        let mut ret = Self::new(layer_count);
+        let desc = ret.desc;
+        let colors = desc.parse_sticker_pattern(pattern)?;
+
+        // Colors at each piece location, along with the direction they face.
+        let mut slots: HashMap<[u8; 4], Vec<(FaceEnum, Face)>> = HashMap::new();
+        for (sticker_info, &color) in desc.stickers().iter().zip(&colors) {
+            let location = desc.piece_locations[sticker_info.piece.0 as usize];
+            let facing = sticker_info.color.into();
+            slots.entry(location).or_default().push((facing, color));
+        }
+
+        // Pieces that have not been placed yet, grouped by their colors.
+        let color_key =
+            |colors: HashSet<Face>| colors.into_iter().map(|c| c.0).sorted().collect_vec();
+        let mut unplaced: HashMap<Vec<u8>, Vec<Piece>> = HashMap::new();
+        for piece in (0..desc.pieces().len() as _).map(Piece) {
+            let key = color_key(desc.piece_colors(piece));
+            unplaced.entry(key).or_default().push(piece);
+        }
+
+        let orientations = PieceState::all_orientations();
+        for &location in &desc.piece_locations {
+            let slot = slots.get(&location).map_or(&[][..], |slot| slot.as_slice());
+            let no_match = || format!("no piece matches the colors at {location:?}");
+            let key = color_key(slot.iter().map(|&(_, color)| color).collect());
+            let candidates = unplaced.get_mut(&key).ok_or_else(no_match)?;
+            let (index, piece_state) = candidates
+                .iter()
+                .enumerate()
+                .find_map(|(i, &piece)| {
+                    let piece_state = orientations.iter().copied().find(|&piece_state| {
+                        ret.piece_location_in_state(piece, piece_state) == location
+                            && desc.info(piece).stickers.iter().all(|&sticker| {
+                                let color = desc.info(sticker).color;
+                                let facing = piece_state.transform_face(color.into());
+                                slot.contains(&(facing, color))
+                            })
+                    })?;
+                    Some((i, piece_state))
+                })
+                .ok_or_else(no_match)?;
+            let piece = candidates.swap_remove(index);
+            ret[piece] = piece_state;
+        }
+        Ok(ret)
+    }
+
     fn desc(&self) -> &Rubiks4DDescription {
         self.desc
     }
 
+    fn layer_from_location(&self, face: FaceEnum, location: [u8; 4]) -> u8 {
+        let face_coord = match face.sign() {
+            Sign::Pos => self.layer_count() - 1,
+            Sign::Neg => 0,
+        };
+        u8::abs_diff(face_coord, location[face.axis() as usize])
+    }
     fn piece_location(&self, piece: Piece) -> [u8; 4] {
-        let piece_state = self[piece];
+        self.piece_location_in_state(piece, self[piece])
+    }
+    fn piece_location_in_state(&self, piece: Piece, piece_state: PieceState) -> [u8; 4] {
         let initial_location = self.desc.piece_locations[piece.0 as usize];
         let mut ret = [0_u8; 4];
         for (i, axis) in Axis::iter().enumerate() {
@@ -610,12 +791,7 @@ impl Rubiks4D {
     }
     fn sticker_face(&self, sticker: Sticker) -> FaceEnum {
         let sticker_info = self.info(sticker);
-        let original_face: FaceEnum = sticker_info.color.into();
-        let current_face = self[sticker_info.piece][original_face.axis()];
-        match original_face.sign() {
-            Sign::Pos => current_face,
-            Sign::Neg => current_face.opposite(),
-        }
+        self[sticker_info.piece].transform_face(sticker_info.color.into())
     }
 
     fn piece_center_4d(&self, piece: Piece, p: StickerGeometryParams) -> Vector4<f32> {
@@ -749,6 +925,33 @@ impl IndexMut<Axis> for PieceState {
     }
 }
 impl PieceState {
+    /// Returns every orientation that a piece can be rotated into.
+    fn all_orientations() -> Vec<Self> {
+        let mut ret = vec![Self::default()];
+        let mut i = 0;
+        while let Some(&piece_state) = ret.get(i) {
+            for face in FaceEnum::iter() {
+                for direction in TwistDirectionEnum::iter() {
+                    let new_state = piece_state.twist(face, direction);
+                    if !ret.contains(&new_state) {
+                        ret.push(new_state);
+                    }
+                }
+            }
+            i += 1;
+        }
+        ret
+    }
+    /// Returns the direction faced by the sticker that initially faced
+    /// `original_face`.
+    fn transform_face(self, original_face: FaceEnum) -> FaceEnum {
+        let current_face = self[original_face.axis()];
+        match original_face.sign() {
+            Sign::Pos => current_face,
+            Sign::Neg => current_face.opposite(),
+        }
+    }
+
     #[must_use]
     fn rotate(mut self, from: Axis, to: Axis) -> Self {
         let diff = (from as u8 ^ to as u8) << 1;
@@ -1460,6 +1663,34 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_rubiks_4d_rotation_notation() {
+        let p = Rubiks4D::new(3);
+        let notation = p.notation_scheme();
+        for s in ["xy", "yx", "zw2", "wx"] {
+            let twist = notation.parse_twist(s).unwrap();
+            assert!(p.is_whole_puzzle_rotation(twist));
+            assert_eq!(notation.twist_to_string(twist), s);
+        }
+
+        // The same rotation written using the opposite face has the same
+        // symbol.
+        let twist = notation.parse_twist("xy").unwrap();
+        let face = FaceEnum::from(twist.axis).opposite();
+        let direction = TwistDirectionEnum::from(twist.direction).mirror(face.axis());
+        let opposite = Twist {
+            axis: face.into(),
+            direction: direction.into(),
+            layers: twist.layers,
+        };
+        assert_ne!(twist, opposite);
+        assert_eq!(notation.twist_to_string(opposite), "xy");
+        let (mut a, mut b) = (p.clone(), p.clone());
+        a.twist(twist).unwrap();
+        b.twist(opposite).unwrap();
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn test_rubiks_4d_twist_canonicalization() {
         for layer_count in 1..=4 {
@@ -1484,6 +1715,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rubiks_4d_state_serialization() {
+        let mut p = Rubiks4D::new(3);
+        use TwistDirectionEnum as Dir;
+        for (axis, direction) in [
+            (FaceEnum::R, Dir::U),
+            (FaceEnum::I, Dir::F2),
+            (FaceEnum::O, Dir::UFR),
+        ] {
+            p.twist(Twist {
+                axis: axis.into(),
+                direction: direction.into(),
+                layers: LayerMask(1),
+            })
+            .unwrap();
+        }
+        let bytes = p.serialize_state();
+        assert_eq!(Ok(p.clone()), Rubiks4D::deserialize_state(3, &bytes));
+
+        assert!(Rubiks4D::deserialize_state(3, &bytes[..bytes.len() - 1]).is_err());
+        let mut bad_face = bytes.clone();
+        bad_face[1] = 8;
+        assert!(Rubiks4D::deserialize_state(3, &bad_face).is_err());
+        let mut reflected = Rubiks4D::new(3).serialize_state();
+        // [L, U, F, O] has all axes distinct but is a reflection.
+        reflected[1..5].copy_from_slice(&[
+            FaceEnum::L as u8,
+            FaceEnum::U as u8,
+            FaceEnum::F as u8,
+            FaceEnum::O as u8,
+        ]);
+        assert!(Rubiks4D::deserialize_state(3, &reflected).is_err());
+
+        assert!(Rubiks4D::deserialize_state(0, &[STATE_FORMAT_VERSION]).is_err());
+        assert!(Rubiks4D::deserialize_state(MAX_LAYER_COUNT + 1, &bytes).is_err());
+    }
+
+    #[test]
+    fn test_rubiks_4d_compose_twists() {
+        let p = Rubiks4D::new(5);
+        // 120-degree corner twist.
+        let twist = Twist {
+            axis: FaceEnum::O.into(),
+            direction: TwistDirectionEnum::UFR.into(),
+            layers: LayerMask(1),
+        };
+        let reverse = p.reverse_twist(twist);
+
+        assert_eq!(
+            Some(p.canonicalize_twist(reverse)),
+            p.compose_twists(twist, twist),
+        );
+        assert_eq!(None, p.compose_twists(twist, reverse));
+        assert_eq!(
+            Some(p.canonicalize_twist(Twist {
+                layers: LayerMask(0b10),
+                ..reverse
+            })),
+            p.compose_twists(
+                twist,
+                Twist {
+                    layers: LayerMask(0b11),
+                    ..reverse
+                }
+            ),
+        );
+    }
+
+    #[test]
+    fn test_rubiks_4d_twist_order() {
+        let p = Rubiks4D::new(3);
+        for direction in TwistDirectionEnum::iter() {
+            let twist = Twist {
+                axis: FaceEnum::R.into(),
+                direction: direction.into(),
+                layers: LayerMask(1),
+            };
+            assert_eq!(Some(direction.period()), p.order_of_twist(twist));
+        }
+        // 120-degree corner twist.
+        let twist = Twist {
+            axis: FaceEnum::O.into(),
+            direction: TwistDirectionEnum::UFR.into(),
+            layers: LayerMask(1),
+        };
+        assert_eq!(Some(3), p.order_of_twist(twist));
+
+        for layer_count in 1..=3 {
+            crate::puzzle::tests::test_twist_reverse_consistency(&Rubiks4D::new(layer_count));
+        }
+    }
+
     fn twist_comparison_key(p: &Rubiks4D, twist: Twist) -> impl PartialEq {
         const SOME_PROGRESS: f32 = 0.1;
 
@@ -0,0 +1,100 @@
+//! Self-consistency checks for puzzle definitions.
+
+use itertools::Itertools;
+
+use super::*;
+
+/// Result of checking one puzzle type for self-consistency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Puzzle type that was checked.
+    pub ty: PuzzleTypeEnum,
+    /// Description of each check that failed.
+    pub failures: Vec<String>,
+}
+impl ValidationReport {
+    /// Returns whether every check passed.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Returns every puzzle type that can be constructed.
+pub fn all_puzzle_types() -> impl Iterator<Item = PuzzleTypeEnum> {
+    itertools::chain(
+        rubiks_3d::LAYER_COUNT_RANGE.map(|layer_count| PuzzleTypeEnum::Rubiks3D { layer_count }),
+        rubiks_4d::LAYER_COUNT_RANGE.map(|layer_count| PuzzleTypeEnum::Rubiks4D { layer_count }),
+    )
+}
+
+/// Checks every puzzle type for self-consistency.
+pub fn validate_all() -> Vec<ValidationReport> {
+    all_puzzle_types().map(validate).collect()
+}
+
+/// Checks a puzzle type for self-consistency: every sticker has a valid color
+/// and belongs to its piece, every color is used, opposite twist axes are
+/// antiparallel, every twist is undone by its reverse, a new puzzle is solved,
+/// and reversing a scramble solves it again.
+pub fn validate(ty: PuzzleTypeEnum) -> ValidationReport {
+    let mut failures = vec![];
+
+    for (i, sticker_info) in ty.stickers().iter().enumerate() {
+        if sticker_info.color.0 as usize >= ty.faces().len() {
+            failures.push(format!("Sticker {i} has invalid color"));
+        }
+        if !ty
+            .info(sticker_info.piece)
+            .stickers
+            .contains(&Sticker(i as _))
+        {
+            failures.push(format!("Sticker {i} is missing from its piece"));
+        }
+    }
+    for color in ty.unused_colors() {
+        failures.push(format!("Color {} has no stickers", ty.info(color).name));
+    }
+
+    for axis in (0..ty.twist_axes().len() as u8).map(TwistAxis) {
+        if !ty.is_opposite_twist_axis_consistent(axis) {
+            let name = ty.info(axis).name;
+            failures.push(format!("Opposite of twist axis {name} is inconsistent"));
+        }
+    }
+
+    // Twists of several layers are made of single-layer twists, so checking
+    // each layer on its own is enough.
+    let axes = (0..ty.twist_axes().len() as u8).map(TwistAxis);
+    let directions = (0..ty.twist_directions().len() as u8).map(TwistDirection);
+    let layers = (0..ty.layer_count()).map(|i| LayerMask(1 << i));
+    for (axis, direction, layers) in itertools::iproduct!(axes, directions, layers) {
+        let twist = Twist {
+            axis,
+            direction,
+            layers,
+        };
+        if !ty.is_reverse_twist_consistent(twist) {
+            failures.push(format!("Reverse of {twist:?} does not undo it"));
+        }
+    }
+
+    let mut p = Puzzle::new(ty);
+    if !p.is_solved() {
+        failures.push("New puzzle is not solved".to_owned());
+    }
+
+    let scramble = (0..ty.scramble_moves_count())
+        .map(|_| Twist::from_rng(ty))
+        .collect_vec();
+    let reversed_scramble = scramble.iter().rev().map(|&twist| ty.reverse_twist(twist));
+    for twist in scramble.iter().copied().chain(reversed_scramble) {
+        if let Err(e) = p.twist(twist) {
+            failures.push(format!("Scramble twist {twist:?} failed: {e}"));
+        }
+    }
+    if !p.is_solved() {
+        failures.push("Reversed scramble does not solve the puzzle".to_owned());
+    }
+
+    ValidationReport { ty, failures }
+}
@@ -2,19 +2,43 @@
 
 use cgmath::*;
 use itertools::Itertools;
+use std::collections::HashMap;
 
 use super::RgbaVertex;
-use crate::preferences::Preferences;
+use crate::preferences::{ColorMode, Preferences, ViewPreferences};
 use crate::puzzle::*;
-use crate::util::IterCyclicPairsExt;
+use crate::util::{self, Aabb, IterCyclicPairsExt};
 
 const OUTLINE_SCALE: f32 = 1.0 / 512.0;
 const OUTLINE_WEDGE_VERTS_PER_RADIAN: f32 = 3.0;
+/// Number of concentric outlines used to approximate a blurred selection glow.
+const SELECTION_GLOW_LAYER_COUNT: usize = 8;
+/// Categorical palette for `ColorMode::PieceType`. Puzzles with more piece
+/// types than this use a gradient through it instead; see
+/// `piece_type_color()`.
+const PIECE_TYPE_COLORS: [egui::Color32; 10] = [
+    egui::Color32::from_rgb(0x4e, 0x79, 0xa7), // blue
+    egui::Color32::from_rgb(0xf2, 0x8e, 0x2b), // orange
+    egui::Color32::from_rgb(0xe1, 0x57, 0x59), // red
+    egui::Color32::from_rgb(0x76, 0xb7, 0xb2), // teal
+    egui::Color32::from_rgb(0x59, 0xa1, 0x4f), // green
+    egui::Color32::from_rgb(0xed, 0xc9, 0x48), // yellow
+    egui::Color32::from_rgb(0xb0, 0x7a, 0xa1), // purple
+    egui::Color32::from_rgb(0xff, 0x9d, 0xa7), // pink
+    egui::Color32::from_rgb(0x9c, 0x75, 0x5f), // brown
+    egui::Color32::from_rgb(0xba, 0xb0, 0xac), // gray
+];
+/// Opacity of the twist rotation guide at the start of a twist.
+const TWIST_ROTATION_GUIDE_OPACITY: f32 = 0.4;
+/// Thickness of the twist rotation guide when it is a line.
+const TWIST_ROTATION_GUIDE_LINE_SIZE: f32 = 3.0;
 
 pub(super) fn make_puzzle_mesh(
     puzzle: &mut PuzzleController,
     prefs: &Preferences,
+    view_prefs: &ViewPreferences,
     sticker_geometries: &[ProjectedStickerGeometry],
+    visible_bounds: Aabb<2>,
 ) -> (Vec<RgbaVertex>, Vec<u32>) {
     // Triangulate polygons and combine the whole puzzle into one mesh.
     let mut verts = vec![];
@@ -26,27 +50,48 @@ pub(super) fn make_puzzle_mesh(
     // incrementation for each sticker to get the next-largest `f32` value.
     let mut z = 0.5_f32;
 
-    let face_colors = &prefs.colors.face_colors_list(puzzle.ty());
+    let face_colors = &prefs.face_colors_list(puzzle.ty());
+
+    if view_prefs.selection_glow.enabled {
+        generate_selection_glow(
+            &mut verts,
+            &mut indices,
+            puzzle,
+            view_prefs,
+            sticker_geometries,
+        );
+    }
 
     for geom in sticker_geometries {
-        let sticker_info = puzzle.info(geom.sticker);
+        // Skip stickers that are entirely off screen.
+        if !geom.bounds_2d().intersects(&visible_bounds) {
+            continue;
+        }
 
-        let visual_state = puzzle.visual_piece_state(sticker_info.piece);
+        let visual_state = puzzle.visual_sticker_state(geom.sticker);
 
         // Determine sticker alpha.
         let alpha = visual_state.opacity(prefs);
 
         // Determine sticker fill color.
-        let sticker_color = egui::Rgba::from(if prefs.colors.blindfold {
+        let opaque_sticker_color = egui::Rgba::from(if prefs.colors.blindfold {
             prefs.colors.blind_face
         } else {
-            face_colors[puzzle.info(geom.sticker).color.0 as usize]
-        })
-        .multiply(alpha);
+            match view_prefs.color_mode {
+                ColorMode::Stickers => face_colors[puzzle.sticker_color(geom.sticker).0 as usize],
+                ColorMode::PieceType => {
+                    let piece_type = puzzle.info(puzzle.info(geom.sticker).piece).piece_type;
+                    piece_type_color(piece_type, puzzle.piece_types().len())
+                }
+            }
+        });
+        let sticker_color = opaque_sticker_color.multiply(alpha);
+        let gap_color = egui::Rgba::from(prefs.colors.sticker_gap).multiply(alpha);
 
         // Determine outline appearance.
+        let is_sticker_selected = puzzle.selection().has_sticker(geom.sticker);
         let outline_color = visual_state
-            .outline_color(prefs, puzzle.selection().contains(&geom.sticker))
+            .outline_color(prefs, opaque_sticker_color, is_sticker_selected)
             .multiply(alpha);
         let outline_size = visual_state.outline_size(prefs);
 
@@ -81,18 +126,67 @@ pub(super) fn make_puzzle_mesh(
 
         // Generate face vertices.
         for polygon in &*geom.front_polygons {
-            let base = verts.len() as u32;
-            verts.extend(polygon.verts.iter().map(|v| RgbaVertex {
-                pos: [v.x, v.y, z],
-                color: [
-                    sticker_color.r() * polygon.illumination,
-                    sticker_color.g() * polygon.illumination,
-                    sticker_color.b() * polygon.illumination,
-                    sticker_color.a(),
-                ],
-            }));
             let n = polygon.verts.len() as u32;
-            indices.extend((2..n).flat_map(|i| [base, base + i - 1, base + i]));
+            let center_sum: Vector2<f32> = polygon.verts.iter().map(|v| vec2(v.x, v.y)).sum();
+            let center = center_sum / n as f32;
+            // Shrink the sticker toward its center to leave room for the gap.
+            let sticker_verts = polygon
+                .verts
+                .iter()
+                .map(|v| center + (vec2(v.x, v.y) - center) * (1.0 - view_prefs.sticker_gap))
+                .collect_vec();
+
+            let color_with_brightness = |brightness: f32| {
+                let brightness = brightness * polygon.illumination;
+                [
+                    sticker_color.r() * brightness,
+                    sticker_color.g() * brightness,
+                    sticker_color.b() * brightness,
+                    sticker_color.a(),
+                ]
+            };
+
+            let base = verts.len() as u32;
+            if view_prefs.ambient_occlusion > 0.0 {
+                // Add a vertex in the center so that the color can fade
+                // darker toward the edges.
+                verts.push(RgbaVertex {
+                    pos: [center.x, center.y, z],
+                    color: color_with_brightness(1.0),
+                });
+                let edge_color = color_with_brightness(1.0 - view_prefs.ambient_occlusion);
+                verts.extend(sticker_verts.iter().map(|v| RgbaVertex {
+                    pos: [v.x, v.y, z],
+                    color: edge_color,
+                }));
+                indices.extend((1..=n).flat_map(|i| [base, base + i, base + i % n + 1]));
+            } else {
+                let color = color_with_brightness(1.0);
+                verts.extend(sticker_verts.iter().map(|v| RgbaVertex {
+                    pos: [v.x, v.y, z],
+                    color,
+                }));
+                indices.extend((2..n).flat_map(|i| [base, base + i - 1, base + i]));
+            }
+
+            if view_prefs.sticker_gap > 0.0 {
+                // Fill the whole polygon with the gap color. This is at the
+                // same depth as the sticker but drawn after it, so it only
+                // shows through in the gap.
+                let brightness = polygon.illumination;
+                let color = [
+                    gap_color.r() * brightness,
+                    gap_color.g() * brightness,
+                    gap_color.b() * brightness,
+                    gap_color.a(),
+                ];
+                let base = verts.len() as u32;
+                verts.extend(polygon.verts.iter().map(|v| RgbaVertex {
+                    pos: [v.x, v.y, z],
+                    color,
+                }));
+                indices.extend((2..n).flat_map(|i| [base, base + i - 1, base + i]));
+            }
         }
 
         // Increase the Z value very slightly. If this scares you, click this
@@ -100,9 +194,92 @@ pub(super) fn make_puzzle_mesh(
         z = f32::from_bits(z.to_bits() + 1);
     }
 
+    if prefs.interaction.show_twist_rotation_guide {
+        if let Some((guide_verts, progress)) = puzzle.twist_rotation_guide(prefs) {
+            // Fade out as the twist completes.
+            let opacity = TWIST_ROTATION_GUIDE_OPACITY * (1.0 - progress);
+            let color = egui::Rgba::from(prefs.outlines.default_color)
+                .multiply(opacity)
+                .to_array();
+            // Draw on top of all the stickers.
+            let make_vert = |Point2 { x, y }| RgbaVertex {
+                pos: [x, y, z],
+                color,
+            };
+            let guide_verts = guide_verts.iter().map(|v| cgmath::point2(v.x, v.y));
+            if let [a, b] = guide_verts.clone().collect_vec()[..] {
+                generate_outline_geometry(
+                    &mut verts,
+                    &mut indices,
+                    &[[a, b]],
+                    TWIST_ROTATION_GUIDE_LINE_SIZE,
+                    make_vert,
+                );
+            } else {
+                let base = verts.len() as u32;
+                let n = guide_verts.len() as u32;
+                verts.extend(guide_verts.map(make_vert));
+                indices.extend((2..n).flat_map(|i| [base, base + i - 1, base + i]));
+            }
+        }
+    }
+
     (verts, indices)
 }
 
+/// Generates a glow around the selected pieces, underneath all the stickers.
+/// The glow is a stack of progressively smaller outlines, each drawn at a
+/// slightly greater depth than the last so that they blend together. Within a
+/// single layer, overlapping outlines are at the same depth and so are only
+/// drawn once.
+fn generate_selection_glow(
+    verts: &mut Vec<RgbaVertex>,
+    indices: &mut Vec<u32>,
+    puzzle: &PuzzleController,
+    view_prefs: &ViewPreferences,
+    sticker_geometries: &[ProjectedStickerGeometry],
+) {
+    let glow = view_prefs.selection_glow;
+    let selection = puzzle.selection();
+    let outlines = sticker_geometries
+        .iter()
+        .filter(|geom| {
+            selection.has_sticker(geom.sticker)
+                || selection.has_piece(puzzle.info(geom.sticker).piece)
+        })
+        .flat_map(|geom| &*geom.front_polygons)
+        .flat_map(|polygon| {
+            polygon
+                .verts
+                .iter()
+                .map(|p| cgmath::point2(p.x, p.y))
+                .cyclic_pairs()
+                .map(|(a, b)| [a, b])
+        })
+        .collect_vec();
+    if outlines.is_empty() {
+        return;
+    }
+
+    // Each layer covers the ones before it, so the glow is brightest near the
+    // piece and fades out toward the edge.
+    let layer_color = egui::Rgba::from(glow.color)
+        .multiply(1.0 / SELECTION_GLOW_LAYER_COUNT as f32)
+        .to_array();
+    // Stickers start at 0.5, so this is always underneath them.
+    let mut z = 0.25_f32;
+    for i in (1..=SELECTION_GLOW_LAYER_COUNT).rev() {
+        let size = glow.radius * i as f32 / SELECTION_GLOW_LAYER_COUNT as f32;
+        generate_outline_geometry(verts, indices, &outlines, size, |Point2 { x, y }| {
+            RgbaVertex {
+                pos: [x, y, z],
+                color: layer_color,
+            }
+        });
+        z = f32::from_bits(z.to_bits() + 1);
+    }
+}
+
 fn generate_outline_geometry(
     verts_out: &mut Vec<RgbaVertex>,
     indices_out: &mut Vec<u32>,
@@ -188,3 +365,119 @@ fn generate_outline_geometry(
         }
     }
 }
+
+/// Splits a mesh into chunks that each have at most `max_verts` vertices and
+/// `max_indices` indices, so that each chunk fits in its own pair of buffers.
+/// Triangles stay in their original order across chunks. If the whole mesh
+/// already fits, it is returned as the only chunk without copying.
+pub(super) fn split_mesh(
+    verts: Vec<RgbaVertex>,
+    indices: Vec<u32>,
+    max_verts: usize,
+    max_indices: usize,
+) -> Vec<(Vec<RgbaVertex>, Vec<u32>)> {
+    if verts.len() <= max_verts && indices.len() <= max_indices {
+        return vec![(verts, indices)];
+    }
+    assert!(
+        max_verts >= 3 && max_indices >= 3,
+        "buffer limits too small for one triangle",
+    );
+
+    let mut chunks = vec![];
+    let mut chunk_verts = vec![];
+    let mut chunk_indices = vec![];
+    // Index of each original vertex within the current chunk.
+    let mut new_indices: HashMap<u32, u32> = HashMap::new();
+    for tri in indices.chunks_exact(3) {
+        let new_vert_count = tri
+            .iter()
+            .unique()
+            .filter(|&&i| !new_indices.contains_key(&i))
+            .count();
+        if chunk_verts.len() + new_vert_count > max_verts || chunk_indices.len() + 3 > max_indices {
+            chunks.push((
+                std::mem::take(&mut chunk_verts),
+                std::mem::take(&mut chunk_indices),
+            ));
+            new_indices.clear();
+        }
+        for &i in tri {
+            let new_index = *new_indices.entry(i).or_insert_with(|| {
+                chunk_verts.push(verts[i as usize]);
+                chunk_verts.len() as u32 - 1
+            });
+            chunk_indices.push(new_index);
+        }
+    }
+    if !chunk_indices.is_empty() {
+        chunks.push((chunk_verts, chunk_indices));
+    }
+    chunks
+}
+
+/// Returns the color of a piece type for `ColorMode::PieceType`. If there are
+/// more piece types than colors in the palette, colors are sampled evenly along
+/// a gradient through the palette so that each piece type still gets its own.
+fn piece_type_color(piece_type: PieceType, piece_type_count: usize) -> egui::Color32 {
+    let i = piece_type.0 as usize;
+    if piece_type_count <= PIECE_TYPE_COLORS.len() {
+        return PIECE_TYPE_COLORS[i];
+    }
+    let max_index = PIECE_TYPE_COLORS.len() - 1;
+    let t = i as f32 / (piece_type_count - 1) as f32 * max_index as f32;
+    let lo = (t as usize).min(max_index - 1);
+    let a = egui::Rgba::from(PIECE_TYPE_COLORS[lo]);
+    let b = egui::Rgba::from(PIECE_TYPE_COLORS[lo + 1]);
+    util::mix(a, b, t - lo as f32).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piece_type_colors_are_distinct() {
+        for piece_type_count in [3, PIECE_TYPE_COLORS.len(), 25] {
+            let colors = (0..piece_type_count as u8)
+                .map(|i| piece_type_color(PieceType(i), piece_type_count))
+                .collect_vec();
+            assert!(colors.iter().all_unique(), "{piece_type_count}: {colors:?}");
+        }
+    }
+
+    #[test]
+    fn test_split_mesh() {
+        // A strip of quads, each made of two triangles that share an edge.
+        const QUAD_COUNT: u32 = 1000;
+        let verts = (0..2 * (QUAD_COUNT + 1))
+            .map(|i| RgbaVertex {
+                pos: [i as f32, 0.0, 0.0],
+                color: [1.0; 4],
+            })
+            .collect_vec();
+        let indices = (0..QUAD_COUNT)
+            .flat_map(|i| [2 * i, 2 * i + 1, 2 * i + 2, 2 * i + 1, 2 * i + 3, 2 * i + 2])
+            .collect_vec();
+        let triangles = |verts: &[RgbaVertex], indices: &[u32]| {
+            indices
+                .iter()
+                .map(|&i| verts[i as usize].pos[0])
+                .collect_vec()
+        };
+        let expected = triangles(&verts, &indices);
+
+        let unsplit = split_mesh(verts.clone(), indices.clone(), usize::MAX, usize::MAX);
+        assert_eq!(unsplit.len(), 1);
+
+        let chunks = split_mesh(verts, indices, 100, 150);
+        assert!(chunks.len() > 1);
+        let mut actual = vec![];
+        for (chunk_verts, chunk_indices) in &chunks {
+            assert!(chunk_verts.len() <= 100);
+            assert!(chunk_indices.len() <= 150);
+            actual.extend(triangles(chunk_verts, chunk_indices));
+        }
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,5 +1,6 @@
 //! Rendering logic.
 
+use cgmath::ElementWise;
 use instant::Instant;
 use std::sync::Arc;
 
@@ -10,7 +11,8 @@ mod state;
 mod structs;
 
 use crate::app::App;
-use crate::puzzle::ProjectedStickerGeometry;
+use crate::puzzle::{Piece, ProjectedStickerGeometry, PuzzleInfo, Sticker};
+use crate::util::Aabb;
 use cache::{CachedDynamicBuffer, CachedUniformBuffer};
 pub(crate) use state::GraphicsState;
 use structs::*;
@@ -24,6 +26,22 @@ struct PuzzleRenderParams {
     scale: f32,
     align_h: f32,
     align_v: f32,
+    pan: [f32; 2],
+}
+impl PuzzleRenderParams {
+    /// Returns the scale factor from puzzle coordinates to normalized device
+    /// coordinates along each axis.
+    fn scale(&self) -> cgmath::Vector2<f32> {
+        let size = cgmath::vec2(self.target_w as f32, self.target_h as f32);
+        let pixel_scale = f32::min(size.x, size.y) * self.scale;
+        cgmath::vec2(pixel_scale / size.x, pixel_scale / size.y)
+    }
+    /// Returns the position of the puzzle's origin in normalized device
+    /// coordinates.
+    fn align(&self) -> cgmath::Vector2<f32> {
+        let pan = cgmath::Vector2::from(self.pan).mul_element_wise(self.scale());
+        cgmath::vec2(self.align_h, self.align_v) + pan
+    }
 }
 
 pub(crate) struct PuzzleRenderCache {
@@ -31,8 +49,9 @@ pub(crate) struct PuzzleRenderCache {
     last_params: Option<PuzzleRenderParams>,
     last_puzzle_geometry: Option<Arc<Vec<ProjectedStickerGeometry>>>,
 
-    vertex_buffer: CachedDynamicBuffer,
-    index_buffer: CachedDynamicBuffer,
+    /// Vertex and index buffers for each chunk of the mesh. There is usually
+    /// only one chunk, unless the mesh is too big to fit in one buffer.
+    mesh_buffers: Vec<(CachedDynamicBuffer, CachedDynamicBuffer)>,
     uniform_buffer: CachedUniformBuffer<BasicUniform>,
 
     multisample_texture: Option<(wgpu::Texture, wgpu::TextureView)>,
@@ -48,14 +67,7 @@ impl Default for PuzzleRenderCache {
             last_params: None,
             last_puzzle_geometry: None,
 
-            vertex_buffer: CachedDynamicBuffer::new::<RgbaVertex>(
-                Some("puzzle_vertex_buffer"),
-                wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-            ),
-            index_buffer: CachedDynamicBuffer::new::<u32>(
-                Some("puzzle_index_buffer"),
-                wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::INDEX,
-            ),
+            mesh_buffers: vec![],
             uniform_buffer: CachedUniformBuffer::new(Some("puzzle_uniform_buffer"), 0),
 
             multisample_texture: None,
@@ -95,6 +107,35 @@ impl PuzzleRenderCache {
 
         ret
     }
+
+    /// Returns the stickers visible in the most recently drawn frame, along
+    /// with the position of each one's centroid in the same coordinates as
+    /// `App::cursor_pos`. Stickers that face away from the camera or whose
+    /// centroid is covered by another sticker are excluded.
+    pub(crate) fn visible_stickers(&self) -> Vec<(Sticker, cgmath::Point2<f32>)> {
+        let (Some(params), Some(geometry)) = (&self.last_params, &self.last_puzzle_geometry) else {
+            return vec![];
+        };
+
+        let scale = params.scale();
+        let align = params.align();
+
+        // Geometry is sorted from back to front, so only stickers later in the
+        // list can cover each sticker.
+        (0..geometry.len())
+            .filter_map(|i| {
+                let centroid = geometry[i].front_centroid()?;
+                if geometry[i + 1..].iter().any(|g| g.covers_point(centroid)) {
+                    return None;
+                }
+                let pos = cgmath::point2(
+                    centroid.x * scale.x + align.x,
+                    centroid.y * scale.y + align.y,
+                );
+                Some((geometry[i].sticker, pos))
+            })
+            .collect()
+    }
 }
 
 pub(crate) fn draw_puzzle(
@@ -129,7 +170,7 @@ pub(crate) fn draw_puzzle(
     puzzle.update_geometry(delta, &prefs.interaction);
 
     // Invalidate cache if parameters changed.
-    force_redraw |= cache.set_params_and_invalidate(PuzzleRenderParams {
+    let params = PuzzleRenderParams {
         target_w: width,
         target_h: height,
         sample_count: prefs.gfx.sample_count(),
@@ -137,14 +178,11 @@ pub(crate) fn draw_puzzle(
         scale: view_prefs.scale,
         align_h: view_prefs.align_h,
         align_v: view_prefs.align_v,
-    });
-
-    // Calculate scale.
-    let scale = {
-        let min_dimen = f32::min(size.x, size.y);
-        let pixel_scale = min_dimen * view_prefs.scale;
-        cgmath::vec2(pixel_scale / size.x, pixel_scale / size.y)
+        pan: view_prefs.pan,
     };
+    let scale = params.scale();
+    let align = params.align();
+    force_redraw |= cache.set_params_and_invalidate(params);
 
     // If the puzzle geometry has changed, force a redraw.
     let puzzle_geometry = puzzle.geometry(prefs);
@@ -157,19 +195,43 @@ pub(crate) fn draw_puzzle(
     }
     cache.last_puzzle_geometry = Some(Arc::clone(&puzzle_geometry));
 
+    // Select or deselect the pieces whose stickers are inside the box.
+    if let Some(box_select) = app.pending_box_select.take() {
+        let a = box_select.start - align;
+        let b = box_select.end - align;
+        let min = cgmath::point2(a.x.min(b.x) / scale.x, a.y.min(b.y) / scale.y);
+        let max = cgmath::point2(a.x.max(b.x) / scale.x, a.y.max(b.y) / scale.y);
+        let pieces: Vec<Piece> = puzzle_geometry
+            .iter()
+            .filter(|geom| geom.is_in_rect(min, max))
+            .map(|geom| puzzle.info(geom.sticker).piece)
+            .collect();
+        puzzle.set_pieces_selected(pieces, box_select.select);
+    }
+
     // Determine which sticker(s) are at the mouse cursor, in order from front
-    // to back.
+    // to back. If there are none, then search nearby so that thin stickers are
+    // still easy to hover.
     if let Some(cursor_pos) = app.cursor_pos {
-        let transformed_cursor_pos = cgmath::point2(
-            (cursor_pos.x - view_prefs.align_h) / scale.x,
-            (cursor_pos.y - view_prefs.align_v) / scale.y,
-        );
-        let hovered_stickers = puzzle_geometry.iter().rev().filter_map(move |geom| {
-            Some((geom.sticker, geom.twists_for_point(transformed_cursor_pos)?))
+        let pixel_size = cgmath::vec2(2.0 / size.x, 2.0 / size.y);
+        let pick_offsets = std::iter::once(cgmath::vec2(0.0, 0.0))
+            .chain(pick_neighborhood(prefs.interaction.pick_radius));
+        let geometry = Arc::clone(&puzzle_geometry);
+        let hovered_stickers = pick_offsets.flat_map(move |offset| {
+            let p = cursor_pos + offset.mul_element_wise(pixel_size) - align;
+            let transformed_cursor_pos = cgmath::point2(p.x / scale.x, p.y / scale.y);
+            let geometry = Arc::clone(&geometry);
+            (0..geometry.len()).rev().filter_map(move |i| {
+                let geom = &geometry[i];
+                if !geom.bounds_2d().contains(transformed_cursor_pos.into()) {
+                    return None;
+                }
+                Some((geom.sticker, geom.twists_for_point(transformed_cursor_pos)?))
+            })
         });
-        puzzle.update_hovered_sticker(hovered_stickers);
+        puzzle.update_hovered_sticker(hovered_stickers, &prefs.interaction);
     } else {
-        puzzle.update_hovered_sticker([]);
+        puzzle.update_hovered_sticker([], &prefs.interaction);
     }
 
     // Animate puzzle decorations (colors, opacity, and outlines). Do this after
@@ -181,8 +243,17 @@ pub(crate) fn draw_puzzle(
         return None; // No repaint needed.
     }
 
+    // Find the part of the puzzle that is on screen, so that stickers outside
+    // of it can be skipped when generating the mesh.
+    let visible_bounds = Aabb::from_points([-1.0, 1.0].map(|ndc| {
+        let p = (cgmath::vec2(ndc, ndc) - align).div_element_wise(scale);
+        [p.x, p.y]
+    }))
+    .expect("no corners");
+
     // Generate the mesh.
-    let (mut verts, mut indices) = mesh::make_puzzle_mesh(puzzle, prefs, &puzzle_geometry);
+    let (verts, indices) =
+        mesh::make_puzzle_mesh(puzzle, prefs, &view_prefs, &puzzle_geometry, visible_bounds);
 
     // Create "out" texture that will ultimately be returned.
     let (out_texture, out_texture_view) = cache.out_texture.get_or_insert_with(|| {
@@ -328,24 +399,39 @@ pub(crate) fn draw_puzzle(
                 })
         }));
 
-        // Populate vertex buffer.
-        let vertex_buffer = cache.vertex_buffer.write_all(gfx, &mut verts);
-        render_pass.set_vertex_buffer(0, vertex_buffer);
-
-        // Populate index buffer.
-        let index_buffer = cache.index_buffer.write_all(gfx, &mut indices);
-        render_pass.set_index_buffer(index_buffer, wgpu::IndexFormat::Uint32);
-
         // Populate and bind uniform.
         let uniform = BasicUniform {
             scale: scale.into(),
-            align: [view_prefs.align_h, view_prefs.align_v],
+            align: align.into(),
         };
         cache.uniform_buffer.write(gfx, &uniform);
         render_pass.set_bind_group(0, cache.uniform_buffer.bind_group(gfx), &[]);
 
+        // Split the mesh into chunks that each fit in one buffer.
+        let max_buffer_size = gfx.device.limits().max_buffer_size as usize;
+        let chunks = mesh::split_mesh(
+            verts,
+            indices,
+            max_buffer_size / std::mem::size_of::<RgbaVertex>(),
+            max_buffer_size / std::mem::size_of::<u32>(),
+        );
+        if cache.mesh_buffers.len() < chunks.len() {
+            cache
+                .mesh_buffers
+                .resize_with(chunks.len(), new_mesh_buffers);
+        }
+
         // Draw stickers.
-        render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+        for ((mut chunk_verts, mut chunk_indices), (vertex_buffer, index_buffer)) in
+            chunks.into_iter().zip(&mut cache.mesh_buffers)
+        {
+            render_pass.set_vertex_buffer(0, vertex_buffer.write_all(gfx, &mut chunk_verts));
+            render_pass.set_index_buffer(
+                index_buffer.write_all(gfx, &mut chunk_indices),
+                wgpu::IndexFormat::Uint32,
+            );
+            render_pass.draw_indexed(0..chunk_indices.len() as u32, 0, 0..1);
+        }
     }
 
     drop(render_pass);
@@ -355,6 +441,34 @@ pub(crate) fn draw_puzzle(
     Some(out_texture.create_view(&wgpu::TextureViewDescriptor::default()))
 }
 
+fn new_mesh_buffers() -> (CachedDynamicBuffer, CachedDynamicBuffer) {
+    (
+        CachedDynamicBuffer::new::<RgbaVertex>(
+            Some("puzzle_vertex_buffer"),
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
+        ),
+        CachedDynamicBuffer::new::<u32>(
+            Some("puzzle_index_buffer"),
+            wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::INDEX,
+        ),
+    )
+}
+
+/// Returns offsets (in pixels) of points to check for stickers when there is no
+/// sticker directly under the cursor, in order of increasing distance.
+fn pick_neighborhood(radius: f32) -> impl Iterator<Item = cgmath::Vector2<f32>> {
+    const SAMPLES_PER_RING: usize = 8;
+
+    let ring_count = radius.max(0.0).ceil() as usize;
+    (1..=ring_count).flat_map(move |ring| {
+        let r = f32::min(ring as f32, radius);
+        (0..SAMPLES_PER_RING).map(move |i| {
+            let angle = std::f32::consts::TAU * i as f32 / SAMPLES_PER_RING as f32;
+            cgmath::vec2(angle.cos(), angle.sin()) * r
+        })
+    })
+}
+
 fn extent3d(width: u32, height: u32) -> wgpu::Extent3d {
     wgpu::Extent3d {
         width,
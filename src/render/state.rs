@@ -1,4 +1,7 @@
+use itertools::Itertools;
+
 use super::shaders::Shaders;
+use crate::preferences::GfxPreferences;
 
 /// Graphics state for the whole window.
 pub(crate) struct GraphicsState {
@@ -10,21 +13,36 @@ pub(crate) struct GraphicsState {
 
     pub(super) shaders: Shaders,
 
+    /// Name of the graphics adapter in use.
+    pub(crate) adapter_name: String,
+    /// Names of all graphics adapters that could be used.
+    pub(crate) available_adapters: Vec<String>,
+
     pub(crate) scale_factor: f32,
 
     /// 1x1 texture used as a temporary value. Its contents are not important.
     pub(crate) dummy_texture: wgpu::Texture,
 }
 impl GraphicsState {
-    pub(crate) async fn new(window: &winit::window::Window) -> Self {
+    pub(crate) async fn new(window: &winit::window::Window, prefs: &GfxPreferences) -> Self {
         let size = window.inner_size();
 
-        // Create surface.
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
-        let surface = unsafe { instance.create_surface(&window) };
-
-        // Request adapter.
-        let adapter = request_adapter(&instance, &surface).await;
+        // Create surface and request adapter, falling back to any backend if
+        // the preferred one doesn't work.
+        let (surface, adapter, available_adapters) =
+            match request_adapter(window, prefs.backend.to_wgpu(), prefs).await {
+                Some(ret) => ret,
+                None => {
+                    log::warn!(
+                        "no graphics adapter available for {} backend; trying all backends",
+                        prefs.backend,
+                    );
+                    request_adapter(window, wgpu::Backends::all(), prefs)
+                        .await
+                        .expect("unable to request graphics adapter")
+                }
+            };
+        let adapter_name = adapter.get_info().name;
 
         // Request device.
         let (device, queue) = adapter
@@ -80,6 +98,9 @@ impl GraphicsState {
 
             shaders,
 
+            adapter_name,
+            available_adapters,
+
             scale_factor,
 
             dummy_texture,
@@ -180,20 +201,48 @@ impl GraphicsState {
     }
 }
 
-async fn request_adapter(instance: &wgpu::Instance, surface: &wgpu::Surface) -> wgpu::Adapter {
+/// Creates a surface for the window and requests an adapter using one of
+/// `backends`. Also returns the names of all adapters that could be used.
+async fn request_adapter(
+    window: &winit::window::Window,
+    backends: wgpu::Backends,
+    prefs: &GfxPreferences,
+) -> Option<(wgpu::Surface, wgpu::Adapter, Vec<String>)> {
+    let instance = wgpu::Instance::new(backends);
+    let surface = unsafe { instance.create_surface(&window) };
+
+    #[cfg(target_arch = "wasm32")]
+    let available_adapters = vec![];
+    #[cfg(not(target_arch = "wasm32"))]
+    let available_adapters = instance
+        .enumerate_adapters(backends)
+        .filter(|adapter| adapter.is_surface_supported(&surface))
+        .map(|adapter| adapter.get_info().name)
+        .unique()
+        .collect_vec();
+
+    // Use the adapter that the user picked, if it's available.
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(adapter_name) = &prefs.adapter {
+        let pinned_adapter = instance.enumerate_adapters(backends).find(|adapter| {
+            adapter.get_info().name == *adapter_name && adapter.is_surface_supported(&surface)
+        });
+        match pinned_adapter {
+            Some(adapter) => return Some((surface, adapter, available_adapters)),
+            None => log::warn!("graphics adapter {adapter_name:?} is not available"),
+        }
+    }
+
     let mut opts = wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::HighPerformance,
-        compatible_surface: Some(surface),
+        power_preference: prefs.power_preference.to_wgpu(),
+        compatible_surface: Some(&surface),
         force_fallback_adapter: false,
     };
 
-    if let Some(adapter) = instance.request_adapter(&opts).await {
-        return adapter;
+    let mut adapter = instance.request_adapter(&opts).await;
+    if adapter.is_none() {
+        opts.force_fallback_adapter = true;
+        adapter = instance.request_adapter(&opts).await;
     }
-    opts.force_fallback_adapter = true;
-    if let Some(adapter) = instance.request_adapter(&opts).await {
-        return adapter;
-    }
-
-    panic!("unable to request graphics adapter")
+    Some((surface, adapter?, available_adapters))
 }
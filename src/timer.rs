@@ -0,0 +1,187 @@
+//! Speedsolving timer with inspection and rolling averages.
+
+use instant::{Duration, Instant};
+
+use crate::puzzle::{PuzzleController, TwistMetric};
+
+/// Length of the inspection period after a full scramble.
+pub const INSPECTION_DURATION: Duration = Duration::from_secs(15);
+
+/// Phase of a solve.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TimerPhase {
+    /// No solve in progress.
+    #[default]
+    Idle,
+    /// The puzzle has been scrambled and the solver is inspecting it. The
+    /// timer starts on the first twist or when inspection runs out.
+    Inspection { start: Instant },
+    /// The solve is in progress.
+    Running { start: Instant },
+    /// The solve has finished.
+    Stopped { duration: Duration },
+}
+
+/// Record of a completed solve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolveRecord {
+    /// Time from the first twist to the solved state.
+    pub duration: Duration,
+    /// Number of twists in the solve, in STM.
+    pub twist_count: usize,
+    /// Log file of the solve.
+    pub log: String,
+}
+
+/// Timer that tracks the phases of a solve and keeps a session of completed
+/// solves.
+#[derive(Debug, Default, Clone)]
+pub struct SolveTimer {
+    phase: TimerPhase,
+    solves: Vec<SolveRecord>,
+}
+impl SolveTimer {
+    /// Returns the current phase of the timer.
+    pub fn phase(&self) -> TimerPhase {
+        self.phase
+    }
+    /// Returns all solves recorded this session, in order from oldest to
+    /// newest.
+    pub fn solves(&self) -> &[SolveRecord] {
+        &self.solves
+    }
+
+    /// Begins inspection. This should be called after the puzzle is fully
+    /// scrambled.
+    pub fn start_inspection(&mut self) {
+        self.phase = TimerPhase::Inspection {
+            start: Instant::now(),
+        };
+    }
+    /// Cancels the current solve, if there is one.
+    pub fn cancel(&mut self) {
+        self.phase = TimerPhase::Idle;
+    }
+
+    /// Starts the timer if the solver has made a twist or inspection has run
+    /// out. This should be called once per frame.
+    pub fn update(&mut self, puzzle: &PuzzleController) {
+        if let TimerPhase::Inspection { start } = self.phase {
+            let now = Instant::now();
+            if puzzle.twist_count(TwistMetric::Stm) > 0 {
+                self.phase = TimerPhase::Running { start: now };
+            } else if now - start >= INSPECTION_DURATION {
+                self.phase = TimerPhase::Running {
+                    start: start + INSPECTION_DURATION,
+                };
+            }
+        }
+    }
+    /// Stops the timer and records the solve. Returns the solve time, or
+    /// `None` if the timer was not running.
+    pub fn stop(&mut self, twist_count: usize, log: String) -> Option<Duration> {
+        match self.phase {
+            TimerPhase::Running { start } => {
+                let duration = Instant::now() - start;
+                self.phase = TimerPhase::Stopped { duration };
+                self.solves.push(SolveRecord {
+                    duration,
+                    twist_count,
+                    log,
+                });
+                Some(duration)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether the displayed time changes every frame.
+    pub fn is_ticking(&self) -> bool {
+        matches!(
+            self.phase,
+            TimerPhase::Inspection { .. } | TimerPhase::Running { .. },
+        )
+    }
+    /// Returns the remaining inspection time, if inspecting.
+    pub fn inspection_remaining(&self) -> Option<Duration> {
+        match self.phase {
+            TimerPhase::Inspection { start } => {
+                Some(INSPECTION_DURATION.saturating_sub(Instant::now() - start))
+            }
+            _ => None,
+        }
+    }
+    /// Returns the time of the current or most recent solve.
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self.phase {
+            TimerPhase::Idle | TimerPhase::Inspection { .. } => None,
+            TimerPhase::Running { start } => Some(Instant::now() - start),
+            TimerPhase::Stopped { duration } => Some(duration),
+        }
+    }
+
+    /// Returns the average of the last 5 solves.
+    pub fn ao5(&self) -> Option<Duration> {
+        self.average_of(5)
+    }
+    /// Returns the average of the last 12 solves.
+    pub fn ao12(&self) -> Option<Duration> {
+        self.average_of(12)
+    }
+    /// Returns the average of the last `n` solves, excluding the best and worst
+    /// time, or `None` if there are fewer than `n` solves. `n` must be at least
+    /// 3.
+    pub fn average_of(&self, n: usize) -> Option<Duration> {
+        if n < 3 || self.solves.len() < n {
+            return None;
+        }
+        let mut times: Vec<Duration> = self.solves[self.solves.len() - n..]
+            .iter()
+            .map(|solve| solve.duration)
+            .collect();
+        times.sort();
+        let counted = &times[1..n - 1];
+        Some(counted.iter().sum::<Duration>() / counted.len() as u32)
+    }
+}
+
+/// Formats a duration as `m:ss.cc` or `s.cc`.
+pub fn format_duration(duration: Duration) -> String {
+    let centis = duration.as_millis() / 10;
+    let (minutes, centis) = (centis / 6000, centis % 6000);
+    if minutes > 0 {
+        format!("{}:{:02}.{:02}", minutes, centis / 100, centis % 100)
+    } else {
+        format!("{}.{:02}", centis / 100, centis % 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer_with_times(secs: &[u64]) -> SolveTimer {
+        SolveTimer {
+            phase: TimerPhase::Idle,
+            solves: secs
+                .iter()
+                .map(|&s| SolveRecord {
+                    duration: Duration::from_secs(s),
+                    twist_count: 0,
+                    log: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_solve_timer_averages() {
+        let timer = timer_with_times(&[100, 10, 20, 30, 40, 1]);
+        // Last five are 10, 20, 30, 40, 1; drop 1 and 40.
+        assert_eq!(timer.ao5(), Some(Duration::from_secs(20)));
+        assert_eq!(timer.ao12(), None);
+
+        assert_eq!(format_duration(Duration::from_millis(9_876)), "9.87");
+        assert_eq!(format_duration(Duration::from_millis(83_050)), "1:23.05");
+    }
+}
@@ -1,4 +1,4 @@
-use cgmath::Point3;
+use cgmath::{InnerSpace, Point3, Quaternion, Vector3};
 use std::ops::{Add, Mul};
 
 pub const INVALID_STR: &str = "<invalid>";
@@ -76,6 +76,39 @@ pub fn min_and_max_bound(verts: &[Point3<f32>]) -> (Point3<f32>, Point3<f32>) {
     (min_bound, max_bound)
 }
 
+/// Axis-aligned bounding box in `N` dimensions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb<const N: usize> {
+    pub min: [f32; N],
+    pub max: [f32; N],
+}
+impl<const N: usize> Aabb<N> {
+    /// Returns the smallest box containing every point, or `None` if there are
+    /// no points.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; N]>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|p| Self { min: p, max: p })
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Returns whether a point is inside the box, including its boundary.
+    pub fn contains(&self, point: [f32; N]) -> bool {
+        (0..N).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+    /// Returns whether two boxes overlap, including touching at the boundary.
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..N).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: std::array::from_fn(|i| f32::min(self.min[i], other.min[i])),
+            max: std::array::from_fn(|i| f32::max(self.max[i], other.max[i])),
+        }
+    }
+}
+
 pub fn wrap_words<S: AsRef<str>>(words: impl Iterator<Item = S>) -> String {
     const WORD_WRAP_WIDTH: usize = 70;
     let mut ret = String::new();
@@ -106,3 +139,112 @@ where
 {
     a * (1.0 - t) + b * t
 }
+
+/// Angle (in radians) below which spherical interpolation falls back to
+/// linear interpolation, to avoid dividing by a tiny sine.
+const SLERP_EPSILON: f32 = 0.0001;
+
+/// Spherically interpolates between two unit quaternions along the shortest
+/// path. Returns `a` at `t = 0` and a quaternion representing the same
+/// rotation as `b` at `t = 1`, which is `-b` if `a` and `b` are in opposite
+/// hemispheres.
+pub fn slerp(a: Quaternion<f32>, b: Quaternion<f32>, t: f32) -> Quaternion<f32> {
+    let b = if a.dot(b) < 0.0 { -b } else { b };
+    let angle = a.dot(b).min(1.0).acos();
+    if angle < SLERP_EPSILON {
+        return mix(a, b, t).normalize();
+    }
+    (a * ((1.0 - t) * angle).sin() + b * (t * angle).sin()) / angle.sin()
+}
+
+/// Spherically interpolates between two unit vectors. Returns `a` at `t = 0`
+/// and `b` at `t = 1`. If `a` and `b` are antiparallel, every great circle
+/// between them is equally short, so this picks an arbitrary one.
+#[allow(unused)] // Not used by any animation yet.
+pub fn vector_slerp(a: Vector3<f32>, b: Vector3<f32>, t: f32) -> Vector3<f32> {
+    let angle = a.dot(b).clamp(-1.0, 1.0).acos();
+    if angle < SLERP_EPSILON {
+        return mix(a, b, t).normalize();
+    }
+    if std::f32::consts::PI - angle < SLERP_EPSILON {
+        // Rotate through any direction perpendicular to `a`.
+        let mut perpendicular = a.cross(Vector3::unit_x());
+        if perpendicular.magnitude2() < SLERP_EPSILON {
+            perpendicular = a.cross(Vector3::unit_y());
+        }
+        let perpendicular = perpendicular.normalize();
+        return a * (t * angle).cos() + perpendicular * (t * angle).sin();
+    }
+    (a * ((1.0 - t) * angle).sin() + b * (t * angle).sin()) / angle.sin()
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Rotation3};
+
+    use super::*;
+
+    const EPSILON: f32 = 0.00001;
+
+    #[test]
+    fn test_slerp() {
+        let a = Quaternion::from_angle_z(Deg(0.0));
+        let b = Quaternion::from_angle_z(Deg(90.0));
+        let halfway = Quaternion::from_angle_z(Deg(45.0));
+        assert!((slerp(a, b, 0.0) - a).magnitude() < EPSILON);
+        assert!((slerp(a, b, 1.0) - b).magnitude() < EPSILON);
+        assert!((slerp(a, b, 0.5) - halfway).magnitude() < EPSILON);
+
+        // `-b` is the same rotation as `b`; the shortest path is still 45
+        // degrees at the midpoint.
+        assert!((slerp(a, -b, 0.5) - halfway).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn test_aabb() {
+        assert_eq!(Aabb::<2>::from_points([]), None);
+
+        let a = Aabb::from_points([[0.0, 1.0], [2.0, -1.0], [1.0, 0.0]]).unwrap();
+        assert_eq!(a.min, [0.0, -1.0]);
+        assert_eq!(a.max, [2.0, 1.0]);
+        assert!(a.contains([2.0, 0.5]));
+        assert!(!a.contains([2.5, 0.5]));
+
+        let b = Aabb {
+            min: [2.0, 0.0],
+            max: [3.0, 3.0],
+        };
+        let c = Aabb {
+            min: [2.5, 0.0],
+            max: [3.0, 3.0],
+        };
+        assert!(a.intersects(&b) && b.intersects(&a));
+        assert!(!a.intersects(&c) && !c.intersects(&a));
+        assert_eq!(
+            a.union(&c),
+            Aabb {
+                min: [0.0, -1.0],
+                max: [3.0, 3.0]
+            }
+        );
+
+        let a = Aabb::from_points([[0.0; 4], [1.0; 4]]).unwrap();
+        assert!(a.contains([0.5; 4]));
+        assert!(!a.contains([0.5, 0.5, 0.5, 1.5]));
+    }
+
+    #[test]
+    fn test_vector_slerp() {
+        let x = Vector3::unit_x();
+        let y = Vector3::unit_y();
+        let halfway = Vector3::new(1.0, 1.0, 0.0).normalize();
+        assert!((vector_slerp(x, y, 0.0) - x).magnitude() < EPSILON);
+        assert!((vector_slerp(x, y, 1.0) - y).magnitude() < EPSILON);
+        assert!((vector_slerp(x, y, 0.5) - halfway).magnitude() < EPSILON);
+
+        let antiparallel_halfway = vector_slerp(x, -x, 0.5);
+        assert!((antiparallel_halfway.magnitude() - 1.0).abs() < EPSILON);
+        assert!(antiparallel_halfway.dot(x).abs() < EPSILON);
+        assert!((vector_slerp(x, -x, 1.0) + x).magnitude() < EPSILON);
+    }
+}